@@ -0,0 +1,100 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+//! Compares storing values by serializing them into a temporary buffer which LMDB
+//! then copies (the backend's plain `put`), against `SingleStore::put`, which
+//! serializes directly into space reserved in the memory map.
+//!
+//! Run with `cargo bench --features lmdb --bench put`.
+
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use tempfile::Builder;
+
+use rkv::{
+    backend::{
+        BackendEnvironment, BackendEnvironmentBuilder, BackendFlags, BackendRwTransaction, Lmdb,
+        LmdbDatabaseFlags, LmdbEnvironment, LmdbWriteFlags,
+    },
+    Rkv, StoreOptions, Value,
+};
+
+const MAP_SIZE: usize = 1024 * 1024 * 1024;
+const ITERATIONS: usize = 5;
+
+fn bench_copy(root: &Path, count: usize, blob: &[u8]) -> Duration {
+    let mut builder = Lmdb::new();
+    builder.set_map_size(MAP_SIZE).set_max_dbs(1);
+    let env: LmdbEnvironment = builder.open(root).expect("opened");
+    let db = env
+        .create_db(Some("store"), LmdbDatabaseFlags::empty())
+        .expect("created");
+
+    let start = Instant::now();
+    let mut txn = env.begin_rw_txn().expect("txn");
+    for i in 0..count {
+        let key = format!("key{i:08}");
+        let bytes = Value::Blob(blob).to_bytes().expect("serialized");
+        txn.put(&db, key.as_bytes(), &bytes, LmdbWriteFlags::empty())
+            .expect("put");
+    }
+    txn.commit().expect("committed");
+    start.elapsed()
+}
+
+fn bench_reserve(root: &Path, count: usize, blob: &[u8]) -> Duration {
+    let mut builder = Rkv::environment_builder::<Lmdb>();
+    builder.set_map_size(MAP_SIZE).set_max_dbs(1);
+    let k = Rkv::from_builder(root, builder).expect("rkv");
+    let store = k
+        .open_single("store", StoreOptions::create())
+        .expect("opened");
+
+    let start = Instant::now();
+    let mut writer = k.write().expect("writer");
+    for i in 0..count {
+        let key = format!("key{i:08}");
+        store
+            .put(&mut writer, key, &Value::Blob(blob))
+            .expect("put");
+    }
+    writer.commit().expect("committed");
+    start.elapsed()
+}
+
+fn run(name: &str, f: impl Fn(&Path) -> Duration) {
+    let mut total = Duration::default();
+    for _ in 0..ITERATIONS {
+        let root = Builder::new()
+            .prefix("bench_put")
+            .tempdir()
+            .expect("tempdir");
+        fs::create_dir_all(root.path()).expect("dir created");
+        total += f(root.path());
+    }
+    println!("{:<24} {:>10.3?} per run", name, total / ITERATIONS as u32);
+}
+
+fn main() {
+    for &(count, size) in &[(100_000, 16), (20_000, 1024), (1_000, 64 * 1024)] {
+        let blob = vec![0xab; size];
+        println!("{count} values of {size} bytes:");
+        run("  copy (to_bytes + put)", |root| {
+            bench_copy(root, count, &blob)
+        });
+        run("  reserve (SingleStore)", |root| {
+            bench_reserve(root, count, &blob)
+        });
+    }
+}
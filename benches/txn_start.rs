@@ -0,0 +1,69 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+//! Measures how long beginning a SafeMode transaction takes as the store grows. The
+//! transactions share the databases' maps rather than copying them, so the times
+//! should stay the same across store sizes.
+//!
+//! Run with `cargo bench --bench txn_start`.
+
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use tempfile::Builder;
+
+use rkv::{
+    backend::{SafeMode, SafeModeEnvironment},
+    Rkv, StoreOptions, Value,
+};
+
+const ITERATIONS: u32 = 10_000;
+
+fn fill(k: &Rkv<SafeModeEnvironment>, count: usize) {
+    let store = k
+        .open_single("store", StoreOptions::create())
+        .expect("opened");
+    let mut writer = k.write().expect("writer");
+    for i in 0..count {
+        let key = format!("key{i:08}");
+        store
+            .put(&mut writer, key, &Value::U64(i as u64))
+            .expect("put");
+    }
+    writer.commit().expect("committed");
+}
+
+fn time(f: impl Fn()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    for &count in &[1_000, 10_000, 100_000, 1_000_000] {
+        let root = Builder::new()
+            .prefix("bench_txn_start")
+            .tempdir()
+            .expect("tempdir");
+        fs::create_dir_all(root.path()).expect("dir created");
+        let k = Rkv::new::<SafeMode>(root.path()).expect("rkv");
+        fill(&k, count);
+
+        println!("{count} values:");
+        let read = time(|| k.read().expect("reader").abort());
+        println!("  {:<22} {:>10.3?} per transaction", "read", read);
+        let write = time(|| k.write().expect("writer").abort());
+        println!("  {:<22} {:>10.3?} per transaction", "write", write);
+    }
+}
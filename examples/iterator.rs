@@ -61,7 +61,7 @@ fn populate_store(
     store: SingleStore<SafeModeDatabase>,
 ) -> Result<(), StoreError> {
     let mut writer = k.write()?;
-    for (country, city) in vec![
+    for (country, city) in [
         ("Canada", Value::Str("Ottawa")),
         ("United States of America", Value::Str("Washington")),
         ("Germany", Value::Str("Berlin")),
@@ -19,8 +19,8 @@ use rkv::{
 type MultiStore = rkv::MultiStore<SafeModeDatabase>;
 type Writer<'w> = rkv::Writer<SafeModeRwTransaction<'w>>;
 
-fn getput<'w, 's>(store: MultiStore, writer: &'w mut Writer, ids: &'s mut Vec<String>) {
-    let keys = vec!["str1", "str2", "str3"];
+fn getput(store: MultiStore, writer: &mut Writer, ids: &mut Vec<String>) {
+    let keys = ["str1", "str2", "str3"];
     // we convert the writer into a cursor so that we can safely read
     for k in keys.iter() {
         // this is a multi-valued database, so get returns an iterator
@@ -39,8 +39,8 @@ fn getput<'w, 's>(store: MultiStore, writer: &'w mut Writer, ids: &'s mut Vec<St
 }
 
 fn delete(store: MultiStore, writer: &mut Writer) {
-    let keys = vec!["str1", "str2", "str3"];
-    let vals = vec!["string uno", "string quatro", "string siete"];
+    let keys = ["str1", "str2", "str3"];
+    let vals = ["string uno", "string quatro", "string siete"];
     // we convert the writer into a cursor so that we can safely read
     for i in 0..keys.len() {
         store
@@ -0,0 +1,225 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+//! Environments whose backend is chosen at runtime, e.g. from configuration, rather
+//! than by the `E` type parameter of `Rkv<E>`. Every type here is an enum with one
+//! variant per backend, which forwards to the corresponding `Rkv` type.
+
+use std::path::Path;
+
+#[cfg(all(feature = "lmdb", any(feature = "db-dup-sort", feature = "db-int-key")))]
+use crate::backend::{BackendDatabaseFlags, DatabaseFlags};
+#[cfg(feature = "lmdb")]
+use crate::backend::{
+    BackendFlags, Lmdb, LmdbDatabase, LmdbDatabaseFlags, LmdbEnvironment, LmdbRoTransaction,
+    LmdbRwTransaction,
+};
+use crate::{
+    backend::{
+        SafeMode, SafeModeDatabase, SafeModeDatabaseFlags, SafeModeEnvironment,
+        SafeModeRoTransaction, SafeModeRwTransaction,
+    },
+    error::StoreError,
+    readwrite::{Reader, Writer},
+    store::{single::SingleStore, Options as StoreOptions},
+    value::Value,
+    Rkv,
+};
+
+type EmptyResult = Result<(), StoreError>;
+
+/// The backends an `AnyRkv` can use.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Backend {
+    SafeMode,
+    #[cfg(feature = "lmdb")]
+    Lmdb,
+}
+
+pub enum AnyRkv {
+    SafeMode(Rkv<SafeModeEnvironment>),
+    #[cfg(feature = "lmdb")]
+    Lmdb(Rkv<LmdbEnvironment>),
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum AnySingleStore {
+    SafeMode(SingleStore<SafeModeDatabase>),
+    #[cfg(feature = "lmdb")]
+    Lmdb(SingleStore<LmdbDatabase>),
+}
+
+pub enum AnyReader<'e> {
+    SafeMode(Reader<SafeModeRoTransaction<'e>>),
+    #[cfg(feature = "lmdb")]
+    Lmdb(Reader<LmdbRoTransaction<'e>>),
+}
+
+pub enum AnyWriter<'e> {
+    SafeMode(Writer<SafeModeRwTransaction<'e>>),
+    #[cfg(feature = "lmdb")]
+    Lmdb(Writer<LmdbRwTransaction<'e>>),
+}
+
+impl AnyRkv {
+    /// Open the environment at `path` with the given backend, like `Rkv::new`.
+    pub fn open(path: &Path, backend: Backend) -> Result<AnyRkv, StoreError> {
+        match backend {
+            Backend::SafeMode => Rkv::new::<SafeMode>(path).map(AnyRkv::SafeMode),
+            #[cfg(feature = "lmdb")]
+            Backend::Lmdb => Rkv::new::<Lmdb>(path).map(AnyRkv::Lmdb),
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self {
+            AnyRkv::SafeMode(_) => Backend::SafeMode,
+            #[cfg(feature = "lmdb")]
+            AnyRkv::Lmdb(_) => Backend::Lmdb,
+        }
+    }
+
+    pub fn get_dbs(&self) -> Result<Vec<Option<String>>, StoreError> {
+        match self {
+            AnyRkv::SafeMode(k) => k.get_dbs(),
+            #[cfg(feature = "lmdb")]
+            AnyRkv::Lmdb(k) => k.get_dbs(),
+        }
+    }
+
+    /// Like `Rkv::open_single`. The options take SafeMode's database flags, since every
+    /// backend supports them.
+    pub fn open_single<'s, T>(
+        &self,
+        name: T,
+        opts: StoreOptions<SafeModeDatabaseFlags>,
+    ) -> Result<AnySingleStore, StoreError>
+    where
+        T: Into<Option<&'s str>>,
+    {
+        match self {
+            AnyRkv::SafeMode(k) => k.open_single(name, opts).map(AnySingleStore::SafeMode),
+            #[cfg(feature = "lmdb")]
+            AnyRkv::Lmdb(k) => {
+                let opts = StoreOptions {
+                    create: opts.create,
+                    flags: lmdb_flags(opts.flags),
+                    utf8_keys: opts.utf8_keys,
+                    changelog: opts.changelog,
+                    default_write_flags: opts.default_write_flags,
+                };
+                k.open_single(name, opts).map(AnySingleStore::Lmdb)
+            }
+        }
+    }
+
+    pub fn read(&self) -> Result<AnyReader<'_>, StoreError> {
+        match self {
+            AnyRkv::SafeMode(k) => k.read().map(AnyReader::SafeMode),
+            #[cfg(feature = "lmdb")]
+            AnyRkv::Lmdb(k) => k.read().map(AnyReader::Lmdb),
+        }
+    }
+
+    pub fn write(&self) -> Result<AnyWriter<'_>, StoreError> {
+        match self {
+            AnyRkv::SafeMode(k) => k.write().map(AnyWriter::SafeMode),
+            #[cfg(feature = "lmdb")]
+            AnyRkv::Lmdb(k) => k.write().map(AnyWriter::Lmdb),
+        }
+    }
+}
+
+#[cfg(feature = "lmdb")]
+// Only the flags enabled by the `db-dup-sort` and `db-int-key` features are used.
+#[allow(unused_mut, unused_variables)]
+fn lmdb_flags(flags: SafeModeDatabaseFlags) -> LmdbDatabaseFlags {
+    let mut lmdb_flags = LmdbDatabaseFlags::empty();
+    #[cfg(feature = "db-dup-sort")]
+    if flags.contains(SafeModeDatabaseFlags::DUP_SORT) {
+        lmdb_flags.set(DatabaseFlags::DUP_SORT, true);
+    }
+    #[cfg(feature = "db-int-key")]
+    if flags.contains(SafeModeDatabaseFlags::INTEGER_KEY) {
+        lmdb_flags.set(DatabaseFlags::INTEGER_KEY, true);
+    }
+    lmdb_flags
+}
+
+/// The store methods take readers and writers of the store's own backend, and fail
+/// with `StoreError::BackendMismatch` otherwise.
+impl AnySingleStore {
+    pub fn get<'r, K>(&self, reader: &'r AnyReader, k: K) -> Result<Option<Value<'r>>, StoreError>
+    where
+        K: AsRef<[u8]>,
+    {
+        match (self, reader) {
+            (AnySingleStore::SafeMode(s), AnyReader::SafeMode(r)) => s.get(r, k),
+            #[cfg(feature = "lmdb")]
+            (AnySingleStore::Lmdb(s), AnyReader::Lmdb(r)) => s.get(r, k),
+            #[cfg(feature = "lmdb")]
+            _ => Err(StoreError::BackendMismatch),
+        }
+    }
+
+    pub fn put<K>(&self, writer: &mut AnyWriter, k: K, v: &Value) -> EmptyResult
+    where
+        K: AsRef<[u8]>,
+    {
+        match (self, writer) {
+            (AnySingleStore::SafeMode(s), AnyWriter::SafeMode(w)) => s.put(w, k, v),
+            #[cfg(feature = "lmdb")]
+            (AnySingleStore::Lmdb(s), AnyWriter::Lmdb(w)) => s.put(w, k, v),
+            #[cfg(feature = "lmdb")]
+            _ => Err(StoreError::BackendMismatch),
+        }
+    }
+
+    pub fn delete<K>(&self, writer: &mut AnyWriter, k: K) -> EmptyResult
+    where
+        K: AsRef<[u8]>,
+    {
+        match (self, writer) {
+            (AnySingleStore::SafeMode(s), AnyWriter::SafeMode(w)) => s.delete(w, k),
+            #[cfg(feature = "lmdb")]
+            (AnySingleStore::Lmdb(s), AnyWriter::Lmdb(w)) => s.delete(w, k),
+            #[cfg(feature = "lmdb")]
+            _ => Err(StoreError::BackendMismatch),
+        }
+    }
+}
+
+impl<'e> AnyReader<'e> {
+    pub fn abort(self) {
+        match self {
+            AnyReader::SafeMode(r) => r.abort(),
+            #[cfg(feature = "lmdb")]
+            AnyReader::Lmdb(r) => r.abort(),
+        }
+    }
+}
+
+impl<'e> AnyWriter<'e> {
+    pub fn commit(self) -> EmptyResult {
+        match self {
+            AnyWriter::SafeMode(w) => w.commit(),
+            #[cfg(feature = "lmdb")]
+            AnyWriter::Lmdb(w) => w.commit(),
+        }
+    }
+
+    pub fn abort(self) {
+        match self {
+            AnyWriter::SafeMode(w) => w.abort(),
+            #[cfg(feature = "lmdb")]
+            AnyWriter::Lmdb(w) => w.abort(),
+        }
+    }
+}
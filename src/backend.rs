@@ -12,6 +12,7 @@ mod common;
 #[cfg(feature = "lmdb")]
 mod impl_lmdb;
 mod impl_safe;
+mod lock;
 mod traits;
 
 pub use common::*;
@@ -34,7 +35,7 @@ pub use impl_safe::{
     EnvironmentBuilderImpl as SafeMode, EnvironmentFlagsImpl as SafeModeEnvironmentFlags,
     EnvironmentImpl as SafeModeEnvironment, ErrorImpl as SafeModeError, InfoImpl as SafeModeInfo,
     IterImpl as SafeModeIter, RoCursorImpl as SafeModeRoCursor,
-    RoTransactionImpl as SafeModeRoTransaction, RwCursorImpl as SafeModeRwCursor,
-    RwTransactionImpl as SafeModeRwTransaction, StatImpl as SafeModeStat,
-    WriteFlagsImpl as SafeModeWriteFlags,
+    RoSnapshotImpl as SafeModeRoSnapshot, RoTransactionImpl as SafeModeRoTransaction,
+    RwCursorImpl as SafeModeRwCursor, RwTransactionImpl as SafeModeRwTransaction,
+    StatImpl as SafeModeStat, WriteFlagsImpl as SafeModeWriteFlags,
 };
@@ -35,6 +35,7 @@ pub enum DatabaseFlags {
     REVERSE_DUP,
 }
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum WriteFlags {
     NO_OVERWRITE,
     NO_DUP_DATA,
@@ -43,6 +44,77 @@ pub enum WriteFlags {
     APPEND_DUP,
 }
 
+/// Statistics about a single database. Backends that don't organize their data in
+/// pages leave the page-related fields as `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DatabaseStat {
+    /// Number of entries, counting each value of a key with duplicate values separately.
+    pub entries: usize,
+    /// Estimated size of the database in bytes.
+    pub size: usize,
+    pub depth: Option<usize>,
+    pub branch_pages: Option<usize>,
+    pub leaf_pages: Option<usize>,
+    pub overflow_pages: Option<usize>,
+}
+
+/// Information about a whole environment, e.g. for monitoring. Backends that don't
+/// use a memory map or pages leave the related fields as `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EnvInfo {
+    /// Size of the memory map in bytes. For SafeMode, the configured size ceiling.
+    pub map_size: Option<usize>,
+    pub last_pgno: Option<usize>,
+    /// Size of the data in bytes: the used pages, or the serialized databases.
+    pub size: usize,
+    pub num_readers: usize,
+    pub max_readers: Option<usize>,
+    /// Number of databases, as returned by `get_dbs`.
+    pub num_dbs: usize,
+}
+
+/// How many read transactions an environment has open, e.g. to catch leaked readers
+/// before LMDB runs out of reader slots.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReaderInfo {
+    /// Number of open read transactions, in every process using the environment.
+    pub in_use: usize,
+    /// Number of reader slots, or `None` if the backend doesn't limit readers.
+    pub max: Option<usize>,
+}
+
+/// How to move a cursor. Stepping an unpositioned cursor forward or backward moves it
+/// to the first or last entry respectively.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum CursorOp<'k> {
+    First,
+    Last,
+    Next,
+    Prev,
+    /// The first entry whose key is equal to the given one.
+    SeekExact(&'k [u8]),
+    /// The first entry whose key is greater than or equal to the given one.
+    SeekRange(&'k [u8]),
+}
+
+/// How much durability commits trade for speed, e.g. to bulk-load data and only call
+/// `Rkv::sync(true)` once at the end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Durability {
+    /// Every commit is flushed to disk. The default.
+    #[default]
+    Full,
+
+    /// Commits skip flushing LMDB's metadata page, so a system crash may undo the last
+    /// transaction, but not corrupt the database. Same as `Full` in SafeMode.
+    NoMetaSync,
+
+    /// Commits aren't flushed to disk. A system crash may undo the last transactions,
+    /// or, with LMDB, corrupt the database. SafeMode doesn't write anything to disk
+    /// until `Rkv::sync` is called, so even a process exit without syncing loses data.
+    NoSync,
+}
+
 /// Strategy to use when corrupted data is detected while opening a database.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RecoveryStrategy {
@@ -17,6 +17,7 @@ mod error;
 mod flags;
 mod info;
 mod iter;
+mod reader_cache;
 mod stat;
 mod transaction;
 
@@ -10,60 +10,135 @@
 
 use lmdb::Cursor;
 
-use super::IterImpl;
-use crate::backend::traits::BackendRoCursor;
+use super::{error::check_value_size, ErrorImpl, IterImpl};
+use crate::backend::{common::CursorOp, traits::BackendRoCursor};
 
 #[derive(Debug)]
-pub struct RoCursorImpl<'c>(pub(crate) lmdb::RoCursor<'c>);
+pub struct RoCursorImpl<'c>(pub(crate) lmdb::RoCursor<'c>, pub(crate) Option<usize>);
 
 impl<'c> BackendRoCursor<'c> for RoCursorImpl<'c> {
+    type Error = ErrorImpl;
     type Iter = IterImpl<'c, lmdb::RoCursor<'c>>;
 
+    fn seek(&mut self, op: CursorOp<'_>) -> Option<Result<(&'c [u8], &'c [u8]), Self::Error>> {
+        seek(&self.0, op, self.1)
+    }
+
     fn into_iter(self) -> Self::Iter {
         // We call RoCursor.iter() instead of RoCursor.iter_start() because
         // the latter panics when there are no items in the store, whereas the
         // former returns an iterator that yields no items. And since we create
         // the Cursor and don't change its position, we can be sure that a call
         // to Cursor.iter() will start at the beginning.
-        IterImpl::new(self.0, lmdb::RoCursor::iter)
+        IterImpl::new(self.0, self.1, lmdb::RoCursor::iter)
     }
 
     fn into_iter_from<K>(self, key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c,
     {
-        IterImpl::new(self.0, |cursor| cursor.iter_from(key))
+        IterImpl::new(self.0, self.1, |cursor| cursor.iter_from(key))
+    }
+
+    fn into_iter_after<K>(self, key: K) -> Self::Iter
+    where
+        K: AsRef<[u8]> + 'c,
+    {
+        IterImpl::new(self.0, self.1, |cursor| iter_after(cursor, key))
     }
 
     fn into_iter_dup_of<K>(self, key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c,
     {
-        IterImpl::new(self.0, |cursor| cursor.iter_dup_of(key))
+        IterImpl::new(self.0, self.1, |cursor| cursor.iter_dup_of(key))
     }
 }
 
 #[derive(Debug)]
-pub struct RwCursorImpl<'c>(pub(crate) lmdb::RwCursor<'c>);
+pub struct RwCursorImpl<'c>(pub(crate) lmdb::RwCursor<'c>, pub(crate) Option<usize>);
 
 impl<'c> BackendRoCursor<'c> for RwCursorImpl<'c> {
+    type Error = ErrorImpl;
     type Iter = IterImpl<'c, lmdb::RwCursor<'c>>;
 
+    fn seek(&mut self, op: CursorOp<'_>) -> Option<Result<(&'c [u8], &'c [u8]), Self::Error>> {
+        seek(&self.0, op, self.1)
+    }
+
     fn into_iter(self) -> Self::Iter {
-        IterImpl::new(self.0, lmdb::RwCursor::iter)
+        IterImpl::new(self.0, self.1, lmdb::RwCursor::iter)
     }
 
     fn into_iter_from<K>(self, key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c,
     {
-        IterImpl::new(self.0, |cursor| cursor.iter_from(key))
+        IterImpl::new(self.0, self.1, |cursor| cursor.iter_from(key))
+    }
+
+    fn into_iter_after<K>(self, key: K) -> Self::Iter
+    where
+        K: AsRef<[u8]> + 'c,
+    {
+        IterImpl::new(self.0, self.1, |cursor| iter_after(cursor, key))
     }
 
     fn into_iter_dup_of<K>(self, key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c,
     {
-        IterImpl::new(self.0, |cursor| cursor.iter_dup_of(key))
+        IterImpl::new(self.0, self.1, |cursor| cursor.iter_dup_of(key))
+    }
+}
+
+/// Iterate from the first key strictly greater than `key`. In `DUP_SORT` databases, this
+/// skips all the values of `key` itself.
+fn iter_after<'c, C, K>(cursor: &mut C, key: K) -> lmdb::Iter<'c>
+where
+    C: Cursor<'c>,
+    K: AsRef<[u8]>,
+{
+    match cursor.get(Some(key.as_ref()), None, lmdb_sys::MDB_SET_RANGE) {
+        Ok((Some(k), _)) if k == key.as_ref() => (),
+        // Without an entry for `key`, this is the same as starting from it.
+        Ok(_) | Err(lmdb::Error::NotFound) => return cursor.iter_from(key),
+        Err(err) => return lmdb::Iter::Err(err),
+    }
+    match cursor.get(None, None, lmdb_sys::MDB_NEXT_NODUP) {
+        Ok((Some(next), _)) => cursor.iter_from(next),
+        Ok((None, _)) => lmdb::Iter::Err(lmdb::Error::Corrupted),
+        // `key` is the last key, and the cursor stays at the end, so nothing is left.
+        Err(lmdb::Error::NotFound) => cursor.iter(),
+        Err(err) => lmdb::Iter::Err(err),
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn seek<'c, C>(
+    cursor: &C,
+    op: CursorOp<'_>,
+    max_value_size: Option<usize>,
+) -> Option<Result<(&'c [u8], &'c [u8]), ErrorImpl>>
+where
+    C: Cursor<'c>,
+{
+    let result = match op {
+        CursorOp::First => cursor.get(None, None, lmdb_sys::MDB_FIRST),
+        CursorOp::Last => cursor.get(None, None, lmdb_sys::MDB_LAST),
+        CursorOp::Next => cursor.get(None, None, lmdb_sys::MDB_NEXT),
+        CursorOp::Prev => cursor.get(None, None, lmdb_sys::MDB_PREV),
+        CursorOp::SeekExact(key) => cursor.get(Some(key), None, lmdb_sys::MDB_SET_KEY),
+        CursorOp::SeekRange(key) => cursor.get(Some(key), None, lmdb_sys::MDB_SET_RANGE),
+    };
+    match result {
+        // All of the above operations return the key of the entry they position the
+        // cursor at, so it's never missing.
+        Ok((Some(key), value)) => {
+            Some(check_value_size(value, max_value_size).map(|value| (key, value)))
+        }
+        Ok((None, _)) => Some(Err(ErrorImpl::LmdbError(lmdb::Error::Corrupted))),
+        Err(lmdb::Error::NotFound) => None,
+        Err(err) => Some(Err(ErrorImpl::LmdbError(err))),
     }
 }
@@ -8,9 +8,53 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
+use std::{
+    collections::HashMap,
+    sync::{PoisonError, RwLock},
+};
+
+use super::DatabaseFlagsImpl;
 use crate::backend::traits::BackendDatabase;
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
-pub struct DatabaseImpl(pub(crate) lmdb::Database);
+pub struct DatabaseImpl {
+    pub(crate) db: lmdb::Database,
+    // The id of the environment the database was opened in, since LMDB's handles are
+    // only indices, which are just as valid in any other environment.
+    pub(crate) env_id: usize,
+    // The generation of the handle when the database was opened, see `Generations`.
+    pub(crate) generation: usize,
+}
+
+impl BackendDatabase for DatabaseImpl {
+    type Flags = DatabaseFlagsImpl;
+}
+
+/// The generation of each LMDB handle of an environment, bumped whenever its database
+/// is dropped, since LMDB then hands it out again to the next database it opens. A
+/// `DatabaseImpl` opened in an older generation would use that other database.
+#[derive(Debug, Default)]
+pub(crate) struct Generations(RwLock<HashMap<lmdb_sys::MDB_dbi, usize>>);
+
+impl Generations {
+    pub(crate) fn get(&self, db: lmdb::Database) -> usize {
+        let generations = self.0.read().unwrap_or_else(PoisonError::into_inner);
+        generations.get(&db.dbi()).copied().unwrap_or_default()
+    }
 
-impl BackendDatabase for DatabaseImpl {}
+    /// Run `drop`, which drops the database of `db`, and bump the generation of `db` if
+    /// it succeeds. LMDB frees the handle right away, even before the transaction ends,
+    /// but no handle can be opened meanwhile, so `db` isn't handed out again before the
+    /// generation is bumped.
+    pub(crate) fn bump_after<T, E, F>(&self, db: lmdb::Database, drop: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        // The map is only ever updated while the lock is held, so poisoning can be
+        // ignored.
+        let mut generations = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        let result = drop()?;
+        *generations.entry(db.dbi()).or_default() += 1;
+        Ok(result)
+    }
+}
@@ -9,29 +9,62 @@
 // specific language governing permissions and limitations under the License.
 
 use std::{
+    ffi::{CStr, CString},
     fs,
+    mem::size_of,
+    os::raw::{c_char, c_int, c_void},
     path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
 };
 
-use lmdb::Error as LmdbError;
+use lmdb::{Cursor, Error as LmdbError, Transaction, WriteFlags};
 
 use super::{
-    DatabaseFlagsImpl, DatabaseImpl, EnvironmentFlagsImpl, ErrorImpl, InfoImpl, RoTransactionImpl,
-    RwTransactionImpl, StatImpl,
+    database::Generations, reader_cache::ReaderCache, DatabaseFlagsImpl, DatabaseImpl,
+    EnvironmentFlagsImpl, ErrorImpl, InfoImpl, RoTransactionImpl, RwTransactionImpl, StatImpl,
 };
-use crate::backend::common::RecoveryStrategy;
+use crate::backend::common::{Durability, EnvInfo, ReaderInfo, RecoveryStrategy};
+use crate::backend::lock::{ReaderCount, WriterGuard, WriterLock};
 use crate::backend::traits::{
     BackendEnvironment, BackendEnvironmentBuilder, BackendInfo, BackendIter, BackendRoCursor,
     BackendRoCursorTransaction, BackendStat,
 };
 
+const DEFAULT_DB_FILENAME: &str = "data.mdb";
+
+/// The size of the record that the default database holds for each named database, an
+/// `MDB_db` struct of a `u32`, two `u16`s and five `size_t`s.
+const DB_RECORD_SIZE: usize = 8 + 5 * size_of::<usize>();
+
+static NEXT_ENV_ID: AtomicUsize = AtomicUsize::new(0);
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct EnvironmentBuilderImpl {
     builder: lmdb::EnvironmentBuilder,
+    flags: EnvironmentFlagsImpl,
     env_path_type: EnvironmentPathType,
     env_lock_type: EnvironmentLockType,
     env_db_type: EnvironmentDefaultDbType,
+    env_access_type: EnvironmentAccessType,
     make_dir_if_needed: bool,
+    auto_resize: bool,
+    warn_on_implicit_abort: bool,
+    reader_cache_size: usize,
+    max_value_size: Option<usize>,
+}
+
+impl EnvironmentBuilderImpl {
+    /// Keep up to `size` read transactions around when their readers end, and renew
+    /// them for later reads in the same thread, saving the cost of beginning new ones.
+    /// Renewed transactions see the latest committed state, just like new ones.
+    ///
+    /// Like the readers that use them, cached transactions hold on to their thread's
+    /// reader slot, so `size` shouldn't exceed the number of threads that read.
+    pub fn set_reader_cache_size(&mut self, size: usize) -> &mut Self {
+        self.reader_cache_size = size;
+        self
+    }
 }
 
 impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
@@ -42,10 +75,16 @@ impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
     fn new() -> EnvironmentBuilderImpl {
         EnvironmentBuilderImpl {
             builder: lmdb::Environment::new(),
+            flags: EnvironmentFlagsImpl(lmdb::EnvironmentFlags::empty()),
             env_path_type: EnvironmentPathType::SubDir,
             env_lock_type: EnvironmentLockType::Lockfile,
             env_db_type: EnvironmentDefaultDbType::SingleDatabase,
+            env_access_type: EnvironmentAccessType::ReadWrite,
             make_dir_if_needed: false,
+            auto_resize: false,
+            warn_on_implicit_abort: false,
+            reader_cache_size: 0,
+            max_value_size: None,
         }
     }
 
@@ -60,10 +99,47 @@ impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
         if flags.0 == lmdb::EnvironmentFlags::NO_LOCK {
             self.env_lock_type = EnvironmentLockType::NoLockfile;
         }
+        self.env_access_type = if flags.0.contains(lmdb::EnvironmentFlags::READ_ONLY) {
+            EnvironmentAccessType::ReadOnly
+        } else {
+            EnvironmentAccessType::ReadWrite
+        };
         self.builder.set_flags(flags.0);
+        self.flags = flags;
         self
     }
 
+    fn set_read_only(&mut self, read_only: bool) -> &mut Self {
+        let mut flags = self.flags;
+        flags.0.set(lmdb::EnvironmentFlags::READ_ONLY, read_only);
+        self.set_flags(flags)
+    }
+
+    fn set_durability(&mut self, durability: Durability) -> &mut Self {
+        let mut flags = self.flags;
+        flags.0.set(
+            lmdb::EnvironmentFlags::NO_META_SYNC,
+            durability == Durability::NoMetaSync,
+        );
+        flags.0.set(
+            lmdb::EnvironmentFlags::NO_SYNC,
+            durability == Durability::NoSync,
+        );
+        self.set_flags(flags)
+    }
+
+    fn set_writemap(&mut self, writemap: bool) -> &mut Self {
+        let mut flags = self.flags;
+        flags.0.set(lmdb::EnvironmentFlags::WRITE_MAP, writemap);
+        self.set_flags(flags)
+    }
+
+    fn set_map_async(&mut self, map_async: bool) -> &mut Self {
+        let mut flags = self.flags;
+        flags.0.set(lmdb::EnvironmentFlags::MAP_ASYNC, map_async);
+        self.set_flags(flags)
+    }
+
     fn set_max_readers(&mut self, max_readers: u32) -> &mut Self {
         self.builder.set_max_readers(max_readers);
         self
@@ -87,6 +163,11 @@ impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
         self
     }
 
+    fn set_auto_resize(&mut self, auto_resize: bool) -> &mut Self {
+        self.auto_resize = auto_resize;
+        self
+    }
+
     /// **UNIMPLEMENTED.** Will panic at runtime.
     fn set_corruption_recovery_strategy(&mut self, _strategy: RecoveryStrategy) -> &mut Self {
         // Unfortunately, when opening a database, LMDB doesn't handle all the ways it could have
@@ -94,6 +175,23 @@ impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
         unimplemented!();
     }
 
+    fn set_warn_on_implicit_abort(&mut self, warn: bool) -> &mut Self {
+        self.warn_on_implicit_abort = warn;
+        self
+    }
+
+    fn set_max_value_size(&mut self, max_value_size: usize) -> &mut Self {
+        self.max_value_size = Some(max_value_size);
+        self
+    }
+
+    fn env_exists(&self, path: &Path) -> bool {
+        match self.env_path_type {
+            EnvironmentPathType::NoSubDir => path.is_file(),
+            EnvironmentPathType::SubDir => path.join(DEFAULT_DB_FILENAME).is_file(),
+        }
+    }
+
     fn open(&self, path: &Path) -> Result<Self::Environment, Self::Error> {
         match self.env_path_type {
             EnvironmentPathType::NoSubDir => {
@@ -120,10 +218,30 @@ impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
                     self.env_path_type,
                     self.env_lock_type,
                     self.env_db_type,
+                    self.env_access_type,
+                    self.auto_resize,
                     lmdbenv,
                 )
             })
+            .map(|mut env| {
+                env.warn_on_implicit_abort = self.warn_on_implicit_abort;
+                env.reader_cache = ReaderCache::new(self.reader_cache_size);
+                env.max_value_size = self.max_value_size;
+                env
+            })
+    }
+}
+
+/// Count the lines of `mdb_reader_list` that list an open transaction. LMDB only lists
+/// its reader table as text: a header, then one line per reader slot held by a thread
+/// or transaction, ending with the transaction id, or `-` if it has none open.
+unsafe extern "C" fn count_open_readers(msg: *const c_char, ctx: *mut c_void) -> c_int {
+    let line = CStr::from_ptr(msg).to_string_lossy();
+    let txnid = line.split_whitespace().last().unwrap_or_default();
+    if txnid.parse::<u64>().is_ok() {
+        *(ctx as *mut usize) += 1;
     }
+    0
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -144,12 +262,35 @@ pub enum EnvironmentDefaultDbType {
     MultipleNamedDatabases,
 }
 
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum EnvironmentAccessType {
+    ReadWrite,
+    ReadOnly,
+}
+
 #[derive(Debug)]
 pub struct EnvironmentImpl {
     path: PathBuf,
     env_path_type: EnvironmentPathType,
     env_lock_type: EnvironmentLockType,
     env_db_type: EnvironmentDefaultDbType,
+    env_access_type: EnvironmentAccessType,
+    auto_resize: bool,
+    warn_on_implicit_abort: bool,
+    // LMDB can only wait for its own write lock, so writers also take this one, which
+    // can be tried. It only covers this process. Every write transaction takes it,
+    // including those that create, rename and drop databases.
+    writer_lock: WriterLock,
+    // Tags databases and transactions, to catch stores used with another environment.
+    id: usize,
+    generations: Generations,
+    // Counts the active read transactions, since LMDB can only grow the map while
+    // there are none.
+    readers: ReaderCount,
+    max_key_size: usize,
+    max_value_size: Option<usize>,
+    // Must be dropped before the environment is closed, so it's declared before it.
+    reader_cache: ReaderCache,
     lmdbenv: lmdb::Environment,
 }
 
@@ -159,6 +300,8 @@ impl EnvironmentImpl {
         env_path_type: EnvironmentPathType,
         env_lock_type: EnvironmentLockType,
         env_db_type: EnvironmentDefaultDbType,
+        env_access_type: EnvironmentAccessType,
+        auto_resize: bool,
         lmdbenv: lmdb::Environment,
     ) -> Result<EnvironmentImpl, ErrorImpl> {
         Ok(EnvironmentImpl {
@@ -166,9 +309,97 @@ impl EnvironmentImpl {
             env_path_type,
             env_lock_type,
             env_db_type,
+            env_access_type,
+            auto_resize,
+            warn_on_implicit_abort: false,
+            writer_lock: WriterLock::default(),
+            id: NEXT_ENV_ID.fetch_add(1, Ordering::Relaxed),
+            generations: Generations::default(),
+            readers: ReaderCount::default(),
+            // A compile-time constant of LMDB, 511 by default.
+            max_key_size: unsafe { lmdb_sys::mdb_env_get_maxkeysize(lmdbenv.env()) } as usize,
+            max_value_size: None,
+            reader_cache: ReaderCache::default(),
             lmdbenv,
         })
     }
+
+    fn begin_rw_txn_with<'e>(
+        &'e self,
+        guard: WriterGuard<'e>,
+    ) -> Result<RwTransactionImpl<'e>, ErrorImpl> {
+        let auto_resize_env = Some((&self.lmdbenv, &self.readers)).filter(|_| self.auto_resize);
+        self.lmdbenv
+            .begin_rw_txn()
+            .map(|txn| {
+                RwTransactionImpl::new(
+                    txn,
+                    self.id,
+                    &self.generations,
+                    self.max_key_size,
+                    self.max_value_size,
+                    auto_resize_env,
+                    guard,
+                )
+            })
+            .map_err(ErrorImpl::LmdbError)
+    }
+
+    // The caller must hold `writer_lock`, since both creating the database and checking
+    // its flags use write transactions.
+    fn create_db_locked(
+        &self,
+        name: Option<&str>,
+        flags: DatabaseFlagsImpl,
+    ) -> Result<DatabaseImpl, ErrorImpl> {
+        let db = self
+            .lmdbenv
+            .create_db(name, flags.0)
+            .map_err(ErrorImpl::LmdbError)?;
+        // LMDB keeps the flags of existing databases, so check them. This uses a write
+        // transaction, since a read transaction would hold on to a reader slot.
+        let txn = self.lmdbenv.begin_rw_txn().map_err(ErrorImpl::LmdbError)?;
+        let existing = txn.db_flags(db).map_err(ErrorImpl::LmdbError)?;
+        txn.abort();
+        if existing != flags.0 {
+            return Err(ErrorImpl::LmdbError(LmdbError::Incompatible));
+        }
+        Ok(self.database(db))
+    }
+
+    // LMDB opens databases in read transactions of its own, which count too.
+    fn open_lmdb_db(&self, name: Option<&str>) -> Result<lmdb::Database, LmdbError> {
+        let _reader = self.readers.enter();
+        self.lmdbenv.open_db(name)
+    }
+
+    /// Open a database that `rename_db` or `drop_db` handles along with another one, or
+    /// return `None` if it doesn't exist.
+    fn open_companion_db(&self, name: &str) -> Result<Option<lmdb::Database>, ErrorImpl> {
+        // Look the name up first, since opening a missing database fails with `DbsFull`
+        // rather than `NotFound` once every slot is taken.
+        {
+            let _reader = self.readers.enter();
+            let main = self.lmdbenv.open_db(None).map_err(ErrorImpl::LmdbError)?;
+            let txn = self.lmdbenv.begin_ro_txn().map_err(ErrorImpl::LmdbError)?;
+            match txn.get(main, &name) {
+                Ok(_) => {}
+                Err(LmdbError::NotFound) => return Ok(None),
+                Err(e) => return Err(ErrorImpl::LmdbError(e)),
+            }
+        }
+        self.open_lmdb_db(Some(name))
+            .map(Some)
+            .map_err(ErrorImpl::LmdbError)
+    }
+
+    fn database(&self, db: lmdb::Database) -> DatabaseImpl {
+        DatabaseImpl {
+            db,
+            env_id: self.id,
+            generation: self.generations.get(db),
+        }
+    }
 }
 
 impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
@@ -180,32 +411,79 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
     type RwTransaction = RwTransactionImpl<'e>;
     type Stat = StatImpl;
 
+    fn backend_name(&self) -> &'static str {
+        "lmdb"
+    }
+
     fn get_dbs(&self) -> Result<Vec<Option<String>>, Self::Error> {
         if self.env_db_type == EnvironmentDefaultDbType::SingleDatabase {
             return Ok(vec![None]);
         }
         let db = self
-            .lmdbenv
-            .open_db(None)
-            .map(DatabaseImpl)
+            .open_lmdb_db(None)
+            .map(|db| self.database(db))
             .map_err(ErrorImpl::LmdbError)?;
-        let reader = self.begin_ro_txn()?;
-        let cursor = reader.open_ro_cursor(&db)?;
-        let mut iter = cursor.into_iter();
+        let mut keys = vec![];
+        {
+            let reader = self.begin_ro_txn()?;
+            let cursor = reader.open_ro_cursor(&db)?;
+            let mut iter = cursor.into_iter();
+            while let Some(result) = iter.next() {
+                let (key, value) = result?;
+                // The default database can also hold pairs of its own, next to the
+                // records of the others. Values of another size can't be records, and
+                // are skipped without opening them, which would take a slot.
+                if value.len() == DB_RECORD_SIZE {
+                    keys.push(key.to_owned());
+                }
+            }
+        }
+        // LMDB refuses to open the remaining pairs as databases. Names can't contain NUL.
+        // Opening a database takes a slot, which later opens of it reuse, so this fails
+        // with `DbsFull` only if there are more databases than slots.
         let mut store = vec![];
-        while let Some(result) = iter.next() {
-            let (key, _) = result?;
-            let name = String::from_utf8(key.to_owned())
-                .map_err(|_| ErrorImpl::LmdbError(lmdb::Error::Corrupted))?;
-            store.push(Some(name));
+        for key in keys {
+            let name = match String::from_utf8(key) {
+                Ok(name) if !name.contains('\0') => name,
+                _ => continue,
+            };
+            match self.open_lmdb_db(Some(&name)) {
+                Ok(_) => store.push(Some(name)),
+                Err(lmdb::Error::Incompatible) => continue,
+                Err(e) => return Err(ErrorImpl::LmdbError(e)),
+            }
         }
         Ok(store)
     }
 
+    fn shared_default_db(&self) -> Result<Option<Self::Database>, Self::Error> {
+        if self.env_db_type == EnvironmentDefaultDbType::SingleDatabase {
+            return Ok(None);
+        }
+        self.open_lmdb_db(None)
+            .map(|db| Some(self.database(db)))
+            .map_err(ErrorImpl::LmdbError)
+    }
+
+    fn db_entries(&self, name: Option<&str>) -> Result<usize, Self::Error> {
+        let _reader = self.readers.enter();
+        let db = self.open_lmdb_db(name).map_err(ErrorImpl::LmdbError)?;
+        let txn = self.lmdbenv.begin_ro_txn().map_err(ErrorImpl::LmdbError)?;
+        let stat = txn.stat(db).map_err(ErrorImpl::LmdbError)?;
+        Ok(stat.entries())
+    }
+
+    fn db_flags(&self, name: Option<&str>) -> Result<Self::Flags, Self::Error> {
+        let _reader = self.readers.enter();
+        let db = self.open_lmdb_db(name).map_err(ErrorImpl::LmdbError)?;
+        let txn = self.lmdbenv.begin_ro_txn().map_err(ErrorImpl::LmdbError)?;
+        let flags = txn.db_flags(db).map_err(ErrorImpl::LmdbError)?;
+        Ok(DatabaseFlagsImpl(flags))
+    }
+
     fn open_db(&self, name: Option<&str>) -> Result<Self::Database, Self::Error> {
-        self.lmdbenv
-            .open_db(name)
-            .map(DatabaseImpl)
+        self.open_lmdb_db(name)
+            .map(|db| self.database(db))
             .map_err(ErrorImpl::LmdbError)
     }
 
@@ -214,30 +492,169 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
         name: Option<&str>,
         flags: Self::Flags,
     ) -> Result<Self::Database, Self::Error> {
-        self.lmdbenv
-            .create_db(name, flags.0)
-            .map(DatabaseImpl)
-            .map_err(ErrorImpl::LmdbError)
+        let _writer = self.writer_lock.lock();
+        self.create_db_locked(name, flags)
+    }
+
+    fn open_or_create_db(
+        &self,
+        name: Option<&str>,
+        flags: Self::Flags,
+    ) -> Result<(Self::Database, bool), Self::Error> {
+        // LMDB doesn't say whether it created a database, so check for it first, in
+        // the same write transaction that creates it, in case another process does too.
+        let _writer = self.writer_lock.lock();
+        let txn = self.lmdbenv.begin_rw_txn().map_err(ErrorImpl::LmdbError)?;
+        // Safe because neither handle is used once the transaction ends: the database
+        // is reopened by name below.
+        let created = match unsafe { txn.open_db(name) } {
+            Ok(_) => false,
+            Err(LmdbError::NotFound) => {
+                unsafe { txn.create_db(name, flags.0) }.map_err(ErrorImpl::LmdbError)?;
+                true
+            }
+            Err(e) => return Err(ErrorImpl::LmdbError(e)),
+        };
+        txn.commit().map_err(ErrorImpl::LmdbError)?;
+        self.create_db_locked(name, flags).map(|db| (db, created))
+    }
+
+    fn rename_db(
+        &self,
+        old: &str,
+        new: &str,
+        companions: &[(&str, &str)],
+    ) -> Result<(), Self::Error> {
+        // LMDB can't rename databases, so copy the pairs to new ones and drop the old
+        // ones, all in a single transaction.
+        let main = self.open_lmdb_db(None).map_err(ErrorImpl::LmdbError)?;
+        let mut renames = vec![(
+            self.open_lmdb_db(Some(old)).map_err(ErrorImpl::LmdbError)?,
+            new,
+        )];
+        for (old, new) in companions {
+            if let Some(db) = self.open_companion_db(old)? {
+                renames.push((db, new));
+            }
+        }
+        let _writer = self.writer_lock.lock();
+        let mut txn = self.lmdbenv.begin_rw_txn().map_err(ErrorImpl::LmdbError)?;
+        for (old_db, new) in renames {
+            match txn.get(main, &new) {
+                Ok(_) => return Err(ErrorImpl::LmdbError(LmdbError::KeyExist)),
+                Err(LmdbError::NotFound) => {}
+                Err(e) => return Err(ErrorImpl::LmdbError(e)),
+            }
+            let flags = txn.db_flags(old_db).map_err(ErrorImpl::LmdbError)?;
+            let pairs = txn
+                .open_ro_cursor(old_db)
+                .and_then(|mut cursor| {
+                    cursor
+                        .iter_start()
+                        .map(|pair| pair.map(|(k, v)| (k.to_vec(), v.to_vec())))
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .map_err(ErrorImpl::LmdbError)?;
+            // Safe because neither handle is used once the transaction ends: the new one
+            // is reopened by name, and the old one is closed by dropping its database.
+            let new_db =
+                unsafe { txn.create_db(Some(new), flags) }.map_err(ErrorImpl::LmdbError)?;
+            for (k, v) in &pairs {
+                txn.put(new_db, k, v, WriteFlags::empty())
+                    .map_err(ErrorImpl::LmdbError)?;
+            }
+            self.generations
+                .bump_after(old_db, || unsafe { txn.drop_db(old_db) })
+                .map_err(ErrorImpl::LmdbError)?;
+        }
+        txn.commit().map_err(ErrorImpl::LmdbError)
+    }
+
+    fn drop_db(&self, name: &str, companions: &[&str]) -> Result<(), Self::Error> {
+        let mut dbs = vec![self
+            .open_lmdb_db(Some(name))
+            .map_err(ErrorImpl::LmdbError)?];
+        for name in companions {
+            dbs.extend(self.open_companion_db(name)?);
+        }
+        let _writer = self.writer_lock.lock();
+        let mut txn = self.lmdbenv.begin_rw_txn().map_err(ErrorImpl::LmdbError)?;
+        for db in dbs {
+            // Safe because the handle isn't used once the database is dropped: handles
+            // opened before are rejected from now on.
+            self.generations
+                .bump_after(db, || unsafe { txn.drop_db(db) })
+                .map_err(ErrorImpl::LmdbError)?;
+        }
+        txn.commit().map_err(ErrorImpl::LmdbError)
     }
 
     fn begin_ro_txn(&'e self) -> Result<Self::RoTransaction, Self::Error> {
-        self.lmdbenv
-            .begin_ro_txn()
-            .map(RoTransactionImpl)
-            .map_err(ErrorImpl::LmdbError)
+        let reader = self.readers.enter();
+        match self.reader_cache.take() {
+            Some(txn) => txn.renew(),
+            None => self.lmdbenv.begin_ro_txn(),
+        }
+        .map(|txn| {
+            RoTransactionImpl::new(
+                txn,
+                self.id,
+                &self.generations,
+                reader,
+                self.max_value_size,
+                &self.reader_cache,
+            )
+        })
+        .map_err(ErrorImpl::LmdbError)
     }
 
     fn begin_rw_txn(&'e self) -> Result<Self::RwTransaction, Self::Error> {
-        self.lmdbenv
-            .begin_rw_txn()
-            .map(RwTransactionImpl)
-            .map_err(ErrorImpl::LmdbError)
+        self.begin_rw_txn_with(self.writer_lock.lock())
+    }
+
+    fn try_begin_rw_txn(&'e self) -> Result<Option<Self::RwTransaction>, Self::Error> {
+        match self.writer_lock.try_lock() {
+            Some(guard) => self.begin_rw_txn_with(guard).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn begin_rw_txn_timeout(
+        &'e self,
+        timeout: Duration,
+    ) -> Result<Option<Self::RwTransaction>, Self::Error> {
+        match self.writer_lock.lock_timeout(timeout) {
+            Some(guard) => self.begin_rw_txn_with(guard).map(Some),
+            None => Ok(None),
+        }
     }
 
     fn sync(&self, force: bool) -> Result<(), Self::Error> {
         self.lmdbenv.sync(force).map_err(ErrorImpl::LmdbError)
     }
 
+    fn copy_to(&self, path: &Path, compact: bool) -> Result<(), Self::Error> {
+        let dir = match self.env_path_type {
+            EnvironmentPathType::SubDir => Some(path),
+            EnvironmentPathType::NoSubDir => path.parent(),
+        };
+        if let Some(dir) = dir {
+            fs::create_dir_all(dir).map_err(ErrorImpl::IoError)?;
+        }
+        let c_path = path
+            .to_str()
+            .and_then(|path| CString::new(path).ok())
+            .ok_or_else(|| ErrorImpl::UnsuitableEnvironmentPath(path.into()))?;
+        let flags = if compact { lmdb_sys::MDB_CP_COMPACT } else { 0 };
+        // Safe because the environment outlives the call, and LMDB copies from its own
+        // read transaction, so writers aren't blocked.
+        let result = unsafe { lmdb_sys::mdb_env_copy2(self.lmdbenv.env(), c_path.as_ptr(), flags) };
+        match result {
+            lmdb_sys::MDB_SUCCESS => Ok(()),
+            code => Err(ErrorImpl::LmdbError(LmdbError::from_err_code(code))),
+        }
+    }
+
     fn stat(&self) -> Result<Self::Stat, Self::Error> {
         self.lmdbenv
             .stat()
@@ -252,10 +669,55 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
             .map_err(ErrorImpl::LmdbError)
     }
 
+    fn env_info(&self) -> Result<EnvInfo, Self::Error> {
+        // Read the info before `get_dbs` takes a reader slot.
+        let info = self.info()?;
+        let stat = self.stat()?;
+        Ok(EnvInfo {
+            map_size: Some(info.map_size()),
+            last_pgno: Some(info.last_pgno()),
+            size: (info.last_pgno() + 1) * stat.page_size(),
+            num_readers: info.num_readers(),
+            max_readers: Some(info.max_readers()),
+            num_dbs: self.get_dbs()?.len(),
+        })
+    }
+
     fn freelist(&self) -> Result<usize, Self::Error> {
+        let _reader = self.readers.enter();
         self.lmdbenv.freelist().map_err(ErrorImpl::LmdbError)
     }
 
+    fn reader_info(&self) -> Result<ReaderInfo, Self::Error> {
+        let mut in_use = 0usize;
+        // Safe because the environment and `in_use` outlive the call, and `ctx` is only
+        // ever `in_use`.
+        let result = unsafe {
+            lmdb_sys::mdb_reader_list(
+                self.lmdbenv.env(),
+                Some(count_open_readers),
+                &mut in_use as *mut usize as *mut c_void,
+            )
+        };
+        if result < 0 {
+            return Err(ErrorImpl::LmdbError(LmdbError::from_err_code(result)));
+        }
+        Ok(ReaderInfo {
+            in_use,
+            max: Some(self.info()?.max_readers()),
+        })
+    }
+
+    fn check_stale_readers(&self) -> Result<usize, Self::Error> {
+        let mut dead: c_int = 0;
+        // Safe because the environment and `dead` outlive the call.
+        let result = unsafe { lmdb_sys::mdb_reader_check(self.lmdbenv.env(), &mut dead) };
+        match result {
+            lmdb_sys::MDB_SUCCESS => Ok(dead as usize),
+            code => Err(ErrorImpl::LmdbError(LmdbError::from_err_code(code))),
+        }
+    }
+
     fn load_ratio(&self) -> Result<Option<f32>, Self::Error> {
         let stat = self.stat()?;
         let info = self.info()?;
@@ -270,6 +732,10 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
         Ok(Some(used_pgs as f32 / total_pgs as f32))
     }
 
+    fn map_size(&self) -> Result<Option<usize>, Self::Error> {
+        Ok(Some(self.info()?.map_size()))
+    }
+
     fn set_map_size(&self, size: usize) -> Result<(), Self::Error> {
         self.lmdbenv
             .set_map_size(size)
@@ -287,7 +753,7 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
         }
 
         let mut db_filename = self.path.clone();
-        db_filename.push("data.mdb");
+        db_filename.push(DEFAULT_DB_FILENAME);
         store.push(db_filename);
 
         if self.env_lock_type == EnvironmentLockType::Lockfile {
@@ -298,4 +764,12 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
 
         store
     }
+
+    fn is_read_only(&self) -> bool {
+        self.env_access_type == EnvironmentAccessType::ReadOnly
+    }
+
+    fn warns_on_implicit_abort(&self) -> bool {
+        self.warn_on_implicit_abort
+    }
 }
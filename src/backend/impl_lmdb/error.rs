@@ -10,13 +10,31 @@
 
 use std::{fmt, io, path::PathBuf};
 
-use crate::{backend::traits::BackendError, error::StoreError};
+use crate::{
+    backend::traits::BackendError,
+    error::{DataError, StoreError},
+};
 
 #[derive(Debug)]
 pub enum ErrorImpl {
     LmdbError(lmdb::Error),
     UnsuitableEnvironmentPath(PathBuf),
     IoError(io::Error),
+    DbIsForeignError,
+    DbIsDroppedError,
+    ValueTooLarge { size: usize, max: usize },
+}
+
+/// Fail if `value` is longer than `max` bytes, so that a value corrupted or written
+/// without the limit is never decoded.
+pub(crate) fn check_value_size(value: &[u8], max: Option<usize>) -> Result<&[u8], ErrorImpl> {
+    match max {
+        Some(max) if value.len() > max => Err(ErrorImpl::ValueTooLarge {
+            size: value.len(),
+            max,
+        }),
+        _ => Ok(value),
+    }
 }
 
 impl BackendError for ErrorImpl {}
@@ -27,6 +45,9 @@ impl fmt::Display for ErrorImpl {
             ErrorImpl::LmdbError(e) => e.fmt(fmt),
             ErrorImpl::UnsuitableEnvironmentPath(_) => write!(fmt, "UnsuitableEnvironmentPath"),
             ErrorImpl::IoError(e) => e.fmt(fmt),
+            ErrorImpl::DbIsForeignError => write!(fmt, "DbIsForeignError"),
+            ErrorImpl::DbIsDroppedError => write!(fmt, "DbIsDroppedError"),
+            ErrorImpl::ValueTooLarge { .. } => write!(fmt, "ValueTooLarge"),
         }
     }
 }
@@ -36,16 +57,23 @@ impl Into<StoreError> for ErrorImpl {
         match self {
             ErrorImpl::LmdbError(lmdb::Error::Corrupted) => StoreError::DatabaseCorrupted,
             ErrorImpl::LmdbError(lmdb::Error::NotFound) => StoreError::KeyValuePairNotFound,
+            ErrorImpl::LmdbError(lmdb::Error::KeyExist) => StoreError::KeyValuePairExists,
             ErrorImpl::LmdbError(lmdb::Error::BadValSize) => StoreError::KeyValuePairBadSize,
             ErrorImpl::LmdbError(lmdb::Error::Invalid) => StoreError::FileInvalid,
             ErrorImpl::LmdbError(lmdb::Error::MapFull) => StoreError::MapFull,
             ErrorImpl::LmdbError(lmdb::Error::DbsFull) => StoreError::DbsFull,
             ErrorImpl::LmdbError(lmdb::Error::ReadersFull) => StoreError::ReadersFull,
+            ErrorImpl::LmdbError(lmdb::Error::Incompatible) => StoreError::IncompatibleFlags,
             ErrorImpl::LmdbError(error) => StoreError::LmdbError(error),
             ErrorImpl::UnsuitableEnvironmentPath(path) => {
                 StoreError::UnsuitableEnvironmentPath(path)
             }
             ErrorImpl::IoError(error) => StoreError::IoError(error),
+            ErrorImpl::DbIsForeignError => StoreError::StoreForeign,
+            ErrorImpl::DbIsDroppedError => StoreError::StoreDropped,
+            ErrorImpl::ValueTooLarge { size, max } => {
+                StoreError::DataError(DataError::ValueTooLarge { size, max })
+            }
         }
     }
 }
@@ -65,6 +65,10 @@ impl BackendDatabaseFlags for DatabaseFlagsImpl {
     fn set(&mut self, flag: DatabaseFlags, value: bool) {
         self.0.set(flag.into(), value)
     }
+
+    fn get(&self, flag: DatabaseFlags) -> bool {
+        self.0.contains(flag.into())
+    }
 }
 
 impl Into<DatabaseFlagsImpl> for DatabaseFlags {
@@ -8,7 +8,7 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
-use super::ErrorImpl;
+use super::{error::check_value_size, ErrorImpl};
 use crate::backend::traits::BackendIter;
 
 pub struct IterImpl<'i, C> {
@@ -19,15 +19,21 @@ pub struct IterImpl<'i, C> {
     #[allow(dead_code)]
     cursor: C,
     iter: lmdb::Iter<'i>,
+    max_value_size: Option<usize>,
 }
 
 impl<'i, C> IterImpl<'i, C> {
     pub(crate) fn new(
         mut cursor: C,
+        max_value_size: Option<usize>,
         to_iter: impl FnOnce(&mut C) -> lmdb::Iter<'i>,
     ) -> IterImpl<'i, C> {
         let iter = to_iter(&mut cursor);
-        IterImpl { cursor, iter }
+        IterImpl {
+            cursor,
+            iter,
+            max_value_size,
+        }
     }
 }
 
@@ -36,6 +42,10 @@ impl<'i, C> BackendIter<'i> for IterImpl<'i, C> {
 
     #[allow(clippy::type_complexity)]
     fn next(&mut self) -> Option<Result<(&'i [u8], &'i [u8]), Self::Error>> {
-        self.iter.next().map(|e| e.map_err(ErrorImpl::LmdbError))
+        let (key, value) = match self.iter.next()? {
+            Ok(pair) => pair,
+            Err(err) => return Some(Err(ErrorImpl::LmdbError(err))),
+        };
+        Some(check_value_size(value, self.max_value_size).map(|value| (key, value)))
     }
 }
@@ -0,0 +1,85 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use std::{
+    mem,
+    sync::{Mutex, MutexGuard, PoisonError},
+    thread::{self, ThreadId},
+};
+
+/// Keeps up to `capacity` read transactions that were reset rather than aborted when
+/// they ended, so that later reads can renew them instead of beginning new ones.
+/// Unless the environment was opened with `NO_TLS`, LMDB ties read transactions to
+/// their thread, so each one is only handed back to the thread that cached it.
+#[derive(Debug, Default)]
+pub(crate) struct ReaderCache {
+    capacity: usize,
+    readers: Mutex<Vec<(ThreadId, CachedReader)>>,
+}
+
+/// A reset transaction of the environment that owns the cache. Rust can't express
+/// that borrow, so its lifetime is erased, which is sound because:
+/// - the cache is a field of `EnvironmentImpl` declared before its `lmdb::Environment`,
+///   so it's dropped, aborting every cached transaction, before the environment closes;
+/// - the cache is never moved out of the environment, and `take` only hands out
+///   transactions with the lifetime of `&self`, so none of them outlives it either.
+#[derive(Debug)]
+struct CachedReader(lmdb::InactiveTransaction<'static>);
+
+// `CachedReader` isn't `Send` because LMDB transactions may be tied to their thread,
+// but it has to be for the cache to be shared by the threads of the environment:
+// - `take` only returns a transaction to the thread whose `ThreadId` it was cached
+//   with, so each transaction is only ever renewed by the thread that reset it;
+// - any other thread only drops it, when the cache itself is dropped, and LMDB allows
+//   reset transactions, which no longer hold a snapshot, to be aborted from any thread.
+unsafe impl Send for CachedReader {}
+
+impl ReaderCache {
+    pub(crate) fn new(capacity: usize) -> ReaderCache {
+        ReaderCache {
+            capacity,
+            readers: Mutex::default(),
+        }
+    }
+
+    /// Take a transaction that this thread cached, if any.
+    pub(crate) fn take(&self) -> Option<lmdb::InactiveTransaction<'_>> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let id = thread::current().id();
+        let mut readers = self.readers();
+        let i = readers.iter().position(|(thread, _)| *thread == id)?;
+        Some(readers.swap_remove(i).1 .0)
+    }
+
+    /// Reset `txn` and cache it, or abort it if the cache is full.
+    pub(crate) fn put(&self, txn: lmdb::RoTransaction<'_>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut readers = self.readers();
+        if readers.len() < self.capacity {
+            // Safe as explained on `CachedReader`: the cache, and so the transaction,
+            // is dropped before the environment that `txn` borrows.
+            let txn = unsafe {
+                mem::transmute::<lmdb::InactiveTransaction<'_>, lmdb::InactiveTransaction<'static>>(
+                    txn.reset(),
+                )
+            };
+            readers.push((thread::current().id(), CachedReader(txn)));
+        }
+    }
+
+    // The list is consistent whenever the mutex is released, even after a panic.
+    fn readers(&self) -> MutexGuard<'_, Vec<(ThreadId, CachedReader)>> {
+        self.readers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -8,27 +8,140 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
-use lmdb::Transaction;
+use std::{
+    borrow::Cow,
+    mem::{self, ManuallyDrop},
+    ops::{Bound, RangeBounds},
+};
+
+use lmdb::{Cursor, Transaction};
 
-use super::{DatabaseImpl, ErrorImpl, RoCursorImpl, WriteFlagsImpl};
-use crate::backend::traits::{
-    BackendRoCursorTransaction, BackendRoTransaction, BackendRwCursorTransaction,
-    BackendRwTransaction,
+use super::{
+    database::Generations, error::check_value_size, reader_cache::ReaderCache, DatabaseFlagsImpl,
+    DatabaseImpl, ErrorImpl, RoCursorImpl, WriteFlagsImpl,
+};
+use crate::backend::{
+    common::DatabaseStat,
+    lock::{ReaderCount, ReaderGuard, WriterGuard},
+    traits::{
+        BackendRoCursorTransaction, BackendRoRenewTransaction, BackendRoTransaction,
+        BackendRwCursorTransaction, BackendRwNestedTransaction, BackendRwTransaction,
+    },
 };
 
+fn database_stat(stat: lmdb::Stat) -> DatabaseStat {
+    let pages = stat.branch_pages() + stat.leaf_pages() + stat.overflow_pages();
+    DatabaseStat {
+        entries: stat.entries(),
+        size: pages * stat.page_size() as usize,
+        depth: Some(stat.depth() as usize),
+        branch_pages: Some(stat.branch_pages()),
+        leaf_pages: Some(stat.leaf_pages()),
+        overflow_pages: Some(stat.overflow_pages()),
+    }
+}
+
+/// Unwrap the LMDB handle of `db`, checking in debug builds that it was opened in the
+/// transaction's environment: another environment would use whichever of its own
+/// databases has the same handle, if any. Handles of databases that were dropped since
+/// are always rejected, for the same reason.
+fn lmdb_db(
+    db: &DatabaseImpl,
+    env_id: usize,
+    generations: &Generations,
+) -> Result<lmdb::Database, ErrorImpl> {
+    if cfg!(debug_assertions) && db.env_id != env_id {
+        return Err(ErrorImpl::DbIsForeignError);
+    }
+    if db.generation != generations.get(db.db) {
+        return Err(ErrorImpl::DbIsDroppedError);
+    }
+    Ok(db.db)
+}
+
 #[derive(Debug)]
-pub struct RoTransactionImpl<'t>(pub(crate) lmdb::RoTransaction<'t>);
+pub struct RoTransactionImpl<'t> {
+    // Only taken when dropped, to hand it to the cache.
+    txn: ManuallyDrop<lmdb::RoTransaction<'t>>,
+    env_id: usize,
+    generations: &'t Generations,
+    // Counts the transaction as active until it's reset and put back in the cache.
+    _reader: ReaderGuard<'t>,
+    max_value_size: Option<usize>,
+    cache: &'t ReaderCache,
+}
+
+impl<'t> RoTransactionImpl<'t> {
+    pub(crate) fn new(
+        txn: lmdb::RoTransaction<'t>,
+        env_id: usize,
+        generations: &'t Generations,
+        reader: ReaderGuard<'t>,
+        max_value_size: Option<usize>,
+        cache: &'t ReaderCache,
+    ) -> RoTransactionImpl<'t> {
+        RoTransactionImpl {
+            txn: ManuallyDrop::new(txn),
+            env_id,
+            generations,
+            _reader: reader,
+            max_value_size,
+            cache,
+        }
+    }
+}
+
+impl<'t> Drop for RoTransactionImpl<'t> {
+    fn drop(&mut self) {
+        // Safe because the transaction isn't used again.
+        let txn = unsafe { ManuallyDrop::take(&mut self.txn) };
+        self.cache.put(txn);
+    }
+}
 
 impl<'t> BackendRoTransaction for RoTransactionImpl<'t> {
     type Database = DatabaseImpl;
     type Error = ErrorImpl;
 
     fn get(&self, db: &Self::Database, key: &[u8]) -> Result<&[u8], Self::Error> {
-        self.0.get(db.0, &key).map_err(ErrorImpl::LmdbError)
+        let value = self
+            .txn
+            .get(lmdb_db(db, self.env_id, self.generations)?, &key)
+            .map_err(ErrorImpl::LmdbError)?;
+        check_value_size(value, self.max_value_size)
+    }
+
+    fn stat(&self, db: &Self::Database) -> Result<DatabaseStat, Self::Error> {
+        self.txn
+            .stat(lmdb_db(db, self.env_id, self.generations)?)
+            .map(database_stat)
+            .map_err(ErrorImpl::LmdbError)
+    }
+
+    fn db_flags(&self, db: &Self::Database) -> Result<DatabaseFlagsImpl, Self::Error> {
+        self.txn
+            .db_flags(lmdb_db(db, self.env_id, self.generations)?)
+            .map(DatabaseFlagsImpl)
+            .map_err(ErrorImpl::LmdbError)
     }
 
     fn abort(self) {
-        self.0.abort()
+        // Dropping the transaction aborts it, unless the reader cache keeps it.
+        drop(self)
+    }
+}
+
+impl<'t> BackendRoRenewTransaction for RoTransactionImpl<'t> {
+    fn renew(&mut self) -> Result<(), Self::Error> {
+        let txn = self.txn.txn();
+        // Safe because `&mut self` ensures nothing read from the transaction is still
+        // borrowed. If renewing fails, the transaction stays reset, which LMDB allows to
+        // be used, failing with `MDB_BAD_TXN`, and to be reset again or aborted.
+        unsafe { lmdb_sys::mdb_txn_reset(txn) };
+        match unsafe { lmdb_sys::mdb_txn_renew(txn) } {
+            lmdb_sys::MDB_SUCCESS => Ok(()),
+            code => Err(ErrorImpl::LmdbError(lmdb::Error::from_err_code(code))),
+        }
     }
 }
 
@@ -36,15 +149,205 @@ impl<'t> BackendRoCursorTransaction<'t> for RoTransactionImpl<'t> {
     type RoCursor = RoCursorImpl<'t>;
 
     fn open_ro_cursor(&'t self, db: &Self::Database) -> Result<Self::RoCursor, Self::Error> {
-        self.0
-            .open_ro_cursor(db.0)
-            .map(RoCursorImpl)
+        self.txn
+            .open_ro_cursor(lmdb_db(db, self.env_id, self.generations)?)
+            .map(|cursor| RoCursorImpl(cursor, self.max_value_size))
             .map_err(ErrorImpl::LmdbError)
     }
 }
 
 #[derive(Debug)]
-pub struct RwTransactionImpl<'t>(pub(crate) lmdb::RwTransaction<'t>);
+pub struct RwTransactionImpl<'t> {
+    // Only `None` after growing the map failed midway, leaving no live transaction.
+    txn: Option<lmdb::RwTransaction<'t>>,
+    env_id: usize,
+    generations: &'t Generations,
+    max_key_size: usize,
+    max_value_size: Option<usize>,
+    resize: Option<Resize<'t>>,
+    nested: Option<Nested<'t>>,
+    // Only `None` for nested transactions, since their parent holds the lock.
+    _writer: Option<WriterGuard<'t>>,
+}
+
+/// What an auto-resizing transaction needs to recover from `MapFull`: a transaction
+/// that runs out of space can't be used anymore, so it's aborted, the map is grown,
+/// and all of its changes so far are replayed in a new transaction.
+#[derive(Debug)]
+struct Resize<'t> {
+    env: &'t lmdb::Environment,
+    readers: &'t ReaderCount,
+    log: Vec<Change<'static>>,
+}
+
+/// What a transaction nested in an auto-resizing one needs: the parent may have to
+/// replay the changes of the nested transaction if it commits, so they're logged, and
+/// moved to the parent's log on commit. The nested transaction itself can't grow the
+/// map, since that would abort its parent.
+#[derive(Debug)]
+struct Nested<'t> {
+    log: Vec<Change<'static>>,
+    parent_log: &'t mut Vec<Change<'static>>,
+}
+
+#[derive(Debug)]
+enum Change<'c> {
+    Put {
+        db: lmdb::Database,
+        key: Cow<'c, [u8]>,
+        value: Cow<'c, [u8]>,
+        flags: lmdb::WriteFlags,
+    },
+    Del {
+        db: lmdb::Database,
+        key: Cow<'c, [u8]>,
+        value: Option<Cow<'c, [u8]>>,
+    },
+    Clear {
+        db: lmdb::Database,
+    },
+}
+
+impl<'c> Change<'c> {
+    fn apply(&self, txn: &mut lmdb::RwTransaction) -> Result<(), lmdb::Error> {
+        match self {
+            Change::Put {
+                db,
+                key,
+                value,
+                flags,
+            } => txn.put(*db, key, value, *flags),
+            Change::Del { db, key, value } => txn.del(*db, key, value.as_deref()),
+            Change::Clear { db } => txn.clear_db(*db),
+        }
+    }
+
+    fn into_owned(self) -> Change<'static> {
+        match self {
+            Change::Put {
+                db,
+                key,
+                value,
+                flags,
+            } => Change::Put {
+                db,
+                key: Cow::Owned(key.into_owned()),
+                value: Cow::Owned(value.into_owned()),
+                flags,
+            },
+            Change::Del { db, key, value } => Change::Del {
+                db,
+                key: Cow::Owned(key.into_owned()),
+                value: value.map(|value| Cow::Owned(value.into_owned())),
+            },
+            Change::Clear { db } => Change::Clear { db },
+        }
+    }
+}
+
+impl<'t> RwTransactionImpl<'t> {
+    pub(crate) fn new(
+        txn: lmdb::RwTransaction<'t>,
+        env_id: usize,
+        generations: &'t Generations,
+        max_key_size: usize,
+        max_value_size: Option<usize>,
+        auto_resize_env: Option<(&'t lmdb::Environment, &'t ReaderCount)>,
+        writer: WriterGuard<'t>,
+    ) -> RwTransactionImpl<'t> {
+        RwTransactionImpl {
+            txn: Some(txn),
+            env_id,
+            generations,
+            max_key_size,
+            max_value_size,
+            resize: auto_resize_env.map(|(env, readers)| Resize {
+                env,
+                readers,
+                log: vec![],
+            }),
+            nested: None,
+            _writer: Some(writer),
+        }
+    }
+
+    /// The log of changes to replay after growing the map, if any: this transaction's
+    /// own if it can grow the map, or the one its parent takes over otherwise.
+    fn log_mut(&mut self) -> Option<&mut Vec<Change<'static>>> {
+        match (&mut self.resize, &mut self.nested) {
+            (Some(resize), _) => Some(&mut resize.log),
+            (None, Some(nested)) => Some(&mut nested.log),
+            (None, None) => None,
+        }
+    }
+
+    fn txn(&self) -> Result<&lmdb::RwTransaction<'t>, ErrorImpl> {
+        self.txn
+            .as_ref()
+            .ok_or(ErrorImpl::LmdbError(lmdb::Error::BadTxn))
+    }
+
+    fn txn_mut(&mut self) -> Result<&mut lmdb::RwTransaction<'t>, ErrorImpl> {
+        self.txn
+            .as_mut()
+            .ok_or(ErrorImpl::LmdbError(lmdb::Error::BadTxn))
+    }
+
+    fn apply(&mut self, change: Change) -> Result<(), ErrorImpl> {
+        loop {
+            match change.apply(self.txn_mut()?) {
+                Err(lmdb::Error::MapFull) if self.resize.is_some() => self.grow()?,
+                Err(err) => return Err(ErrorImpl::LmdbError(err)),
+                Ok(()) => break,
+            }
+        }
+        if let Some(log) = self.log_mut() {
+            log.push(change.into_owned());
+        }
+        Ok(())
+    }
+
+    /// Abort the current transaction, double the map size, and replay the logged
+    /// changes in a new transaction, until they fit. LMDB can't resize the map while
+    /// read transactions are active, so this fails with `MapFull` if there are any,
+    /// and keeps new ones waiting until it's done.
+    fn grow(&mut self) -> Result<(), ErrorImpl> {
+        let resize = match &self.resize {
+            Some(resize) => resize,
+            None => return Err(ErrorImpl::LmdbError(lmdb::Error::MapFull)),
+        };
+        let _readers = resize
+            .readers
+            .exclude()
+            .ok_or(ErrorImpl::LmdbError(lmdb::Error::MapFull))?;
+        if let Some(txn) = self.txn.take() {
+            txn.abort();
+        }
+        loop {
+            let map_size = resize.env.info().map_err(ErrorImpl::LmdbError)?.map_size();
+            let map_size = map_size
+                .checked_mul(2)
+                .ok_or(ErrorImpl::LmdbError(lmdb::Error::MapFull))?;
+            resize
+                .env
+                .set_map_size(map_size)
+                .map_err(ErrorImpl::LmdbError)?;
+            let mut txn = resize.env.begin_rw_txn().map_err(ErrorImpl::LmdbError)?;
+            match resize
+                .log
+                .iter()
+                .try_for_each(|change| change.apply(&mut txn))
+            {
+                Ok(()) => {
+                    self.txn = Some(txn);
+                    return Ok(());
+                }
+                Err(lmdb::Error::MapFull) => txn.abort(),
+                Err(err) => return Err(ErrorImpl::LmdbError(err)),
+            }
+        }
+    }
+}
 
 impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
     type Database = DatabaseImpl;
@@ -52,7 +355,25 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
     type Flags = WriteFlagsImpl;
 
     fn get(&self, db: &Self::Database, key: &[u8]) -> Result<&[u8], Self::Error> {
-        self.0.get(db.0, &key).map_err(ErrorImpl::LmdbError)
+        let value = self
+            .txn()?
+            .get(lmdb_db(db, self.env_id, self.generations)?, &key)
+            .map_err(ErrorImpl::LmdbError)?;
+        check_value_size(value, self.max_value_size)
+    }
+
+    fn stat(&self, db: &Self::Database) -> Result<DatabaseStat, Self::Error> {
+        self.txn()?
+            .stat(lmdb_db(db, self.env_id, self.generations)?)
+            .map(database_stat)
+            .map_err(ErrorImpl::LmdbError)
+    }
+
+    fn db_flags(&self, db: &Self::Database) -> Result<DatabaseFlagsImpl, Self::Error> {
+        self.txn()?
+            .db_flags(lmdb_db(db, self.env_id, self.generations)?)
+            .map(DatabaseFlagsImpl)
+            .map_err(ErrorImpl::LmdbError)
     }
 
     fn put(
@@ -62,14 +383,53 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
         value: &[u8],
         flags: Self::Flags,
     ) -> Result<(), Self::Error> {
-        self.0
-            .put(db.0, &key, &value, flags.0)
-            .map_err(ErrorImpl::LmdbError)
+        self.apply(Change::Put {
+            db: lmdb_db(db, self.env_id, self.generations)?,
+            key: Cow::Borrowed(key),
+            value: Cow::Borrowed(value),
+            flags: flags.0,
+        })
+    }
+
+    fn reserve<F>(
+        &mut self,
+        db: &Self::Database,
+        key: &[u8],
+        len: usize,
+        flags: Self::Flags,
+        fill: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnOnce(&mut [u8]),
+    {
+        let lmdb_db = lmdb_db(db, self.env_id, self.generations)?;
+        let dup_sort = self
+            .txn()?
+            .db_flags(lmdb_db)
+            .map_err(ErrorImpl::LmdbError)?
+            .contains(lmdb::DatabaseFlags::DUP_SORT);
+        // Replaying needs a copy of the value anyway, and LMDB can't reserve space in
+        // databases that allow duplicate values.
+        if self.log_mut().is_some() || dup_sort {
+            let mut value = vec![0; len];
+            fill(&mut value);
+            return self.put(db, key, &value, flags);
+        }
+        let buf = self
+            .txn_mut()?
+            .reserve(lmdb_db, &key, len, flags.0)
+            .map_err(ErrorImpl::LmdbError)?;
+        fill(buf);
+        Ok(())
     }
 
     #[cfg(not(feature = "db-dup-sort"))]
     fn del(&mut self, db: &Self::Database, key: &[u8]) -> Result<(), Self::Error> {
-        self.0.del(db.0, &key, None).map_err(ErrorImpl::LmdbError)
+        self.apply(Change::Del {
+            db: lmdb_db(db, self.env_id, self.generations)?,
+            key: Cow::Borrowed(key),
+            value: None,
+        })
     }
 
     #[cfg(feature = "db-dup-sort")]
@@ -79,19 +439,148 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
         key: &[u8],
         value: Option<&[u8]>,
     ) -> Result<(), Self::Error> {
-        self.0.del(db.0, &key, value).map_err(ErrorImpl::LmdbError)
+        self.apply(Change::Del {
+            db: lmdb_db(db, self.env_id, self.generations)?,
+            key: Cow::Borrowed(key),
+            value: value.map(Cow::Borrowed),
+        })
+    }
+
+    fn scan_range<F>(
+        &self,
+        db: &Self::Database,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+        mut f: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&[u8], &[u8]),
+    {
+        let db = lmdb_db(db, self.env_id, self.generations)?;
+        let mut cursor = self
+            .txn()?
+            .open_ro_cursor(db)
+            .map_err(ErrorImpl::LmdbError)?;
+        let iter = match from {
+            Bound::Included(key) | Bound::Excluded(key) => cursor.iter_from(key),
+            Bound::Unbounded => cursor.iter(),
+        };
+        for result in iter {
+            let (key, value) = result.map_err(ErrorImpl::LmdbError)?;
+            if from == Bound::Excluded(key) {
+                continue;
+            }
+            if !(Bound::Unbounded, to).contains(key) {
+                break;
+            }
+            f(key, value);
+        }
+        Ok(())
+    }
+
+    fn del_range<F>(
+        &mut self,
+        db: &Self::Database,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+        mut pred: F,
+    ) -> Result<Vec<Vec<u8>>, Self::Error>
+    where
+        F: FnMut(&[u8], &[u8]) -> bool,
+    {
+        // Collect the keys before deleting any of them, because deleting entries
+        // would invalidate the cursor.
+        let mut keys: Vec<Vec<u8>> = vec![];
+        self.scan_range(db, from, to, |key, value| {
+            if pred(key, value) && keys.last().map_or(true, |last| last.as_slice() != key) {
+                keys.push(key.to_vec());
+            }
+        })?;
+        let db = lmdb_db(db, self.env_id, self.generations)?;
+        for key in &keys {
+            self.apply(Change::Del {
+                db,
+                key: Cow::Borrowed(key),
+                value: None,
+            })?;
+        }
+        Ok(keys)
     }
 
     fn clear_db(&mut self, db: &Self::Database) -> Result<(), Self::Error> {
-        self.0.clear_db(db.0).map_err(ErrorImpl::LmdbError)
+        self.apply(Change::Clear {
+            db: lmdb_db(db, self.env_id, self.generations)?,
+        })
+    }
+
+    fn max_key_size(&self) -> Option<usize> {
+        Some(self.max_key_size)
+    }
+
+    fn max_value_size(&self) -> Option<usize> {
+        self.max_value_size
     }
 
-    fn commit(self) -> Result<(), Self::Error> {
-        self.0.commit().map_err(ErrorImpl::LmdbError)
+    fn commit(mut self) -> Result<(), Self::Error> {
+        loop {
+            let txn = self
+                .txn
+                .take()
+                .ok_or(ErrorImpl::LmdbError(lmdb::Error::BadTxn))?;
+            match txn.commit() {
+                Err(lmdb::Error::MapFull) if self.resize.is_some() => self.grow()?,
+                Err(err) => return Err(ErrorImpl::LmdbError(err)),
+                Ok(()) => break,
+            }
+        }
+        if let Some(nested) = self.nested {
+            nested.parent_log.extend(nested.log);
+        }
+        Ok(())
     }
 
     fn abort(self) {
-        self.0.abort()
+        if let Some(txn) = self.txn {
+            txn.abort()
+        }
+    }
+}
+
+impl<'p, 't> BackendRwNestedTransaction<'p> for RwTransactionImpl<'t> {
+    type Nested = RwTransactionImpl<'p>;
+
+    fn begin_nested_txn(&'p mut self) -> Result<Self::Nested, Self::Error> {
+        let parent_log = match (&mut self.resize, &mut self.nested) {
+            (Some(resize), _) => Some(&mut resize.log),
+            (None, Some(nested)) => Some(&mut nested.log),
+            (None, None) => None,
+        };
+        let txn = self
+            .txn
+            .as_mut()
+            .ok_or(ErrorImpl::LmdbError(lmdb::Error::BadTxn))?
+            .begin_nested_txn()
+            .map_err(ErrorImpl::LmdbError)?;
+        // The lmdb crate ignores failures to begin nested transactions, e.g. in
+        // environments opened with `WRITE_MAP`, and returns a null transaction instead,
+        // which mustn't be aborted when dropped.
+        if txn.txn().is_null() {
+            mem::forget(txn);
+            return Err(ErrorImpl::LmdbError(lmdb::Error::BadTxn));
+        }
+        Ok(RwTransactionImpl {
+            txn: Some(txn),
+            env_id: self.env_id,
+            generations: self.generations,
+            max_key_size: self.max_key_size,
+            max_value_size: self.max_value_size,
+            resize: None,
+            nested: parent_log.map(|parent_log| Nested {
+                log: vec![],
+                parent_log,
+            }),
+            _writer: None,
+        })
     }
 }
 
@@ -99,9 +588,9 @@ impl<'t> BackendRwCursorTransaction<'t> for RwTransactionImpl<'t> {
     type RoCursor = RoCursorImpl<'t>;
 
     fn open_ro_cursor(&'t self, db: &Self::Database) -> Result<Self::RoCursor, Self::Error> {
-        self.0
-            .open_ro_cursor(db.0)
-            .map(RoCursorImpl)
+        self.txn()?
+            .open_ro_cursor(lmdb_db(db, self.env_id, self.generations)?)
+            .map(|cursor| RoCursorImpl(cursor, self.max_value_size))
             .map_err(ErrorImpl::LmdbError)
     }
 }
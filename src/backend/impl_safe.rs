@@ -10,6 +10,8 @@
 
 mod cursor;
 mod database;
+#[cfg(feature = "encryption")]
+mod encryption;
 mod environment;
 mod error;
 mod flags;
@@ -27,4 +29,4 @@ pub use flags::{DatabaseFlagsImpl, EnvironmentFlagsImpl, WriteFlagsImpl};
 pub use info::InfoImpl;
 pub use iter::IterImpl;
 pub use stat::StatImpl;
-pub use transaction::{RoTransactionImpl, RwTransactionImpl};
+pub use transaction::{RoSnapshotImpl, RoTransactionImpl, RwTransactionImpl};
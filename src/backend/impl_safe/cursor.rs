@@ -8,76 +8,147 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
-use super::{snapshot::Snapshot, IterImpl};
-use crate::backend::traits::BackendRoCursor;
+use std::ops::Bound::{Excluded, Unbounded};
+
+use super::{error::check_value_size, snapshot::Snapshot, ErrorImpl, IterImpl};
+use crate::backend::{common::CursorOp, traits::BackendRoCursor};
 
 #[derive(Debug)]
-pub struct RoCursorImpl<'c>(pub(crate) &'c Snapshot);
+pub struct RoCursorImpl<'c> {
+    snapshot: &'c Snapshot,
+    position: Option<(&'c [u8], &'c [u8])>,
+    max_value_size: Option<usize>,
+}
+
+impl<'c> RoCursorImpl<'c> {
+    pub(crate) fn new(snapshot: &'c Snapshot, max_value_size: Option<usize>) -> RoCursorImpl<'c> {
+        RoCursorImpl {
+            snapshot,
+            position: None,
+            max_value_size,
+        }
+    }
+}
 
 #[cfg(not(feature = "db-dup-sort"))]
 impl<'c> BackendRoCursor<'c> for RoCursorImpl<'c> {
+    type Error = ErrorImpl;
     type Iter = IterImpl<'c>;
 
+    fn seek(&mut self, op: CursorOp<'_>) -> Option<Result<(&'c [u8], &'c [u8]), Self::Error>> {
+        // A failed seek leaves the cursor where it was.
+        let entry = self.snapshot.seek(self.position, op)?;
+        self.position = Some(entry);
+        let (key, value) = entry;
+        Some(check_value_size(value, self.max_value_size).map(|value| (key, value)))
+    }
+
     fn into_iter(self) -> Self::Iter {
-        IterImpl(Box::new(self.0.iter()))
+        IterImpl::new(Box::new(self.snapshot.iter()), self.max_value_size)
     }
 
     fn into_iter_from<K>(self, key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c,
     {
-        IterImpl(Box::new(
-            self.0.iter().skip_while(move |&(k, _)| k < key.as_ref()),
-        ))
+        IterImpl::new(
+            Box::new(
+                self.snapshot
+                    .iter()
+                    .skip_while(move |&(k, _)| k < key.as_ref()),
+            ),
+            self.max_value_size,
+        )
+    }
+
+    fn into_iter_after<K>(self, key: K) -> Self::Iter
+    where
+        K: AsRef<[u8]> + 'c,
+    {
+        let range = self.snapshot.range(Excluded(key.as_ref()), Unbounded);
+        IterImpl::new(Box::new(range), self.max_value_size)
     }
 
     fn into_iter_dup_of<K>(self, key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c,
     {
-        IterImpl(Box::new(
-            self.0.iter().filter(move |&(k, _)| k == key.as_ref()),
-        ))
+        IterImpl::new(
+            Box::new(
+                self.snapshot
+                    .iter()
+                    .filter(move |&(k, _)| k == key.as_ref()),
+            ),
+            self.max_value_size,
+        )
     }
 }
 
 #[cfg(feature = "db-dup-sort")]
 impl<'c> BackendRoCursor<'c> for RoCursorImpl<'c> {
+    type Error = ErrorImpl;
     type Iter = IterImpl<'c>;
 
+    fn seek(&mut self, op: CursorOp<'_>) -> Option<Result<(&'c [u8], &'c [u8]), Self::Error>> {
+        // A failed seek leaves the cursor where it was.
+        let entry = self.snapshot.seek(self.position, op)?;
+        self.position = Some(entry);
+        let (key, value) = entry;
+        Some(check_value_size(value, self.max_value_size).map(|value| (key, value)))
+    }
+
     fn into_iter(self) -> Self::Iter {
         let flattened = self
-            .0
+            .snapshot
             .iter()
             .flat_map(|(key, values)| values.map(move |value| (key, value)));
-        IterImpl(Box::new(flattened))
+        IterImpl::new(Box::new(flattened), self.max_value_size)
     }
 
     fn into_iter_from<K>(self, key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c,
     {
-        let skipped = self.0.iter().skip_while(move |&(k, _)| k < key.as_ref());
+        let skipped = self
+            .snapshot
+            .iter()
+            .skip_while(move |&(k, _)| k < key.as_ref());
         let flattened = skipped.flat_map(|(key, values)| values.map(move |value| (key, value)));
-        IterImpl(Box::new(flattened))
+        IterImpl::new(Box::new(flattened), self.max_value_size)
+    }
+
+    fn into_iter_after<K>(self, key: K) -> Self::Iter
+    where
+        K: AsRef<[u8]> + 'c,
+    {
+        let range = self.snapshot.range(Excluded(key.as_ref()), Unbounded);
+        IterImpl::new(Box::new(range), self.max_value_size)
     }
 
     fn into_iter_dup_of<K>(self, key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c,
     {
-        let filtered = self.0.iter().filter(move |&(k, _)| k == key.as_ref());
+        let filtered = self
+            .snapshot
+            .iter()
+            .filter(move |&(k, _)| k == key.as_ref());
         let flattened = filtered.flat_map(|(key, values)| values.map(move |value| (key, value)));
-        IterImpl(Box::new(flattened))
+        IterImpl::new(Box::new(flattened), self.max_value_size)
     }
 }
 
 #[derive(Debug)]
-pub struct RwCursorImpl<'c>(&'c mut Snapshot);
+pub struct RwCursorImpl<'c>(#[allow(dead_code)] &'c mut Snapshot);
 
 impl<'c> BackendRoCursor<'c> for RwCursorImpl<'c> {
+    type Error = ErrorImpl;
     type Iter = IterImpl<'c>;
 
+    fn seek(&mut self, _op: CursorOp<'_>) -> Option<Result<(&'c [u8], &'c [u8]), Self::Error>> {
+        unimplemented!()
+    }
+
     fn into_iter(self) -> Self::Iter {
         unimplemented!()
     }
@@ -89,6 +160,13 @@ impl<'c> BackendRoCursor<'c> for RwCursorImpl<'c> {
         unimplemented!()
     }
 
+    fn into_iter_after<K>(self, _key: K) -> Self::Iter
+    where
+        K: AsRef<[u8]> + 'c,
+    {
+        unimplemented!()
+    }
+
     fn into_iter_dup_of<K>(self, _key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c,
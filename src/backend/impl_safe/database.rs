@@ -17,7 +17,9 @@ use crate::backend::traits::BackendDatabase;
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub struct DatabaseImpl(pub(crate) Id<Database>);
 
-impl BackendDatabase for DatabaseImpl {}
+impl BackendDatabase for DatabaseImpl {
+    type Flags = DatabaseFlagsImpl;
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Database {
@@ -35,6 +37,18 @@ impl Database {
         self.snapshot.clone()
     }
 
+    pub(crate) fn entries(&self) -> usize {
+        self.snapshot.entries()
+    }
+
+    pub(crate) fn flags(&self) -> DatabaseFlagsImpl {
+        *self.snapshot.flags()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.snapshot.clear();
+    }
+
     pub(crate) fn replace(&mut self, snapshot: Snapshot) -> Snapshot {
         std::mem::replace(&mut self.snapshot, snapshot)
     }
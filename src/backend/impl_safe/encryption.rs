@@ -0,0 +1,69 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use std::{fmt, io};
+
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+
+use super::ErrorImpl;
+
+/// A key to encrypt the database file with ChaCha20-Poly1305. Each write uses a new
+/// random nonce, which is prepended to the encrypted data.
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub(crate) struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    pub(crate) fn new(key: &[u8; 32]) -> EncryptionKey {
+        EncryptionKey(*key)
+    }
+
+    fn aead_key(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&CHACHA20_POLY1305, &self.0).expect("32 byte key"))
+    }
+
+    pub(crate) fn seal(&self, mut data: Vec<u8>) -> Result<Vec<u8>, ErrorImpl> {
+        let failed = |_| io::Error::new(io::ErrorKind::Other, "couldn't encrypt the database");
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).map_err(failed)?;
+        self.aead_key()
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(failed)?;
+        let mut sealed = nonce.to_vec();
+        sealed.append(&mut data);
+        Ok(sealed)
+    }
+
+    pub(crate) fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, ErrorImpl> {
+        if sealed.len() < NONCE_LEN {
+            return Err(ErrorImpl::DecryptionError);
+        }
+        let (nonce, data) = sealed.split_at(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(nonce).map_err(|_| ErrorImpl::DecryptionError)?;
+        let mut data = data.to_vec();
+        let len = self
+            .aead_key()
+            .open_in_place(nonce, Aad::empty(), &mut data)
+            .map_err(|_| ErrorImpl::DecryptionError)?
+            .len();
+        data.truncate(len);
+        Ok(data)
+    }
+}
+
+/// Never prints the key itself, since builders and environments get logged.
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
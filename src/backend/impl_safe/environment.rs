@@ -12,19 +12,27 @@ use std::{
     borrow::Cow,
     collections::HashMap,
     fs,
+    io::Write,
     ops::DerefMut,
     path::{Path, PathBuf},
-    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+    time::Duration,
 };
 
 use id_arena::Arena;
 use log::warn;
 
+#[cfg(feature = "encryption")]
+use super::encryption::EncryptionKey;
 use super::{
     database::Database, DatabaseFlagsImpl, DatabaseImpl, EnvironmentFlagsImpl, ErrorImpl, InfoImpl,
     RoTransactionImpl, RwTransactionImpl, StatImpl,
 };
-use crate::backend::common::RecoveryStrategy;
+use crate::backend::common::{Durability, EnvInfo, ReaderInfo, RecoveryStrategy};
+use crate::backend::lock::WriterLock;
 use crate::backend::traits::{BackendEnvironment, BackendEnvironmentBuilder};
 
 const DEFAULT_DB_FILENAME: &str = "data.safe.bin";
@@ -41,6 +49,34 @@ pub struct EnvironmentBuilderImpl {
     map_size: Option<usize>,
     make_dir_if_needed: bool,
     corruption_recovery_strategy: RecoveryStrategy,
+    warn_on_implicit_abort: bool,
+    max_key_size: Option<usize>,
+    max_value_size: Option<usize>,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<EncryptionKey>,
+}
+
+impl EnvironmentBuilderImpl {
+    /// Fail writes of keys longer than `max_key_size` bytes with `StoreError::KeyTooLong`,
+    /// e.g. to keep keys usable with LMDB, whose limit can't be configured. Keys aren't
+    /// limited by default.
+    pub fn set_max_key_size(&mut self, max_key_size: usize) -> &mut Self {
+        self.max_key_size = Some(max_key_size);
+        self
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl EnvironmentBuilderImpl {
+    /// Encrypt the database file with `key`, using ChaCha20-Poly1305. The environment
+    /// must always be opened with the same key: opening it with another key, or after
+    /// its file was tampered with, fails with `StoreError::DecryptionFailed`. The
+    /// corruption recovery strategy applies to these failures too, so beware that
+    /// `RecoveryStrategy::Discard` can't tell a wrong key from a tampered file.
+    pub fn set_encryption_key(&mut self, key: &[u8; 32]) -> &mut Self {
+        self.encryption_key = Some(EncryptionKey::new(key));
+        self
+    }
 }
 
 impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
@@ -56,6 +92,11 @@ impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
             map_size: None,
             make_dir_if_needed: false,
             corruption_recovery_strategy: RecoveryStrategy::Error,
+            warn_on_implicit_abort: false,
+            max_key_size: None,
+            max_value_size: None,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
         }
     }
 
@@ -67,6 +108,35 @@ impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
         self
     }
 
+    fn set_read_only(&mut self, read_only: bool) -> &mut Self {
+        self.flags.set(EnvironmentFlagsImpl::READ_ONLY, read_only);
+        self
+    }
+
+    fn set_durability(&mut self, durability: Durability) -> &mut Self {
+        self.flags.set(
+            EnvironmentFlagsImpl::NO_SYNC,
+            durability == Durability::NoSync,
+        );
+        self
+    }
+
+    /// A no-op, since this backend doesn't memory-map its file.
+    fn set_writemap(&mut self, writemap: bool) -> &mut Self {
+        if writemap {
+            warn!("Ignoring `writemap={}`", writemap);
+        }
+        self
+    }
+
+    /// A no-op, since this backend doesn't memory-map its file.
+    fn set_map_async(&mut self, map_async: bool) -> &mut Self {
+        if map_async {
+            warn!("Ignoring `map_async={}`", map_async);
+        }
+        self
+    }
+
     fn set_max_readers(&mut self, max_readers: u32) -> &mut Self {
         self.max_readers = Some(max_readers as usize);
         self
@@ -87,11 +157,31 @@ impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
         self
     }
 
+    /// A no-op, since this backend has no map that could fill up.
+    fn set_auto_resize(&mut self, _auto_resize: bool) -> &mut Self {
+        self
+    }
+
     fn set_corruption_recovery_strategy(&mut self, strategy: RecoveryStrategy) -> &mut Self {
         self.corruption_recovery_strategy = strategy;
         self
     }
 
+    fn set_warn_on_implicit_abort(&mut self, warn: bool) -> &mut Self {
+        self.warn_on_implicit_abort = warn;
+        self
+    }
+
+    fn set_max_value_size(&mut self, max_value_size: usize) -> &mut Self {
+        self.max_value_size = Some(max_value_size);
+        self
+    }
+
+    fn env_exists(&self, path: &Path) -> bool {
+        // The database file is only written by the first commit.
+        path.join(DEFAULT_DB_FILENAME).is_file()
+    }
+
     fn open(&self, path: &Path) -> Result<Self::Environment, Self::Error> {
         // Technically NO_SUB_DIR should change these checks here, but they're both currently
         // unimplemented with this storage backend.
@@ -108,6 +198,13 @@ impl<'b> BackendEnvironmentBuilder<'b> for EnvironmentBuilderImpl {
             self.max_dbs,
             self.map_size,
         )?;
+        env.warn_on_implicit_abort = self.warn_on_implicit_abort;
+        env.max_key_size = self.max_key_size;
+        env.max_value_size = self.max_value_size;
+        #[cfg(feature = "encryption")]
+        {
+            env.encryption_key = self.encryption_key;
+        }
         env.read_from_disk(self.corruption_recovery_strategy)?;
         Ok(env)
     }
@@ -126,7 +223,7 @@ pub(crate) struct EnvironmentDbsRefMut<'a> {
 }
 
 impl<'a> From<&'a mut EnvironmentDbs> for EnvironmentDbsRefMut<'a> {
-    fn from(dbs: &mut EnvironmentDbs) -> EnvironmentDbsRefMut {
+    fn from(dbs: &mut EnvironmentDbs) -> EnvironmentDbsRefMut<'_> {
         EnvironmentDbsRefMut {
             arena: &mut dbs.arena,
             name_map: &mut dbs.name_map,
@@ -137,10 +234,20 @@ impl<'a> From<&'a mut EnvironmentDbs> for EnvironmentDbsRefMut<'a> {
 #[derive(Debug)]
 pub struct EnvironmentImpl {
     path: PathBuf,
+    flags: EnvironmentFlagsImpl,
     max_dbs: usize,
+    // The configured size ceiling in bytes, or 0 if none was set. Nothing is
+    // memory-mapped by this backend, so it's only used to compute `load_ratio`.
+    map_size: AtomicUsize,
     dbs: RwLock<EnvironmentDbs>,
     ro_txns: Arc<()>,
     rw_txns: Arc<()>,
+    writer_lock: WriterLock,
+    warn_on_implicit_abort: bool,
+    max_key_size: Option<usize>,
+    max_value_size: Option<usize>,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<EncryptionKey>,
 }
 
 impl EnvironmentImpl {
@@ -154,13 +261,33 @@ impl EnvironmentImpl {
         Ok(bincode::serialize(&data)?)
     }
 
+    /// The bytes of the database file: the serialized databases, encrypted if an
+    /// encryption key is set.
+    fn encode(&self) -> Result<Vec<u8>, ErrorImpl> {
+        let bytes = self.serialize()?;
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.encryption_key {
+            return key.seal(bytes);
+        }
+        Ok(bytes)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<(DatabaseArena, DatabaseNameMap), ErrorImpl> {
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.encryption_key {
+            return Self::deserialize(&key.open(bytes)?);
+        }
+        Self::deserialize(bytes)
+    }
+
     fn load(
+        &self,
         path: &Path,
         strategy: RecoveryStrategy,
     ) -> Result<(DatabaseArena, DatabaseNameMap), ErrorImpl> {
         let bytes = fs::read(path)?;
 
-        match Self::deserialize(&bytes) {
+        match self.decode(&bytes) {
             Ok((arena, name_map)) => Ok((arena, name_map)),
             Err(err) => match strategy {
                 RecoveryStrategy::Error => Err(err),
@@ -195,25 +322,32 @@ impl EnvironmentImpl {
         max_dbs: Option<usize>,
         map_size: Option<usize>,
     ) -> Result<EnvironmentImpl, ErrorImpl> {
-        if !flags.is_empty() {
-            warn!("Ignoring `flags={:?}`", flags);
+        let unsupported =
+            flags.difference(EnvironmentFlagsImpl::READ_ONLY | EnvironmentFlagsImpl::NO_SYNC);
+        if !unsupported.is_empty() {
+            warn!("Ignoring `flags={:?}`", unsupported);
         }
         if let Some(max_readers) = max_readers {
             warn!("Ignoring `max_readers={}`", max_readers);
         }
-        if let Some(map_size) = map_size {
-            warn!("Ignoring `map_size={}`", map_size);
-        }
 
         Ok(EnvironmentImpl {
             path: path.to_path_buf(),
-            max_dbs: max_dbs.unwrap_or(std::usize::MAX),
+            flags,
+            max_dbs: max_dbs.unwrap_or(usize::MAX),
+            map_size: AtomicUsize::new(map_size.unwrap_or(0)),
             dbs: RwLock::new(EnvironmentDbs {
                 arena: DatabaseArena::new(),
                 name_map: HashMap::new(),
             }),
             ro_txns: Arc::new(()),
             rw_txns: Arc::new(()),
+            writer_lock: WriterLock::default(),
+            warn_on_implicit_abort: false,
+            max_key_size: None,
+            max_value_size: None,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
         })
     }
 
@@ -225,12 +359,14 @@ impl EnvironmentImpl {
         if fs::metadata(&path).is_err() {
             return Ok(());
         };
-        let (arena, name_map) = Self::load(&path, strategy)?;
+        let (arena, name_map) = self.load(&path, strategy)?;
         self.dbs = RwLock::new(EnvironmentDbs { arena, name_map });
         Ok(())
     }
 
-    pub(crate) fn write_to_disk(&self) -> Result<(), ErrorImpl> {
+    /// Write the databases to disk. With `sync`, also wait until the operating system
+    /// has flushed them to the device.
+    pub(crate) fn write_to_disk(&self, sync: bool) -> Result<(), ErrorImpl> {
         let mut path = Cow::from(&self.path);
         if fs::metadata(&path)?.is_dir() {
             path.to_mut().push(DEFAULT_DB_FILENAME);
@@ -238,18 +374,67 @@ impl EnvironmentImpl {
 
         // Write to a temp file first.
         let tmp_path = path.with_extension("tmp");
-        fs::write(&tmp_path, self.serialize()?)?;
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&self.encode()?)?;
+        if sync {
+            file.sync_all()?;
+        }
+        drop(file);
 
         // Atomically move that file to the database file.
-        fs::rename(tmp_path, path)?;
+        fs::rename(tmp_path, &path)?;
+
+        // The rename itself is only durable once the directory is synced too.
+        #[cfg(unix)]
+        if sync {
+            if let Some(dir) = path.parent() {
+                fs::File::open(dir)?.sync_all()?;
+            }
+        }
         Ok(())
     }
 
-    pub(crate) fn dbs(&self) -> Result<RwLockReadGuard<EnvironmentDbs>, ErrorImpl> {
+    /// Whether commits are only written to disk by `sync`.
+    pub(crate) fn defers_writes(&self) -> bool {
+        self.flags.contains(EnvironmentFlagsImpl::NO_SYNC)
+    }
+
+    /// Rebuild the arena from the databases that are still named, dropping the entries
+    /// that `drop_db` left behind, and rewrite the database file.
+    pub(crate) fn compact(&self) -> Result<(), ErrorImpl> {
+        if self.is_read_only() {
+            return Err(ErrorImpl::EnvIsReadOnly);
+        }
+        if Arc::strong_count(&self.ro_txns) > 1 || Arc::strong_count(&self.rw_txns) > 1 {
+            return Err(ErrorImpl::DbsIllegalOpen);
+        }
+        let mut dbs = self.dbs_mut()?;
+        let mut arena = DatabaseArena::new();
+        let mut name_map = HashMap::new();
+        for (name, id) in &dbs.name_map {
+            let db = dbs.arena.get(id.0).ok_or(ErrorImpl::DbIsForeignError)?;
+            let id = arena.alloc(Database::new(None, Some(db.snapshot())));
+            name_map.insert(name.clone(), DatabaseImpl(id));
+        }
+        *dbs = EnvironmentDbs { arena, name_map };
+
+        drop(dbs);
+        self.write_to_disk(false)
+    }
+
+    pub(crate) fn max_key_size(&self) -> Option<usize> {
+        self.max_key_size
+    }
+
+    pub(crate) fn max_value_size(&self) -> Option<usize> {
+        self.max_value_size
+    }
+
+    pub(crate) fn dbs(&self) -> Result<RwLockReadGuard<'_, EnvironmentDbs>, ErrorImpl> {
         self.dbs.read().map_err(|_| ErrorImpl::EnvPoisonError)
     }
 
-    pub(crate) fn dbs_mut(&self) -> Result<RwLockWriteGuard<EnvironmentDbs>, ErrorImpl> {
+    pub(crate) fn dbs_mut(&self) -> Result<RwLockWriteGuard<'_, EnvironmentDbs>, ErrorImpl> {
         self.dbs.write().map_err(|_| ErrorImpl::EnvPoisonError)
     }
 }
@@ -263,11 +448,35 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
     type RwTransaction = RwTransactionImpl<'e>;
     type Stat = StatImpl;
 
+    fn backend_name(&self) -> &'static str {
+        "safe mode"
+    }
+
     fn get_dbs(&self) -> Result<Vec<Option<String>>, Self::Error> {
         let dbs = self.dbs.read().map_err(|_| ErrorImpl::EnvPoisonError)?;
         Ok(dbs.name_map.keys().map(|key| key.to_owned()).collect())
     }
 
+    fn shared_default_db(&self) -> Result<Option<Self::Database>, Self::Error> {
+        Ok(None)
+    }
+
+    fn db_entries(&self, name: Option<&str>) -> Result<usize, Self::Error> {
+        let key = name.map(String::from);
+        let dbs = self.dbs()?;
+        let id = dbs.name_map.get(&key).ok_or(ErrorImpl::DbNotFoundError)?;
+        let db = dbs.arena.get(id.0).ok_or(ErrorImpl::DbIsForeignError)?;
+        Ok(db.entries())
+    }
+
+    fn db_flags(&self, name: Option<&str>) -> Result<Self::Flags, Self::Error> {
+        let key = name.map(String::from);
+        let dbs = self.dbs()?;
+        let id = dbs.name_map.get(&key).ok_or(ErrorImpl::DbNotFoundError)?;
+        let db = dbs.arena.get(id.0).ok_or(ErrorImpl::DbIsForeignError)?;
+        Ok(db.flags())
+    }
+
     fn open_db(&self, name: Option<&str>) -> Result<Self::Database, Self::Error> {
         if Arc::strong_count(&self.ro_txns) > 1 {
             return Err(ErrorImpl::DbsIllegalOpen);
@@ -284,6 +493,14 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
         name: Option<&str>,
         flags: Self::Flags,
     ) -> Result<Self::Database, Self::Error> {
+        self.open_or_create_db(name, flags).map(|(db, _)| db)
+    }
+
+    fn open_or_create_db(
+        &self,
+        name: Option<&str>,
+        flags: Self::Flags,
+    ) -> Result<(Self::Database, bool), Self::Error> {
         if Arc::strong_count(&self.ro_txns) > 1 {
             return Err(ErrorImpl::DbsIllegalOpen);
         }
@@ -297,10 +514,81 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
         let parts = EnvironmentDbsRefMut::from(dbs.deref_mut());
         let arena = parts.arena;
         let name_map = parts.name_map;
+        let created = !name_map.contains_key(&key);
         let id = name_map
             .entry(key)
             .or_insert_with(|| DatabaseImpl(arena.alloc(Database::new(Some(flags), None))));
-        Ok(*id)
+        let db = arena.get(id.0).ok_or(ErrorImpl::DbIsForeignError)?;
+        if db.flags() != flags {
+            return Err(ErrorImpl::DbIncompatibleFlags);
+        }
+        Ok((*id, created))
+    }
+
+    fn rename_db(
+        &self,
+        old: &str,
+        new: &str,
+        companions: &[(&str, &str)],
+    ) -> Result<(), Self::Error> {
+        if self.is_read_only() {
+            return Err(ErrorImpl::EnvIsReadOnly);
+        }
+        // Database handles are arena ids, so they remain valid under the new name.
+        let mut dbs = self.dbs_mut()?;
+        let old = Some(old.to_string());
+        if !dbs.name_map.contains_key(&old) {
+            return Err(ErrorImpl::DbNotFoundError);
+        }
+        let mut renames = vec![(old, Some(new.to_string()))];
+        for (old, new) in companions {
+            let old = Some(old.to_string());
+            if dbs.name_map.contains_key(&old) {
+                renames.push((old, Some(new.to_string())));
+            }
+        }
+        if renames
+            .iter()
+            .any(|(_, new)| dbs.name_map.contains_key(new))
+        {
+            return Err(ErrorImpl::KeyValuePairExists);
+        }
+        for (old, new) in renames {
+            if let Some(id) = dbs.name_map.remove(&old) {
+                dbs.name_map.insert(new, id);
+            }
+        }
+
+        drop(dbs);
+        if self.defers_writes() {
+            return Ok(());
+        }
+        self.write_to_disk(false)
+    }
+
+    fn drop_db(&self, name: &str, companions: &[&str]) -> Result<(), Self::Error> {
+        if self.is_read_only() {
+            return Err(ErrorImpl::EnvIsReadOnly);
+        }
+        // Arenas can't free their entries, so only the pairs of the databases are freed.
+        let mut dbs = self.dbs_mut()?;
+        let name = Some(name.to_string());
+        if !dbs.name_map.contains_key(&name) {
+            return Err(ErrorImpl::DbNotFoundError);
+        }
+        let companions = companions.iter().map(|name| Some(name.to_string()));
+        for name in std::iter::once(name).chain(companions) {
+            if let Some(id) = dbs.name_map.remove(&name) {
+                let db = dbs.arena.get_mut(id.0).ok_or(ErrorImpl::DbIsForeignError)?;
+                db.clear();
+            }
+        }
+
+        drop(dbs);
+        if self.defers_writes() {
+            return Ok(());
+        }
+        self.write_to_disk(false)
     }
 
     fn begin_ro_txn(&'e self) -> Result<Self::RoTransaction, Self::Error> {
@@ -308,12 +596,56 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
     }
 
     fn begin_rw_txn(&'e self) -> Result<Self::RwTransaction, Self::Error> {
-        RwTransactionImpl::new(self, self.rw_txns.clone())
+        if self.is_read_only() {
+            return Err(ErrorImpl::EnvIsReadOnly);
+        }
+        RwTransactionImpl::new(self, self.rw_txns.clone(), self.writer_lock.lock())
+    }
+
+    fn try_begin_rw_txn(&'e self) -> Result<Option<Self::RwTransaction>, Self::Error> {
+        if self.is_read_only() {
+            return Err(ErrorImpl::EnvIsReadOnly);
+        }
+        match self.writer_lock.try_lock() {
+            Some(guard) => RwTransactionImpl::new(self, self.rw_txns.clone(), guard).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn begin_rw_txn_timeout(
+        &'e self,
+        timeout: Duration,
+    ) -> Result<Option<Self::RwTransaction>, Self::Error> {
+        if self.is_read_only() {
+            return Err(ErrorImpl::EnvIsReadOnly);
+        }
+        match self.writer_lock.lock_timeout(timeout) {
+            Some(guard) => RwTransactionImpl::new(self, self.rw_txns.clone(), guard).map(Some),
+            None => Ok(None),
+        }
     }
 
     fn sync(&self, force: bool) -> Result<(), Self::Error> {
-        warn!("Ignoring `force={}`", force);
-        self.write_to_disk()
+        self.write_to_disk(force)
+    }
+
+    fn copy_to(&self, path: &Path, compact: bool) -> Result<(), Self::Error> {
+        warn!("Ignoring `compact={}`", compact);
+        let mut path = Cow::from(path);
+        if fs::metadata(&self.path)?.is_dir() {
+            fs::create_dir_all(&path)?;
+            path.to_mut().push(DEFAULT_DB_FILENAME);
+        };
+
+        // Only committed transactions are ever visible here, so serializing
+        // the databases yields a consistent snapshot.
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        file.write_all(&self.encode()?)?;
+        file.sync_all()?;
+        Ok(())
     }
 
     fn stat(&self) -> Result<Self::Stat, Self::Error> {
@@ -324,20 +656,52 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
         Ok(InfoImpl)
     }
 
+    fn env_info(&self) -> Result<EnvInfo, Self::Error> {
+        Ok(EnvInfo {
+            map_size: self.map_size()?,
+            last_pgno: None,
+            size: self.serialize()?.len(),
+            // Each reader holds a clone of `ro_txns`.
+            num_readers: Arc::strong_count(&self.ro_txns) - 1,
+            max_readers: None,
+            num_dbs: self.get_dbs()?.len(),
+        })
+    }
+
     fn freelist(&self) -> Result<usize, Self::Error> {
-        unimplemented!()
+        // There are no pages, so nothing is ever waiting to be reused.
+        Ok(0)
+    }
+
+    fn reader_info(&self) -> Result<ReaderInfo, Self::Error> {
+        Ok(ReaderInfo {
+            // Each reader holds a clone of `ro_txns`.
+            in_use: Arc::strong_count(&self.ro_txns) - 1,
+            max: None,
+        })
+    }
+
+    fn check_stale_readers(&self) -> Result<usize, Self::Error> {
+        // Readers only exist in this process, so they can't be stale.
+        Ok(0)
     }
 
     fn load_ratio(&self) -> Result<Option<f32>, Self::Error> {
-        warn!("`load_ratio()` is irrelevant for this storage backend.");
-        Ok(None)
+        match self.map_size()? {
+            Some(map_size) => Ok(Some(self.serialize()?.len() as f32 / map_size as f32)),
+            None => Ok(None),
+        }
+    }
+
+    fn map_size(&self) -> Result<Option<usize>, Self::Error> {
+        match self.map_size.load(Ordering::SeqCst) {
+            0 => Ok(None),
+            map_size => Ok(Some(map_size)),
+        }
     }
 
     fn set_map_size(&self, size: usize) -> Result<(), Self::Error> {
-        warn!(
-            "`set_map_size({})` is ignored by this storage backend.",
-            size
-        );
+        self.map_size.store(size, Ordering::SeqCst);
         Ok(())
     }
 
@@ -348,4 +712,12 @@ impl<'e> BackendEnvironment<'e> for EnvironmentImpl {
         db_filename.push(DEFAULT_DB_FILENAME);
         vec![db_filename]
     }
+
+    fn is_read_only(&self) -> bool {
+        self.flags.contains(EnvironmentFlagsImpl::READ_ONLY)
+    }
+
+    fn warns_on_implicit_abort(&self) -> bool {
+        self.warn_on_implicit_abort
+    }
 }
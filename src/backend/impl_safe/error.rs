@@ -12,19 +12,43 @@ use std::{fmt, io, path::PathBuf};
 
 use bincode::Error as BincodeError;
 
-use crate::{backend::traits::BackendError, error::StoreError};
+use crate::{
+    backend::traits::BackendError,
+    error::{DataError, StoreError},
+};
 
 #[derive(Debug)]
 pub enum ErrorImpl {
     KeyValuePairNotFound,
+    KeyValuePairExists,
     EnvPoisonError,
     DbsFull,
     DbsIllegalOpen,
     DbNotFoundError,
     DbIsForeignError,
+    DbIncompatibleFlags,
+    EnvIsReadOnly,
     UnsuitableEnvironmentPath(PathBuf),
     IoError(io::Error),
     BincodeError(BincodeError),
+    ValueTooLarge {
+        size: usize,
+        max: usize,
+    },
+    #[cfg(feature = "encryption")]
+    DecryptionError,
+}
+
+/// Fail if `value` is longer than `max` bytes, so that a value corrupted or written
+/// without the limit is never decoded.
+pub(crate) fn check_value_size(value: &[u8], max: Option<usize>) -> Result<&[u8], ErrorImpl> {
+    match max {
+        Some(max) if value.len() > max => Err(ErrorImpl::ValueTooLarge {
+            size: value.len(),
+            max,
+        }),
+        _ => Ok(value),
+    }
 }
 
 impl BackendError for ErrorImpl {}
@@ -33,16 +57,22 @@ impl fmt::Display for ErrorImpl {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ErrorImpl::KeyValuePairNotFound => write!(fmt, "KeyValuePairNotFound (safe mode)"),
+            ErrorImpl::KeyValuePairExists => write!(fmt, "KeyValuePairExists (safe mode)"),
             ErrorImpl::EnvPoisonError => write!(fmt, "EnvPoisonError (safe mode)"),
             ErrorImpl::DbsFull => write!(fmt, "DbsFull (safe mode)"),
             ErrorImpl::DbsIllegalOpen => write!(fmt, "DbIllegalOpen (safe mode)"),
             ErrorImpl::DbNotFoundError => write!(fmt, "DbNotFoundError (safe mode)"),
             ErrorImpl::DbIsForeignError => write!(fmt, "DbIsForeignError (safe mode)"),
+            ErrorImpl::DbIncompatibleFlags => write!(fmt, "DbIncompatibleFlags (safe mode)"),
+            ErrorImpl::EnvIsReadOnly => write!(fmt, "EnvIsReadOnly (safe mode)"),
             ErrorImpl::UnsuitableEnvironmentPath(_) => {
                 write!(fmt, "UnsuitableEnvironmentPath (safe mode)")
             }
             ErrorImpl::IoError(e) => e.fmt(fmt),
             ErrorImpl::BincodeError(e) => e.fmt(fmt),
+            ErrorImpl::ValueTooLarge { .. } => write!(fmt, "ValueTooLarge (safe mode)"),
+            #[cfg(feature = "encryption")]
+            ErrorImpl::DecryptionError => write!(fmt, "DecryptionError (safe mode)"),
         }
     }
 }
@@ -55,12 +85,20 @@ impl Into<StoreError> for ErrorImpl {
         // unimplemented yet, but they should be in the future.
         match self {
             ErrorImpl::KeyValuePairNotFound => StoreError::KeyValuePairNotFound,
+            ErrorImpl::KeyValuePairExists => StoreError::KeyValuePairExists,
             ErrorImpl::BincodeError(_) => StoreError::FileInvalid,
             ErrorImpl::DbsFull => StoreError::DbsFull,
+            ErrorImpl::DbIncompatibleFlags => StoreError::IncompatibleFlags,
+            ErrorImpl::EnvIsReadOnly => StoreError::EnvironmentReadOnly,
             ErrorImpl::UnsuitableEnvironmentPath(path) => {
                 StoreError::UnsuitableEnvironmentPath(path)
             }
             ErrorImpl::IoError(error) => StoreError::IoError(error),
+            ErrorImpl::ValueTooLarge { size, max } => {
+                StoreError::DataError(DataError::ValueTooLarge { size, max })
+            }
+            #[cfg(feature = "encryption")]
+            ErrorImpl::DecryptionError => StoreError::DecryptionFailed,
             _ => StoreError::SafeModeError(self),
         }
     }
@@ -20,6 +20,8 @@ bitflags! {
     #[derive(Default, Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
     pub struct EnvironmentFlagsImpl: u32 {
         const NIL = 0b0000_0000;
+        const READ_ONLY = 0b0000_0001;
+        const NO_SYNC = 0b0000_0010;
     }
 }
 
@@ -41,11 +43,12 @@ impl Into<EnvironmentFlagsImpl> for EnvironmentFlags {
             EnvironmentFlags::FIXED_MAP => unimplemented!(),
             EnvironmentFlags::NO_SUB_DIR => unimplemented!(),
             EnvironmentFlags::WRITE_MAP => unimplemented!(),
-            EnvironmentFlags::READ_ONLY => unimplemented!(),
+            EnvironmentFlags::READ_ONLY => EnvironmentFlagsImpl::READ_ONLY,
             EnvironmentFlags::NO_META_SYNC => unimplemented!(),
-            EnvironmentFlags::NO_SYNC => unimplemented!(),
+            EnvironmentFlags::NO_SYNC => EnvironmentFlagsImpl::NO_SYNC,
             EnvironmentFlags::MAP_ASYNC => unimplemented!(),
-            EnvironmentFlags::NO_TLS => unimplemented!(),
+            // Readers aren't tied to threads anyway, so nested reads always work.
+            EnvironmentFlags::NO_TLS => EnvironmentFlagsImpl::NIL,
             EnvironmentFlags::NO_LOCK => unimplemented!(),
             EnvironmentFlags::NO_READAHEAD => unimplemented!(),
             EnvironmentFlags::NO_MEM_INIT => unimplemented!(),
@@ -74,6 +77,10 @@ impl BackendDatabaseFlags for DatabaseFlagsImpl {
     fn set(&mut self, flag: DatabaseFlags, value: bool) {
         self.set(flag.into(), value)
     }
+
+    fn get(&self, flag: DatabaseFlags) -> bool {
+        self.contains(flag.into())
+    }
 }
 
 impl Into<DatabaseFlagsImpl> for DatabaseFlags {
@@ -96,6 +103,8 @@ bitflags! {
     #[derive(Default, Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
     pub struct WriteFlagsImpl: u32 {
         const NIL = 0b0000_0000;
+        const APPEND = 0b0000_0001;
+        const NO_OVERWRITE = 0b0000_0010;
     }
 }
 
@@ -114,10 +123,10 @@ impl BackendWriteFlags for WriteFlagsImpl {
 impl Into<WriteFlagsImpl> for WriteFlags {
     fn into(self) -> WriteFlagsImpl {
         match self {
-            WriteFlags::NO_OVERWRITE => unimplemented!(),
+            WriteFlags::NO_OVERWRITE => WriteFlagsImpl::NO_OVERWRITE,
             WriteFlags::NO_DUP_DATA => unimplemented!(),
             WriteFlags::CURRENT => unimplemented!(),
-            WriteFlags::APPEND => unimplemented!(),
+            WriteFlags::APPEND => WriteFlagsImpl::APPEND,
             WriteFlags::APPEND_DUP => unimplemented!(),
         }
     }
@@ -8,17 +8,33 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
-use super::ErrorImpl;
+use super::{error::check_value_size, ErrorImpl};
 use crate::backend::traits::BackendIter;
 
 // FIXME: Use generics instead.
-pub struct IterImpl<'i>(pub(crate) Box<dyn Iterator<Item = (&'i [u8], &'i [u8])> + 'i>);
+pub struct IterImpl<'i> {
+    iter: Box<dyn Iterator<Item = (&'i [u8], &'i [u8])> + 'i>,
+    max_value_size: Option<usize>,
+}
+
+impl<'i> IterImpl<'i> {
+    pub(crate) fn new(
+        iter: Box<dyn Iterator<Item = (&'i [u8], &'i [u8])> + 'i>,
+        max_value_size: Option<usize>,
+    ) -> IterImpl<'i> {
+        IterImpl {
+            iter,
+            max_value_size,
+        }
+    }
+}
 
 impl<'i> BackendIter<'i> for IterImpl<'i> {
     type Error = ErrorImpl;
 
     #[allow(clippy::type_complexity)]
     fn next(&mut self) -> Option<Result<(&'i [u8], &'i [u8]), Self::Error>> {
-        self.0.next().map(Ok)
+        let (key, value) = self.iter.next()?;
+        Some(check_value_size(value, self.max_value_size).map(|value| (key, value)))
     }
 }
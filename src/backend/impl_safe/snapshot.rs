@@ -10,16 +10,22 @@
 
 use std::{
     collections::{BTreeMap, BTreeSet},
+    ops::{Bound, RangeBounds},
     sync::Arc,
 };
 
 use serde_derive::{Deserialize, Serialize};
 
 use super::DatabaseFlagsImpl;
+use crate::backend::common::CursorOp;
 
 type Key = Box<[u8]>;
 type Value = Box<[u8]>;
 
+/// The contents of a database as seen by a transaction. Cloning one only clones the
+/// `Arc` of its map, so beginning a transaction doesn't depend on the size of the
+/// databases. The map itself is only copied by the first write to a clone that still
+/// shares it, and committing swaps the written clone into the database.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Snapshot {
     flags: DatabaseFlagsImpl,
@@ -44,6 +50,73 @@ impl Snapshot {
     pub(crate) fn clear(&mut self) {
         self.map = Default::default();
     }
+
+    pub(crate) fn keys(&self) -> impl DoubleEndedIterator<Item = &[u8]> {
+        self.map.keys().map(|key| key.as_ref())
+    }
+
+    /// Call `f` with every pair within the given bounds, in order.
+    pub(crate) fn scan_range<F>(&self, from: Bound<&[u8]>, to: Bound<&[u8]>, mut f: F)
+    where
+        F: FnMut(&[u8], &[u8]),
+    {
+        for (key, value) in self.range(from, Bound::Unbounded) {
+            if !(Bound::Unbounded, to).contains(key) {
+                break;
+            }
+            f(key, value);
+        }
+    }
+
+    /// Delete every key within the given bounds that has a value for which `pred`
+    /// returns true, returning their keys.
+    pub(crate) fn del_range<F>(
+        &mut self,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+        mut pred: F,
+    ) -> Vec<Vec<u8>>
+    where
+        F: FnMut(&[u8], &[u8]) -> bool,
+    {
+        let mut keys: Vec<Vec<u8>> = vec![];
+        self.scan_range(from, to, |key, value| {
+            if pred(key, value) && keys.last().map_or(true, |last| last.as_slice() != key) {
+                keys.push(key.to_vec());
+            }
+        });
+        let map = Arc::make_mut(&mut self.map);
+        for key in &keys {
+            map.remove(key.as_slice());
+        }
+        keys
+    }
+
+    /// The entry that a cursor currently at `position` ends up at after `op`.
+    pub(crate) fn seek<'s>(
+        &'s self,
+        position: Option<(&[u8], &[u8])>,
+        op: CursorOp<'_>,
+    ) -> Option<(&'s [u8], &'s [u8])> {
+        use Bound::{Included, Unbounded};
+        match (op, position) {
+            (CursorOp::First, _) | (CursorOp::Next, None) => {
+                self.range(Unbounded, Unbounded).next()
+            }
+            (CursorOp::Last, _) | (CursorOp::Prev, None) => {
+                self.range(Unbounded, Unbounded).next_back()
+            }
+            (CursorOp::Next, Some((key, value))) => self
+                .range(Included(key), Unbounded)
+                .find(|&(k, v)| k != key || v > value),
+            (CursorOp::Prev, Some((key, value))) => self
+                .range(Unbounded, Included(key))
+                .rev()
+                .find(|&(k, v)| k != key || v < value),
+            (CursorOp::SeekExact(key), _) => self.range(Included(key), Included(key)).next(),
+            (CursorOp::SeekRange(key), _) => self.range(Included(key), Unbounded).next(),
+        }
+    }
 }
 
 #[cfg(not(feature = "db-dup-sort"))]
@@ -52,6 +125,18 @@ impl Snapshot {
         self.map.get(key).map(|value| value.as_ref())
     }
 
+    pub(crate) fn entries(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Total size of the keys and values in bytes.
+    pub(crate) fn size(&self) -> usize {
+        self.map
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum()
+    }
+
     pub(crate) fn put(&mut self, key: &[u8], value: &[u8]) {
         let map = Arc::make_mut(&mut self.map);
         map.insert(Box::from(key), Box::from(value));
@@ -67,6 +152,16 @@ impl Snapshot {
             .iter()
             .map(|(key, value)| (key.as_ref(), value.as_ref()))
     }
+
+    pub(crate) fn range(
+        &self,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+    ) -> impl DoubleEndedIterator<Item = (&[u8], &[u8])> {
+        self.map
+            .range::<[u8], _>((from, to))
+            .map(|(key, value)| (key.as_ref(), value.as_ref()))
+    }
 }
 
 #[cfg(feature = "db-dup-sort")]
@@ -78,6 +173,18 @@ impl Snapshot {
             .map(|v| v.as_ref())
     }
 
+    pub(crate) fn entries(&self) -> usize {
+        self.map.values().map(|values| values.len()).sum()
+    }
+
+    /// Total size of the keys and values in bytes, counting a key once per value.
+    pub(crate) fn size(&self) -> usize {
+        self.map
+            .iter()
+            .flat_map(|(key, values)| values.iter().map(move |value| key.len() + value.len()))
+            .sum()
+    }
+
     pub(crate) fn put(&mut self, key: &[u8], value: &[u8]) {
         let map = Arc::make_mut(&mut self.map);
         match map.get_mut(key) {
@@ -110,6 +217,20 @@ impl Snapshot {
             .iter()
             .map(|(key, values)| (key.as_ref(), values.iter().map(|value| value.as_ref())))
     }
+
+    pub(crate) fn range(
+        &self,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+    ) -> impl DoubleEndedIterator<Item = (&[u8], &[u8])> {
+        self.map
+            .range::<[u8], _>((from, to))
+            .flat_map(|(key, values)| {
+                values
+                    .iter()
+                    .map(move |value| (key.as_ref(), value.as_ref()))
+            })
+    }
 }
 
 #[cfg(feature = "db-dup-sort")]
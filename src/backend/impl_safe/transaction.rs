@@ -9,16 +9,30 @@
 // specific language governing permissions and limitations under the License.
 #![allow(dead_code)] // TODO: Get rid of unused struct members
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, ops::Bound, sync::Arc};
 
 use super::{
-    snapshot::Snapshot, DatabaseImpl, EnvironmentImpl, ErrorImpl, RoCursorImpl, WriteFlagsImpl,
+    error::check_value_size, snapshot::Snapshot, DatabaseFlagsImpl, DatabaseImpl, EnvironmentImpl,
+    ErrorImpl, RoCursorImpl, WriteFlagsImpl,
 };
-use crate::backend::traits::{
-    BackendRoCursorTransaction, BackendRoTransaction, BackendRwCursorTransaction,
-    BackendRwTransaction,
+use crate::backend::{
+    common::DatabaseStat,
+    lock::WriterGuard,
+    traits::{
+        BackendRoCursorTransaction, BackendRoRenewTransaction, BackendRoTransaction,
+        BackendRwCursorTransaction, BackendRwNestedTransaction, BackendRwTransaction,
+    },
 };
 
+fn snapshots(env: &EnvironmentImpl) -> Result<HashMap<DatabaseImpl, Snapshot>, ErrorImpl> {
+    Ok(env
+        .dbs()?
+        .arena
+        .iter()
+        .map(|(id, db)| (DatabaseImpl(id), db.snapshot()))
+        .collect())
+}
+
 #[derive(Debug)]
 pub struct RoTransactionImpl<'t> {
     env: &'t EnvironmentImpl,
@@ -31,18 +45,16 @@ impl<'t> RoTransactionImpl<'t> {
         env: &'t EnvironmentImpl,
         idx: Arc<()>,
     ) -> Result<RoTransactionImpl<'t>, ErrorImpl> {
-        let snapshots = env
-            .dbs()?
-            .arena
-            .iter()
-            .map(|(id, db)| (DatabaseImpl(id), db.snapshot()))
-            .collect();
         Ok(RoTransactionImpl {
             env,
-            snapshots,
+            snapshots: snapshots(env)?,
             idx,
         })
     }
+
+    fn max_value_size(&self) -> Option<usize> {
+        self.env.max_value_size()
+    }
 }
 
 impl<'t> BackendRoTransaction for RoTransactionImpl<'t> {
@@ -51,7 +63,22 @@ impl<'t> BackendRoTransaction for RoTransactionImpl<'t> {
 
     fn get(&self, db: &Self::Database, key: &[u8]) -> Result<&[u8], Self::Error> {
         let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
-        snapshot.get(key).ok_or(ErrorImpl::KeyValuePairNotFound)
+        let value = snapshot.get(key).ok_or(ErrorImpl::KeyValuePairNotFound)?;
+        check_value_size(value, self.max_value_size())
+    }
+
+    fn stat(&self, db: &Self::Database) -> Result<DatabaseStat, Self::Error> {
+        let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
+        Ok(DatabaseStat {
+            entries: snapshot.entries(),
+            size: snapshot.size(),
+            ..Default::default()
+        })
+    }
+
+    fn db_flags(&self, db: &Self::Database) -> Result<DatabaseFlagsImpl, Self::Error> {
+        let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
+        Ok(*snapshot.flags())
     }
 
     fn abort(self) {
@@ -59,12 +86,79 @@ impl<'t> BackendRoTransaction for RoTransactionImpl<'t> {
     }
 }
 
+impl<'t> BackendRoRenewTransaction for RoTransactionImpl<'t> {
+    fn renew(&mut self) -> Result<(), Self::Error> {
+        self.snapshots = snapshots(self.env)?;
+        Ok(())
+    }
+}
+
 impl<'t> BackendRoCursorTransaction<'t> for RoTransactionImpl<'t> {
     type RoCursor = RoCursorImpl<'t>;
 
     fn open_ro_cursor(&'t self, db: &Self::Database) -> Result<Self::RoCursor, Self::Error> {
         let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
-        Ok(RoCursorImpl(snapshot))
+        Ok(RoCursorImpl::new(snapshot, self.max_value_size()))
+    }
+}
+
+/// Like a read transaction, but owns its snapshots of the databases instead of
+/// borrowing the environment, so it can outlive it or be sent to another thread.
+/// Doesn't count as an open reader.
+#[derive(Debug, Clone)]
+pub struct RoSnapshotImpl {
+    snapshots: HashMap<DatabaseImpl, Snapshot>,
+    max_value_size: Option<usize>,
+}
+
+impl RoSnapshotImpl {
+    pub(crate) fn new(env: &EnvironmentImpl) -> Result<RoSnapshotImpl, ErrorImpl> {
+        Ok(RoSnapshotImpl {
+            snapshots: snapshots(env)?,
+            max_value_size: env.max_value_size(),
+        })
+    }
+
+    fn max_value_size(&self) -> Option<usize> {
+        self.max_value_size
+    }
+}
+
+impl BackendRoTransaction for RoSnapshotImpl {
+    type Database = DatabaseImpl;
+    type Error = ErrorImpl;
+
+    fn get(&self, db: &Self::Database, key: &[u8]) -> Result<&[u8], Self::Error> {
+        let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
+        let value = snapshot.get(key).ok_or(ErrorImpl::KeyValuePairNotFound)?;
+        check_value_size(value, self.max_value_size())
+    }
+
+    fn stat(&self, db: &Self::Database) -> Result<DatabaseStat, Self::Error> {
+        let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
+        Ok(DatabaseStat {
+            entries: snapshot.entries(),
+            size: snapshot.size(),
+            ..Default::default()
+        })
+    }
+
+    fn db_flags(&self, db: &Self::Database) -> Result<DatabaseFlagsImpl, Self::Error> {
+        let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
+        Ok(*snapshot.flags())
+    }
+
+    fn abort(self) {
+        // noop
+    }
+}
+
+impl<'t> BackendRoCursorTransaction<'t> for RoSnapshotImpl {
+    type RoCursor = RoCursorImpl<'t>;
+
+    fn open_ro_cursor(&'t self, db: &Self::Database) -> Result<Self::RoCursor, Self::Error> {
+        let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
+        Ok(RoCursorImpl::new(snapshot, self.max_value_size()))
     }
 }
 
@@ -72,26 +166,62 @@ impl<'t> BackendRoCursorTransaction<'t> for RoTransactionImpl<'t> {
 pub struct RwTransactionImpl<'t> {
     env: &'t EnvironmentImpl,
     snapshots: HashMap<DatabaseImpl, Snapshot>,
+    // Only set for nested transactions, which commit into their parent's snapshots
+    // rather than into the environment.
+    parent: Option<&'t mut HashMap<DatabaseImpl, Snapshot>>,
     idx: Arc<()>,
+    // Only `None` for nested transactions, since their parent holds the lock.
+    writer: Option<WriterGuard<'t>>,
 }
 
 impl<'t> RwTransactionImpl<'t> {
     pub(crate) fn new(
         env: &'t EnvironmentImpl,
         idx: Arc<()>,
+        writer: WriterGuard<'t>,
     ) -> Result<RwTransactionImpl<'t>, ErrorImpl> {
-        let snapshots = env
-            .dbs()?
-            .arena
-            .iter()
-            .map(|(id, db)| (DatabaseImpl(id), db.snapshot()))
-            .collect();
         Ok(RwTransactionImpl {
             env,
-            snapshots,
+            snapshots: snapshots(env)?,
+            parent: None,
             idx,
+            writer: Some(writer),
         })
     }
+
+    fn max_value_size(&self) -> Option<usize> {
+        self.env.max_value_size()
+    }
+}
+
+fn check_put_flags(
+    snapshot: &Snapshot,
+    key: &[u8],
+    flags: WriteFlagsImpl,
+) -> Result<(), ErrorImpl> {
+    if flags.contains(WriteFlagsImpl::NO_OVERWRITE) && snapshot.get(key).is_some() {
+        return Err(ErrorImpl::KeyValuePairExists);
+    }
+    if flags.contains(WriteFlagsImpl::APPEND) {
+        // Like LMDB, refuse to append a key that doesn't sort after every existing key.
+        // Integer keys are stored little-endian, so they aren't kept in numeric order and
+        // have to be compared from their most significant byte against every key.
+        #[cfg(feature = "db-int-key")]
+        let integer_key = snapshot.flags().contains(DatabaseFlagsImpl::INTEGER_KEY);
+        #[cfg(not(feature = "db-int-key"))]
+        let integer_key = false;
+        let sorts_last = if integer_key {
+            snapshot
+                .keys()
+                .all(|other| other.iter().rev().lt(key.iter().rev()))
+        } else {
+            snapshot.keys().next_back().map_or(true, |last| last < key)
+        };
+        if !sorts_last {
+            return Err(ErrorImpl::KeyValuePairExists);
+        }
+    }
+    Ok(())
 }
 
 impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
@@ -101,7 +231,22 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
 
     fn get(&self, db: &Self::Database, key: &[u8]) -> Result<&[u8], Self::Error> {
         let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
-        snapshot.get(key).ok_or(ErrorImpl::KeyValuePairNotFound)
+        let value = snapshot.get(key).ok_or(ErrorImpl::KeyValuePairNotFound)?;
+        check_value_size(value, self.max_value_size())
+    }
+
+    fn stat(&self, db: &Self::Database) -> Result<DatabaseStat, Self::Error> {
+        let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
+        Ok(DatabaseStat {
+            entries: snapshot.entries(),
+            size: snapshot.size(),
+            ..Default::default()
+        })
+    }
+
+    fn db_flags(&self, db: &Self::Database) -> Result<DatabaseFlagsImpl, Self::Error> {
+        let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
+        Ok(*snapshot.flags())
     }
 
     #[cfg(not(feature = "db-dup-sort"))]
@@ -110,12 +255,13 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
         db: &Self::Database,
         key: &[u8],
         value: &[u8],
-        _flags: Self::Flags,
+        flags: Self::Flags,
     ) -> Result<(), Self::Error> {
         let snapshot = self
             .snapshots
             .get_mut(db)
             .ok_or_else(|| ErrorImpl::DbIsForeignError)?;
+        check_put_flags(snapshot, key, flags)?;
         snapshot.put(key, value);
         Ok(())
     }
@@ -126,13 +272,13 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
         db: &Self::Database,
         key: &[u8],
         value: &[u8],
-        _flags: Self::Flags,
+        flags: Self::Flags,
     ) -> Result<(), Self::Error> {
-        use super::DatabaseFlagsImpl;
         let snapshot = self
             .snapshots
             .get_mut(db)
             .ok_or(ErrorImpl::DbIsForeignError)?;
+        check_put_flags(snapshot, key, flags)?;
         if snapshot.flags().contains(DatabaseFlagsImpl::DUP_SORT) {
             snapshot.put_dup(key, value);
         } else {
@@ -141,6 +287,22 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
         Ok(())
     }
 
+    fn reserve<F>(
+        &mut self,
+        db: &Self::Database,
+        key: &[u8],
+        len: usize,
+        flags: Self::Flags,
+        fill: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnOnce(&mut [u8]),
+    {
+        let mut value = vec![0; len];
+        fill(&mut value);
+        self.put(db, key, &value, flags)
+    }
+
     #[cfg(not(feature = "db-dup-sort"))]
     fn del(&mut self, db: &Self::Database, key: &[u8]) -> Result<(), Self::Error> {
         let snapshot = self
@@ -158,7 +320,6 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
         key: &[u8],
         value: Option<&[u8]>,
     ) -> Result<(), Self::Error> {
-        use super::DatabaseFlagsImpl;
         let snapshot = self
             .snapshots
             .get_mut(db)
@@ -172,6 +333,38 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
         deleted.ok_or(ErrorImpl::KeyValuePairNotFound)
     }
 
+    fn scan_range<F>(
+        &self,
+        db: &Self::Database,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+        f: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&[u8], &[u8]),
+    {
+        let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
+        snapshot.scan_range(from, to, f);
+        Ok(())
+    }
+
+    fn del_range<F>(
+        &mut self,
+        db: &Self::Database,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+        pred: F,
+    ) -> Result<Vec<Vec<u8>>, Self::Error>
+    where
+        F: FnMut(&[u8], &[u8]) -> bool,
+    {
+        let snapshot = self
+            .snapshots
+            .get_mut(db)
+            .ok_or(ErrorImpl::DbIsForeignError)?;
+        Ok(snapshot.del_range(from, to, pred))
+    }
+
     fn clear_db(&mut self, db: &Self::Database) -> Result<(), Self::Error> {
         let snapshot = self
             .snapshots
@@ -181,7 +374,19 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
         Ok(())
     }
 
+    fn max_key_size(&self) -> Option<usize> {
+        self.env.max_key_size()
+    }
+
+    fn max_value_size(&self) -> Option<usize> {
+        self.env.max_value_size()
+    }
+
     fn commit(self) -> Result<(), Self::Error> {
+        if let Some(parent) = self.parent {
+            *parent = self.snapshots;
+            return Ok(());
+        }
         let mut dbs = self.env.dbs_mut()?;
 
         for (id, snapshot) in self.snapshots {
@@ -190,7 +395,10 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
         }
 
         drop(dbs);
-        self.env.write_to_disk()
+        if self.env.defers_writes() {
+            return Ok(());
+        }
+        self.env.write_to_disk(false)
     }
 
     fn abort(self) {
@@ -198,11 +406,26 @@ impl<'t> BackendRwTransaction for RwTransactionImpl<'t> {
     }
 }
 
+impl<'p, 't> BackendRwNestedTransaction<'p> for RwTransactionImpl<'t> {
+    type Nested = RwTransactionImpl<'p>;
+
+    fn begin_nested_txn(&'p mut self) -> Result<Self::Nested, Self::Error> {
+        // Snapshots share their data until they're written to, so copying them is cheap.
+        Ok(RwTransactionImpl {
+            env: self.env,
+            snapshots: self.snapshots.clone(),
+            parent: Some(&mut self.snapshots),
+            idx: self.idx.clone(),
+            writer: None,
+        })
+    }
+}
+
 impl<'t> BackendRwCursorTransaction<'t> for RwTransactionImpl<'t> {
     type RoCursor = RoCursorImpl<'t>;
 
     fn open_ro_cursor(&'t self, db: &Self::Database) -> Result<Self::RoCursor, Self::Error> {
         let snapshot = self.snapshots.get(db).ok_or(ErrorImpl::DbIsForeignError)?;
-        Ok(RoCursorImpl(snapshot))
+        Ok(RoCursorImpl::new(snapshot, self.max_value_size()))
     }
 }
@@ -0,0 +1,112 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use std::{
+    sync::{Condvar, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+/// Lets a single write transaction at a time into an environment. Unlike LMDB's own
+/// write lock, it can be tried without blocking.
+#[derive(Debug, Default)]
+pub(crate) struct WriterLock {
+    locked: Mutex<bool>,
+    released: Condvar,
+}
+
+impl WriterLock {
+    pub(crate) fn lock(&self) -> WriterGuard<'_> {
+        let locked = self.state();
+        let mut locked = self
+            .released
+            .wait_while(locked, |locked| *locked)
+            .unwrap_or_else(PoisonError::into_inner);
+        *locked = true;
+        WriterGuard(self)
+    }
+
+    pub(crate) fn try_lock(&self) -> Option<WriterGuard<'_>> {
+        let mut locked = self.state();
+        if *locked {
+            return None;
+        }
+        *locked = true;
+        Some(WriterGuard(self))
+    }
+
+    pub(crate) fn lock_timeout(&self, timeout: Duration) -> Option<WriterGuard<'_>> {
+        let locked = self.state();
+        let (mut locked, _) = self
+            .released
+            .wait_timeout_while(locked, timeout, |locked| *locked)
+            .unwrap_or_else(PoisonError::into_inner);
+        if *locked {
+            return None;
+        }
+        *locked = true;
+        Some(WriterGuard(self))
+    }
+
+    // The flag is only ever set and cleared while the mutex is held, so it can't be
+    // left inconsistent by a panic, and poisoning can be ignored.
+    fn state(&self) -> MutexGuard<'_, bool> {
+        self.locked.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Releases the `WriterLock` when dropped, along with the transaction that holds it.
+#[derive(Debug)]
+pub(crate) struct WriterGuard<'l>(&'l WriterLock);
+
+impl<'l> Drop for WriterGuard<'l> {
+    fn drop(&mut self) {
+        *self.0.state() = false;
+        self.0.released.notify_one();
+    }
+}
+
+/// Counts the active read transactions of an environment, since LMDB can only resize
+/// the map while there are none.
+#[cfg(feature = "lmdb")]
+#[derive(Debug, Default)]
+pub(crate) struct ReaderCount(Mutex<usize>);
+
+#[cfg(feature = "lmdb")]
+impl ReaderCount {
+    /// Count a read transaction until the guard is dropped, waiting while new readers
+    /// are excluded.
+    pub(crate) fn enter(&self) -> ReaderGuard<'_> {
+        *self.count() += 1;
+        ReaderGuard(self)
+    }
+
+    /// Keep new readers out until the guard is dropped, or return `None` if there are
+    /// active ones already.
+    pub(crate) fn exclude(&self) -> Option<MutexGuard<'_, usize>> {
+        Some(self.count()).filter(|count| **count == 0)
+    }
+
+    // Like the flag of `WriterLock`, the count is only updated while the mutex is held.
+    fn count(&self) -> MutexGuard<'_, usize> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Stops counting its read transaction when dropped.
+#[cfg(feature = "lmdb")]
+#[derive(Debug)]
+pub(crate) struct ReaderGuard<'l>(&'l ReaderCount);
+
+#[cfg(feature = "lmdb")]
+impl<'l> Drop for ReaderGuard<'l> {
+    fn drop(&mut self) {
+        *self.0.count() -= 1;
+    }
+}
@@ -10,17 +10,24 @@
 
 use std::{
     fmt::{Debug, Display},
+    ops::Bound,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use crate::{
-    backend::common::{DatabaseFlags, EnvironmentFlags, RecoveryStrategy, WriteFlags},
+    backend::common::{
+        CursorOp, DatabaseFlags, DatabaseStat, Durability, EnvInfo, EnvironmentFlags, ReaderInfo,
+        RecoveryStrategy, WriteFlags,
+    },
     error::StoreError,
 };
 
 pub trait BackendError: Debug + Display + Into<StoreError> {}
 
-pub trait BackendDatabase: Debug + Eq + PartialEq + Copy + Clone {}
+pub trait BackendDatabase: Debug + Eq + PartialEq + Copy + Clone {
+    type Flags: BackendDatabaseFlags;
+}
 
 pub trait BackendFlags: Debug + Eq + PartialEq + Copy + Clone + Default {
     fn empty() -> Self;
@@ -32,6 +39,8 @@ pub trait BackendEnvironmentFlags: BackendFlags {
 
 pub trait BackendDatabaseFlags: BackendFlags {
     fn set(&mut self, flag: DatabaseFlags, value: bool);
+
+    fn get(&self, flag: DatabaseFlags) -> bool;
 }
 
 pub trait BackendWriteFlags: BackendFlags {
@@ -75,6 +84,27 @@ pub trait BackendEnvironmentBuilder<'b>: Debug + Eq + PartialEq + Copy + Clone {
     where
         T: Into<Self::Flags>;
 
+    /// Add or remove `EnvironmentFlags::READ_ONLY`, keeping the other flags. Writing to a
+    /// read-only environment fails with `StoreError::EnvironmentReadOnly`.
+    fn set_read_only(&mut self, read_only: bool) -> &mut Self;
+
+    /// Set how much durability commits trade for speed, replacing the
+    /// `EnvironmentFlags::NO_META_SYNC` and `EnvironmentFlags::NO_SYNC` flags.
+    fn set_durability(&mut self, durability: Durability) -> &mut Self;
+
+    /// Write through a writable memory map instead of with system calls, which is
+    /// faster for write-heavy workloads, and commits are just as durable. However, stray
+    /// writes through bad pointers in the process can then corrupt the database, and
+    /// `Writer::begin_nested` isn't supported. Only LMDB supports this: other backends
+    /// log a warning and ignore it.
+    fn set_writemap(&mut self, writemap: bool) -> &mut Self;
+
+    /// With `set_writemap`, flush the map asynchronously when committing, which makes
+    /// commits faster still. The database stays consistent, but a system crash or power
+    /// loss can lose the transactions committed since the last flush, e.g. by `sync`.
+    /// Only LMDB supports this: other backends log a warning and ignore it.
+    fn set_map_async(&mut self, map_async: bool) -> &mut Self;
+
     fn set_max_dbs(&mut self, max_dbs: u32) -> &mut Self;
 
     fn set_max_readers(&mut self, max_readers: u32) -> &mut Self;
@@ -83,9 +113,37 @@ pub trait BackendEnvironmentBuilder<'b>: Debug + Eq + PartialEq + Copy + Clone {
 
     fn set_make_dir_if_needed(&mut self, make_dir_if_needed: bool) -> &mut Self;
 
+    /// Make write transactions grow the map and carry on when it fills up, instead of
+    /// failing with `StoreError::MapFull`. The map size is doubled as often as needed,
+    /// and the changes the transaction made so far are replayed in a new transaction.
+    ///
+    /// This is off by default, because of its memory cost: each write transaction
+    /// keeps a copy of every key and value it writes until it ends, so a transaction
+    /// that writes 100 MB holds on to 100 MB more in memory.
+    ///
+    /// LMDB can't resize the map while read transactions are active, so if readers of
+    /// this environment are active when the map fills up, the write still fails with
+    /// `StoreError::MapFull`. New readers wait while the map is resized. Readers of
+    /// other processes, or of another `Rkv` for the same path, aren't detected, so
+    /// it's up to the consumer to prevent those.
+    fn set_auto_resize(&mut self, auto_resize: bool) -> &mut Self;
+
     /// Set the corruption recovery strategy. See [`RecoveryStrategy`] for details.
     fn set_corruption_recovery_strategy(&mut self, strategy: RecoveryStrategy) -> &mut Self;
 
+    /// Log a warning when a `Writer` with changes is dropped without being committed or
+    /// aborted, which silently discards its changes. Only debug builds check for this.
+    fn set_warn_on_implicit_abort(&mut self, warn: bool) -> &mut Self;
+
+    /// Fail writes of values longer than `max_value_size` bytes, as well as reads of
+    /// such values already stored, e.g. corrupt ones, with `DataError::ValueTooLarge`,
+    /// rather than decoding them. Values aren't limited by default.
+    fn set_max_value_size(&mut self, max_value_size: usize) -> &mut Self;
+
+    /// Whether an environment of this backend exists at `path`, judging by its data
+    /// file, with the path layout this builder would open.
+    fn env_exists(&self, path: &Path) -> bool;
+
     fn open(&self, path: &Path) -> Result<Self::Environment, Self::Error>;
 }
 
@@ -98,8 +156,21 @@ pub trait BackendEnvironment<'e>: Debug {
     type RoTransaction: BackendRoCursorTransaction<'e, Database = Self::Database>;
     type RwTransaction: BackendRwCursorTransaction<'e, Database = Self::Database>;
 
+    fn backend_name(&self) -> &'static str;
+
     fn get_dbs(&self) -> Result<Vec<Option<String>>, Self::Error>;
 
+    /// The default database, if it holds the records of the others next to pairs of
+    /// its own, so it isn't listed by `get_dbs`: LMDB's, with named databases.
+    fn shared_default_db(&self) -> Result<Option<Self::Database>, Self::Error>;
+
+    /// Number of entries in the named database, counting each value of a key in a
+    /// database with duplicate values separately.
+    fn db_entries(&self, name: Option<&str>) -> Result<usize, Self::Error>;
+
+    /// Flags the named database was created with.
+    fn db_flags(&self, name: Option<&str>) -> Result<Self::Flags, Self::Error>;
+
     fn open_db(&self, name: Option<&str>) -> Result<Self::Database, Self::Error>;
 
     fn create_db(
@@ -108,23 +179,75 @@ pub trait BackendEnvironment<'e>: Debug {
         flags: Self::Flags,
     ) -> Result<Self::Database, Self::Error>;
 
+    /// Like `create_db`, but also returns whether the database was created, rather than
+    /// opened.
+    fn open_or_create_db(
+        &self,
+        name: Option<&str>,
+        flags: Self::Flags,
+    ) -> Result<(Self::Database, bool), Self::Error>;
+
+    /// Rename the `old` database to `new`, failing if `new` already exists. Each of the
+    /// `companions`, given as `(old, new)` names, that exists is renamed in the same
+    /// operation, e.g. a store's changelog.
+    fn rename_db(
+        &self,
+        old: &str,
+        new: &str,
+        companions: &[(&str, &str)],
+    ) -> Result<(), Self::Error>;
+
+    /// Delete the named database and its pairs, freeing its slot. Each of the
+    /// `companions` that exists is deleted in the same operation.
+    fn drop_db(&self, name: &str, companions: &[&str]) -> Result<(), Self::Error>;
+
     fn begin_ro_txn(&'e self) -> Result<Self::RoTransaction, Self::Error>;
 
     fn begin_rw_txn(&'e self) -> Result<Self::RwTransaction, Self::Error>;
 
+    /// Like `begin_rw_txn`, but returns `None` instead of waiting for the active write
+    /// transaction of this environment to end.
+    fn try_begin_rw_txn(&'e self) -> Result<Option<Self::RwTransaction>, Self::Error>;
+
+    /// Like `begin_rw_txn`, but returns `None` if the active write transaction of this
+    /// environment doesn't end within `timeout`.
+    fn begin_rw_txn_timeout(
+        &'e self,
+        timeout: Duration,
+    ) -> Result<Option<Self::RwTransaction>, Self::Error>;
+
     fn sync(&self, force: bool) -> Result<(), Self::Error>;
 
+    fn copy_to(&self, path: &Path, compact: bool) -> Result<(), Self::Error>;
+
     fn stat(&self) -> Result<Self::Stat, Self::Error>;
 
     fn info(&self) -> Result<Self::Info, Self::Error>;
 
+    fn env_info(&self) -> Result<EnvInfo, Self::Error>;
+
+    /// Number of free pages, that can be reused without growing the environment.
     fn freelist(&self) -> Result<usize, Self::Error>;
 
+    fn reader_info(&self) -> Result<ReaderInfo, Self::Error>;
+
+    /// Release the reader slots held by processes that died, returning how many were
+    /// released.
+    fn check_stale_readers(&self) -> Result<usize, Self::Error>;
+
     fn load_ratio(&self) -> Result<Option<f32>, Self::Error>;
 
+    /// Size of the memory map, or `None` if the backend doesn't use one.
+    fn map_size(&self) -> Result<Option<usize>, Self::Error>;
+
     fn set_map_size(&self, size: usize) -> Result<(), Self::Error>;
 
     fn get_files_on_disk(&self) -> Vec<PathBuf>;
+
+    fn is_read_only(&self) -> bool;
+
+    /// Whether the environment was built with `set_warn_on_implicit_abort(true)`.
+    fn warns_on_implicit_abort(&self) -> bool;
 }
 
 pub trait BackendRoTransaction: Debug {
@@ -133,6 +256,14 @@ pub trait BackendRoTransaction: Debug {
 
     fn get(&self, db: &Self::Database, key: &[u8]) -> Result<&[u8], Self::Error>;
 
+    fn stat(&self, db: &Self::Database) -> Result<DatabaseStat, Self::Error>;
+
+    /// Flags the database was created with.
+    fn db_flags(
+        &self,
+        db: &Self::Database,
+    ) -> Result<<Self::Database as BackendDatabase>::Flags, Self::Error>;
+
     fn abort(self);
 }
 
@@ -143,6 +274,14 @@ pub trait BackendRwTransaction: Debug {
 
     fn get(&self, db: &Self::Database, key: &[u8]) -> Result<&[u8], Self::Error>;
 
+    fn stat(&self, db: &Self::Database) -> Result<DatabaseStat, Self::Error>;
+
+    /// Flags the database was created with.
+    fn db_flags(
+        &self,
+        db: &Self::Database,
+    ) -> Result<<Self::Database as BackendDatabase>::Flags, Self::Error>;
+
     fn put(
         &mut self,
         db: &Self::Database,
@@ -151,6 +290,20 @@ pub trait BackendRwTransaction: Debug {
         flags: Self::Flags,
     ) -> Result<(), Self::Error>;
 
+    /// Store an item of `len` bytes whose contents are written in place by `fill`,
+    /// avoiding an intermediate copy where the backend supports it. In databases that
+    /// allow duplicate values, `fill` writes into a temporary buffer that's then copied.
+    fn reserve<F>(
+        &mut self,
+        db: &Self::Database,
+        key: &[u8],
+        len: usize,
+        flags: Self::Flags,
+        fill: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnOnce(&mut [u8]);
+
     #[cfg(not(feature = "db-dup-sort"))]
     fn del(&mut self, db: &Self::Database, key: &[u8]) -> Result<(), Self::Error>;
 
@@ -162,13 +315,64 @@ pub trait BackendRwTransaction: Debug {
         value: Option<&[u8]>,
     ) -> Result<(), Self::Error>;
 
+    /// Call `f` with every pair within the given bounds, in order, without changing the
+    /// database. Unlike the cursors of `BackendRwCursorTransaction`, this doesn't keep
+    /// the transaction borrowed, e.g. to write to it based on what was read.
+    fn scan_range<F>(
+        &self,
+        db: &Self::Database,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+        f: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&[u8], &[u8]);
+
+    /// Delete every key within the given bounds that has a value for which `pred`
+    /// returns true, returning the deleted keys in order. All values of a deleted key
+    /// are deleted.
+    fn del_range<F>(
+        &mut self,
+        db: &Self::Database,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+        pred: F,
+    ) -> Result<Vec<Vec<u8>>, Self::Error>
+    where
+        F: FnMut(&[u8], &[u8]) -> bool;
+
     fn clear_db(&mut self, db: &Self::Database) -> Result<(), Self::Error>;
 
+    /// The longest key that can be written, in bytes, or `None` if there's no limit.
+    fn max_key_size(&self) -> Option<usize>;
+
+    /// The longest value that can be written, in bytes, or `None` if there's no limit.
+    fn max_value_size(&self) -> Option<usize>;
+
     fn commit(self) -> Result<(), Self::Error>;
 
     fn abort(self);
 }
 
+pub trait BackendRwNestedTransaction<'p>: BackendRwTransaction {
+    type Nested: BackendRwTransaction<
+        Database = Self::Database,
+        Error = Self::Error,
+        Flags = Self::Flags,
+    >;
+
+    /// Begin a transaction nested in this one, which can't be used until the nested one
+    /// ends. Committing the nested transaction folds its changes into this one, while
+    /// aborting it only discards its own changes.
+    fn begin_nested_txn(&'p mut self) -> Result<Self::Nested, Self::Error>;
+}
+
+pub trait BackendRoRenewTransaction: BackendRoTransaction {
+    /// Release the transaction's snapshot and take a new one, so that it sees every
+    /// commit since it began, without beginning a new transaction.
+    fn renew(&mut self) -> Result<(), Self::Error>;
+}
+
 pub trait BackendRoCursorTransaction<'t>: BackendRoTransaction {
     type RoCursor: BackendRoCursor<'t>;
 
@@ -182,14 +386,25 @@ pub trait BackendRwCursorTransaction<'t>: BackendRwTransaction {
 }
 
 pub trait BackendRoCursor<'c>: Debug {
+    type Error: BackendError;
     type Iter: BackendIter<'c>;
 
+    /// Move the cursor, returning the entry it ends up at, or `None` if there's no
+    /// such entry.
+    #[allow(clippy::type_complexity)]
+    fn seek(&mut self, op: CursorOp<'_>) -> Option<Result<(&'c [u8], &'c [u8]), Self::Error>>;
+
     fn into_iter(self) -> Self::Iter;
 
     fn into_iter_from<K>(self, key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c;
 
+    /// Like `into_iter_from`, but starts at the first key strictly greater than `key`.
+    fn into_iter_after<K>(self, key: K) -> Self::Iter
+    where
+        K: AsRef<[u8]> + 'c;
+
     fn into_iter_dup_of<K>(self, key: K) -> Self::Iter
     where
         K: AsRef<[u8]> + 'c;
@@ -0,0 +1,157 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+//! A command-line utility to inspect an environment, e.g. to debug a production
+//! database. Given a path, it lists the environment's stores and their number of
+//! entries. With `--store NAME`, it prints that store's key/value pairs instead, one
+//! per line; use `--store ""` for the default store. `--backend lmdb|safe` selects
+//! the backend, which defaults to `safe`. The environment is opened read-only.
+
+use std::{
+    env::args,
+    error::Error,
+    io::{self, Write},
+    path::Path,
+    str,
+};
+
+#[cfg(feature = "lmdb")]
+use rkv::backend::Lmdb;
+#[cfg(feature = "db-dup-sort")]
+use rkv::{backend::BackendDatabaseFlags, DatabaseFlags, MultiStore};
+use rkv::{
+    backend::{BackendEnvironmentBuilder, SafeMode},
+    Rkv, SingleStore, StoreOptions,
+};
+
+/// The maximum number of stores the LMDB backend can open.
+const MAX_DBS: u32 = 1024;
+
+/// A store opened as the type matching its flags, so stores with several values per
+/// key are read as such.
+enum Store<D> {
+    Single(SingleStore<D>),
+    #[cfg(feature = "db-dup-sort")]
+    Multi(MultiStore<D>),
+}
+
+macro_rules! inspect {
+    ($backend:ty, $path:expr, $store:expr) => {{
+        let mut builder = Rkv::environment_builder::<$backend>();
+        builder.set_read_only(true).set_max_dbs(MAX_DBS);
+        let k = Rkv::from_builder($path, builder)?;
+
+        let mut names = k.get_dbs()?;
+        names.retain(|name| $store.map_or(true, |store| store == name.as_deref().unwrap_or("")));
+        if names.is_empty() && $store.is_some() {
+            return Err(format!("no store named {:?}", $store.unwrap_or("")).into());
+        }
+
+        let mut stores = vec![];
+        for name in names {
+            let opts = StoreOptions {
+                flags: k.db_flags(name.as_deref())?,
+                ..Default::default()
+            };
+            #[cfg(feature = "db-dup-sort")]
+            if opts.flags.get(DatabaseFlags::DUP_SORT) {
+                let store = k.open_multi(name.as_deref(), opts)?;
+                stores.push((name, Store::Multi(store)));
+                continue;
+            }
+            let store = k.open_single(name.as_deref(), opts)?;
+            stores.push((name, Store::Single(store)));
+        }
+
+        let reader = k.read()?;
+        let mut out = io::stdout().lock();
+        for (name, store) in &stores {
+            if $store.is_some() {
+                match store {
+                    Store::Single(store) => {
+                        for pair in store.iter_start(&reader)? {
+                            let (key, value) = pair?;
+                            writeln!(out, "{}\t{}", format_key(key), value)?;
+                        }
+                    }
+                    #[cfg(feature = "db-dup-sort")]
+                    Store::Multi(store) => {
+                        for pair in store.iter_start(&reader)? {
+                            let (key, value) = pair?;
+                            writeln!(out, "{}\t{}", format_key(key), value)?;
+                        }
+                    }
+                }
+            } else {
+                let entries = match store {
+                    Store::Single(store) => store.stat(&reader)?.entries,
+                    // Multi stores have no `stat`, so their values are counted.
+                    #[cfg(feature = "db-dup-sort")]
+                    Store::Multi(store) => store.iter_start(&reader)?.count(),
+                };
+                let name = name.as_deref().unwrap_or("<default>");
+                writeln!(out, "{}\t{}", name, entries)?;
+            }
+        }
+    }};
+}
+
+/// Keys are printed as strings if they're valid UTF-8, and in hex otherwise.
+fn format_key(key: &[u8]) -> String {
+    match str::from_utf8(key) {
+        Ok(key) => format!("{:?}", key),
+        Err(_) => key.iter().map(|b| format!("{:02x}", b)).collect(),
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = args();
+    let mut backend = "safe".to_string();
+    let mut store = None;
+    let mut path = None;
+
+    // The first arg is the name of the program, which we can ignore.
+    args.next();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--backend" => {
+                backend = args
+                    .next()
+                    .ok_or("--backend must be followed by a backend")?;
+            }
+            "--store" => {
+                store = Some(
+                    args.next()
+                        .ok_or("--store must be followed by a store name")?,
+                );
+            }
+            arg if arg.starts_with('-') => return Err(format!("arg {} not recognized", arg).into()),
+            _ => {
+                if path.is_some() {
+                    return Err("must provide only one path to the environment".into());
+                }
+                path = Some(arg);
+            }
+        }
+    }
+
+    let path = path.ok_or("must provide a path to the environment")?;
+    let path = Path::new(&path);
+    let store = store.as_deref();
+    match backend.as_str() {
+        "safe" => inspect!(SafeMode, path, store),
+        #[cfg(feature = "lmdb")]
+        "lmdb" => inspect!(Lmdb, path, store),
+        backend => return Err(format!("backend {} isn't supported by this build", backend).into()),
+    }
+
+    Ok(())
+}
@@ -9,23 +9,34 @@
 // specific language governing permissions and limitations under the License.
 
 use std::{
-    fs,
+    fmt, fs, io,
+    ops::Bound,
     os::raw::c_uint,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 #[cfg(any(feature = "db-dup-sort", feature = "db-int-key"))]
 use crate::backend::{BackendDatabaseFlags, DatabaseFlags};
+#[cfg(feature = "lmdb")]
+use crate::backend::{Lmdb, LmdbEnvironment};
 use crate::{
     backend::{
         BackendEnvironment, BackendEnvironmentBuilder, BackendRoCursorTransaction,
-        BackendRwCursorTransaction, SafeModeError,
+        BackendRwCursorTransaction, EnvInfo, ReaderInfo, SafeModeEnvironment, SafeModeError,
+        SafeModeRoSnapshot,
     },
     error::{CloseError, StoreError},
-    readwrite::{Reader, Writer},
-    store::{single::SingleStore, CloseOptions, Options as StoreOptions},
+    readwrite::{OwnedReader, Reader, Writer},
+    store::{
+        expiring::ExpiringStore, integerordered::OrderedIntegerStore, keys::OrderedInt,
+        list::ListStore, log::LogStore, single::SingleStore, CloseOptions, Options as StoreOptions,
+    },
 };
 
+#[cfg(feature = "compression")]
+use crate::store::compressed::{CompressedStore, Compression};
+
 #[cfg(feature = "db-dup-sort")]
 use crate::store::multi::MultiStore;
 
@@ -40,12 +51,53 @@ use crate::store::integermulti::MultiIntegerStore;
 pub static DEFAULT_MAX_DBS: c_uint = 5;
 
 /// Wrapper around an `Environment` (e.g. such as an `LMDB` or `SafeMode` environment).
-#[derive(Debug)]
 pub struct Rkv<E> {
-    _path: PathBuf,
+    path: PathBuf,
     env: E,
 }
 
+/// Summarizes the environment: its stores and their entry counts, and its map usage.
+/// Never includes any keys or values, so it's safe to log.
+impl<'e, E> fmt::Debug for Rkv<E>
+where
+    E: BackendEnvironment<'e>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = f.debug_struct("Rkv");
+        s.field("path", &self.path);
+        s.field("backend", &self.env.backend_name());
+        match self.store_entries() {
+            Ok(stores) => s.field("stores", &StoreEntries(stores)),
+            Err(err) => s.field("stores", &err),
+        };
+        if let Ok(Some(map_size)) = self.env.map_size() {
+            s.field("map_size", &map_size);
+            s.field("load_ratio", &self.env.load_ratio().ok().flatten());
+        }
+        s.finish()
+    }
+}
+
+struct StoreEntries(Vec<(Option<String>, usize)>);
+
+impl fmt::Debug for StoreEntries {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut map = f.debug_map();
+        for (name, entries) in &self.0 {
+            match name {
+                Some(name) => map.entry(name, entries),
+                None => map.entry(&format_args!("None"), entries),
+            };
+        }
+        map.finish()
+    }
+}
+
+/// The name of the changelog that the `changelog` option keeps for the named store.
+fn changelog_name(name: &str) -> String {
+    format!("{}.changelog", name)
+}
+
 /// Static methods.
 impl<'e, E> Rkv<E>
 where
@@ -58,6 +110,16 @@ where
         B::new()
     }
 
+    /// Whether an environment of the given backend exists at `path`, e.g. to choose
+    /// between creating and opening one. LMDB creates its data file when it opens the
+    /// environment, but SafeMode only writes it when the first write is committed.
+    pub fn environment_exists<B>(path: &Path) -> bool
+    where
+        B: BackendEnvironmentBuilder<'e, Environment = E>,
+    {
+        B::new().env_exists(path)
+    }
+
     /// Return a new Rkv environment that supports up to `DEFAULT_MAX_DBS` open databases.
     #[allow(clippy::new_ret_no_self)]
     pub fn new<B>(path: &Path) -> Result<Rkv<E>, StoreError>
@@ -85,7 +147,7 @@ where
         B: BackendEnvironmentBuilder<'e, Environment = E>,
     {
         Ok(Rkv {
-            _path: path.into(),
+            path: path.into(),
             env: builder.open(path).map_err(|e| e.into())?,
         })
     }
@@ -97,13 +159,61 @@ where
     E: BackendEnvironment<'e>,
 {
     /// Return all created databases.
+    ///
+    /// With LMDB, every named database is opened, to tell it apart from the default
+    /// database's own pairs, so this fails with `StoreError::DbsFull` if the environment
+    /// holds more databases than its maximum number of them.
     pub fn get_dbs(&self) -> Result<Vec<Option<String>>, StoreError> {
         self.env.get_dbs().map_err(|e| e.into())
     }
 
+    /// Return the flags a database was created with.
+    pub fn db_flags(&self, name: Option<&str>) -> Result<E::Flags, StoreError> {
+        self.env.db_flags(name).map_err(|e| e.into())
+    }
+
+    /// Rename the `old` store to `new`, e.g. during a schema migration, along with its
+    /// changelog if it has one. This fails with `StoreError::KeyValuePairExists` if `new`
+    /// already exists. It's atomic, but not cheap with LMDB, which can't rename
+    /// databases: every pair is copied, within a single write transaction. Stores opened
+    /// under the old name have to be opened again under the new one: with LMDB, using
+    /// them fails with `StoreError::StoreDropped`.
+    pub fn rename_store(&self, old: &str, new: &str) -> Result<(), StoreError> {
+        let changelogs = (changelog_name(old), changelog_name(new));
+        self.env
+            .rename_db(old, new, &[(&changelogs.0, &changelogs.1)])
+            .map_err(|e| e.into())
+    }
+
+    /// Delete the named store and all of its pairs, along with its changelog if it has
+    /// one, unlike `SingleStore::clear`, which keeps the store, so that it no longer
+    /// counts towards the maximum number of databases. Opening it again requires
+    /// `create`. Stores opened under this name must not be used afterwards: with LMDB,
+    /// using them fails with `StoreError::StoreDropped`.
+    pub fn drop_store(&self, name: &str) -> Result<(), StoreError> {
+        self.env
+            .drop_db(name, &[&changelog_name(name)])
+            .map_err(|e| e.into())
+    }
+
+    pub(crate) fn db_entries(&self, name: Option<&str>) -> Result<usize, StoreError> {
+        self.env.db_entries(name).map_err(|e| e.into())
+    }
+
+    fn store_entries(&self) -> Result<Vec<(Option<String>, usize)>, StoreError> {
+        let mut stores = vec![];
+        for name in self.get_dbs()? {
+            let entries = self.db_entries(name.as_deref())?;
+            stores.push((name, entries));
+        }
+        Ok(stores)
+    }
+
     /// Create or Open an existing database in (&[u8] -> Single Value) mode.
     /// Note: that create=true cannot be called concurrently with other operations so if
     /// you are sure that the database exists, call this with create=false.
+    /// With the `changelog` option, this also opens the store's changelog, named after the
+    /// store with a `.changelog` suffix.
     pub fn open_single<'s, T>(
         &self,
         name: T,
@@ -112,12 +222,149 @@ where
     where
         T: Into<Option<&'s str>>,
     {
-        self.open(name, opts).map(SingleStore::new)
+        self.open_single_created(name, opts).map(|(store, _)| store)
+    }
+
+    /// Like `open_single`, but also returns whether the database was created by this
+    /// call, rather than opened, e.g. to initialize it the first time. It's never
+    /// created unless `opts.create` is set.
+    pub fn open_single_created<'s, T>(
+        &self,
+        name: T,
+        opts: StoreOptions<E::Flags>,
+    ) -> Result<(SingleStore<E::Database>, bool), StoreError>
+    where
+        T: Into<Option<&'s str>>,
+    {
+        let name = name.into();
+        let (db, created) = self.open_created(name, opts)?;
+        let store = SingleStore::new(db)
+            .with_utf8_keys(opts.utf8_keys)
+            .with_write_flags(opts.default_write_flags);
+        if !opts.changelog {
+            return Ok((store, created));
+        }
+        let changelog_name = changelog_name(name.unwrap_or_default());
+        let changelog_opts = StoreOptions {
+            create: opts.create,
+            ..Default::default()
+        };
+        let changelog = self.open_log(changelog_name.as_str(), changelog_opts)?;
+        Ok((store.with_changelog(changelog), created))
+    }
+
+    /// Open the default database in (&[u8] -> Single Value) mode, like `open_single`
+    /// with a name of `None`.
+    pub fn open_default(
+        &self,
+        opts: StoreOptions<E::Flags>,
+    ) -> Result<SingleStore<E::Database>, StoreError> {
+        self.open_single(None, opts)
+    }
+
+    /// Create or open the default database in (&[u8] -> Single Value) mode, like
+    /// `open_default` with `StoreOptions::create()`.
+    pub fn create_default(&self) -> Result<SingleStore<E::Database>, StoreError> {
+        self.open_default(StoreOptions::create())
+    }
+
+    /// Open every database listed by `get_dbs` in (&[u8] -> Single Value) mode, e.g. to
+    /// back up or export all of them, along with its name, which is `None` for the
+    /// default database. Each database is opened with the flags it was created with.
+    ///
+    /// Databases created with `DUP_SORT` hold several values per key, which a
+    /// `SingleStore` would mishandle, so they're skipped: open them with `open_multi`.
+    #[allow(clippy::type_complexity)]
+    pub fn open_all_single(
+        &self,
+    ) -> Result<Vec<(Option<String>, SingleStore<E::Database>)>, StoreError> {
+        let mut stores = vec![];
+        for name in self.get_dbs()? {
+            let flags = self.db_flags(name.as_deref())?;
+            #[cfg(feature = "db-dup-sort")]
+            if flags.get(DatabaseFlags::DUP_SORT) {
+                continue;
+            }
+            let opts = StoreOptions {
+                flags,
+                ..Default::default()
+            };
+            let store = self.open_single(name.as_deref(), opts)?;
+            stores.push((name, store));
+        }
+        Ok(stores)
+    }
+
+    /// Create or Open an existing database in (&[u8] -> Single Value) mode, compressing
+    /// large `Blob` and `Str` values as configured by `compression`.
+    /// Note: that create=true cannot be called concurrently with other operations so if
+    /// you are sure that the database exists, call this with create=false.
+    #[cfg(feature = "compression")]
+    pub fn open_compressed<'s, T>(
+        &self,
+        name: T,
+        opts: StoreOptions<E::Flags>,
+        compression: Compression,
+    ) -> Result<CompressedStore<E::Database>, StoreError>
+    where
+        T: Into<Option<&'s str>>,
+    {
+        self.open(name, opts)
+            .map(|db| CompressedStore::new(db, compression))
+    }
+
+    /// Create or Open an existing database in (&[u8] -> Single Value) mode, where each
+    /// value has an expiry time.
+    /// Note: that create=true cannot be called concurrently with other operations so if
+    /// you are sure that the database exists, call this with create=false.
+    pub fn open_expiring<'s, T>(
+        &self,
+        name: T,
+        opts: StoreOptions<E::Flags>,
+    ) -> Result<ExpiringStore<E::Database>, StoreError>
+    where
+        T: Into<Option<&'s str>>,
+    {
+        self.open(name, opts).map(ExpiringStore::new)
+    }
+
+    /// Create or Open an existing database in (&[u8] -> List of Values) mode, where each
+    /// list is stored as a single value.
+    /// Note: that create=true cannot be called concurrently with other operations so if
+    /// you are sure that the database exists, call this with create=false.
+    pub fn open_list<'s, T>(
+        &self,
+        name: T,
+        opts: StoreOptions<E::Flags>,
+    ) -> Result<ListStore<E::Database>, StoreError>
+    where
+        T: Into<Option<&'s str>>,
+    {
+        self.open(name, opts).map(ListStore::new)
+    }
+
+    /// Create or Open an existing database in append-only (Sequence Number -> Single Value)
+    /// mode.
+    /// Note: that create=true cannot be called concurrently with other operations so if
+    /// you are sure that the database exists, call this with create=false.
+    pub fn open_log<'s, T>(
+        &self,
+        name: T,
+        opts: StoreOptions<E::Flags>,
+    ) -> Result<LogStore<E::Database>, StoreError>
+    where
+        T: Into<Option<&'s str>>,
+    {
+        self.open(name, opts).map(LogStore::new)
     }
 
     /// Create or Open an existing database in (Integer -> Single Value) mode.
     /// Note: that create=true cannot be called concurrently with other operations so if
     /// you are sure that the database exists, call this with create=false.
+    ///
+    /// Keys are encoded little-endian, in a database with the `INTEGER_KEY` flag, so
+    /// only LMDB iterates over them in numeric order. Use `open_ordered_integer` for
+    /// stores that need to iterate in numeric order on every backend.
     #[cfg(feature = "db-int-key")]
     pub fn open_integer<'s, T, K>(
         &self,
@@ -132,6 +379,25 @@ where
         self.open(name, opts).map(IntegerStore::new)
     }
 
+    /// Create or Open an existing database in (Integer -> Single Value) mode, encoding
+    /// keys so that the store iterates in numeric key order, see `OrderedInt`. This is
+    /// a different on-disk format than `open_integer`'s, which doesn't support every
+    /// integer type or preserve numeric order, so a database created by one can't be
+    /// opened by the other.
+    /// Note: that create=true cannot be called concurrently with other operations so if
+    /// you are sure that the database exists, call this with create=false.
+    pub fn open_ordered_integer<'s, T, K>(
+        &self,
+        name: T,
+        opts: StoreOptions<E::Flags>,
+    ) -> Result<OrderedIntegerStore<E::Database, K>, StoreError>
+    where
+        K: OrderedInt,
+        T: Into<Option<&'s str>>,
+    {
+        self.open(name, opts).map(OrderedIntegerStore::new)
+    }
+
     /// Create or Open an existing database in (&[u8] -> Multiple Values) mode.
     /// Note: that create=true cannot be called concurrently with other operations so if
     /// you are sure that the database exists, call this with create=false.
@@ -166,13 +432,30 @@ where
         self.open(name, opts).map(MultiIntegerStore::new)
     }
 
+    /// Open or create the database, failing with `StoreError::IncompatibleFlags` if it
+    /// exists with other flags than the requested ones, since e.g. reading a `DUP_SORT`
+    /// database as a single store would silently misbehave.
     fn open<'s, T>(&self, name: T, opts: StoreOptions<E::Flags>) -> Result<E::Database, StoreError>
     where
         T: Into<Option<&'s str>>,
     {
+        self.open_created(name, opts).map(|(db, _)| db)
+    }
+
+    fn open_created<'s, T>(
+        &self,
+        name: T,
+        opts: StoreOptions<E::Flags>,
+    ) -> Result<(E::Database, bool), StoreError>
+    where
+        T: Into<Option<&'s str>>,
+    {
+        let name = name.into();
         if opts.create {
+            // The backends check the flags of existing databases themselves, within the
+            // transaction that creates them.
             self.env
-                .create_db(name.into(), opts.flags)
+                .open_or_create_db(name, opts.flags)
                 .map_err(|e| match e.into() {
                     #[cfg(feature = "lmdb")]
                     StoreError::LmdbError(lmdb::Error::BadRslot) => {
@@ -184,7 +467,7 @@ where
                     e => e,
                 })
         } else {
-            self.env.open_db(name.into()).map_err(|e| match e.into() {
+            let db = self.env.open_db(name).map_err(|e| match e.into() {
                 #[cfg(feature = "lmdb")]
                 StoreError::LmdbError(lmdb::Error::BadRslot) => {
                     StoreError::open_during_transaction()
@@ -193,7 +476,11 @@ where
                     StoreError::open_during_transaction()
                 }
                 e => e,
-            })
+            })?;
+            if self.db_flags(name)? != opts.flags {
+                return Err(StoreError::IncompatibleFlags);
+            }
+            Ok((db, false))
         }
     }
 }
@@ -206,6 +493,14 @@ where
     /// Create a read transaction.  There can be multiple concurrent readers for an
     /// environment, up to the maximum specified by LMDB (default 126), and you can open
     /// readers while a write transaction is active.
+    ///
+    /// By default, LMDB only allows one read transaction per thread, and opening another
+    /// one fails with `StoreError::ReadTransactionAlreadyExists`, e.g. when a function
+    /// that holds a reader calls a helper that opens its own. Build the environment with
+    /// `EnvironmentFlags::NO_TLS` to allow such nested readers. Each reader still sees
+    /// a consistent snapshot, as of when it was opened, so to see the same data as the
+    /// outer reader, pass it to the helper instead, and use `Reader::renew` to move it
+    /// to a later commit without opening another reader.
     pub fn read<T>(&'e self) -> Result<Reader<T>, StoreError>
     where
         E: BackendEnvironment<'e, RoTransaction = T>,
@@ -217,12 +512,136 @@ where
     /// Create a write transaction.  There can be only one write transaction active at any
     /// given time, so trying to create a second one will block until the first is
     /// committed or aborted.
+    ///
+    /// Returns `StoreError::EnvironmentReadOnly` if the environment was opened with
+    /// `EnvironmentFlags::READ_ONLY`.
     pub fn write<T>(&'e self) -> Result<Writer<T>, StoreError>
     where
         E: BackendEnvironment<'e, RwTransaction = T>,
         T: BackendRwCursorTransaction<'e, Database = E::Database>,
     {
-        Ok(Writer::new(self.env.begin_rw_txn().map_err(|e| e.into())?))
+        if self.env.is_read_only() {
+            return Err(StoreError::EnvironmentReadOnly);
+        }
+        let txn = self.env.begin_rw_txn().map_err(|e| e.into())?;
+        Ok(Writer::new(txn, self.env.warns_on_implicit_abort()))
+    }
+
+    /// Like `write`, but returns `None` right away instead of blocking if another write
+    /// transaction of this environment is active, e.g. to avoid blocking an event loop.
+    /// Creating, renaming and dropping stores use write transactions too.
+    ///
+    /// Only writers of this `Rkv` are detected: with LMDB, this still blocks while
+    /// another process, or another `Rkv` for the same path, is writing.
+    pub fn try_write<T>(&'e self) -> Result<Option<Writer<T>>, StoreError>
+    where
+        E: BackendEnvironment<'e, RwTransaction = T>,
+        T: BackendRwCursorTransaction<'e, Database = E::Database>,
+    {
+        if self.env.is_read_only() {
+            return Err(StoreError::EnvironmentReadOnly);
+        }
+        let txn = self.env.try_begin_rw_txn().map_err(|e| e.into())?;
+        Ok(txn.map(|txn| Writer::new(txn, self.env.warns_on_implicit_abort())))
+    }
+
+    /// Like `write`, but fails with `StoreError::WriteTimeout` if another write
+    /// transaction of this environment is still active after `timeout`, e.g. because
+    /// its holder forgot to commit it. Like `try_write`, only writers of this `Rkv` are
+    /// waited for with a timeout, including those creating, renaming or dropping stores.
+    pub fn write_timeout<T>(&'e self, timeout: Duration) -> Result<Writer<T>, StoreError>
+    where
+        E: BackendEnvironment<'e, RwTransaction = T>,
+        T: BackendRwCursorTransaction<'e, Database = E::Database>,
+    {
+        if self.env.is_read_only() {
+            return Err(StoreError::EnvironmentReadOnly);
+        }
+        match self
+            .env
+            .begin_rw_txn_timeout(timeout)
+            .map_err(|e| e.into())?
+        {
+            Some(txn) => Ok(Writer::new(txn, self.env.warns_on_implicit_abort())),
+            None => Err(StoreError::WriteTimeout),
+        }
+    }
+
+    /// Empty every store listed by `get_dbs` in a single write transaction, so either
+    /// all of them are cleared or none is, e.g. to reset test fixtures. The stores
+    /// themselves are kept, along with their flags.
+    ///
+    /// With LMDB, the default database holds the names of the others, so it's only
+    /// listed in environments without named databases. In the others, its own pairs
+    /// are still deleted, while the records of the other databases are kept.
+    pub fn clear_all<T>(&'e self) -> Result<(), StoreError>
+    where
+        E: BackendEnvironment<'e, RwTransaction = T>,
+        T: BackendRwCursorTransaction<'e, Database = E::Database>,
+    {
+        let names = self.get_dbs()?;
+        let mut dbs = vec![];
+        for name in &names {
+            let opts = StoreOptions {
+                flags: self.db_flags(name.as_deref())?,
+                ..Default::default()
+            };
+            dbs.push(self.open(name.as_deref(), opts)?);
+        }
+        let shared = self.env.shared_default_db().map_err(|e| e.into())?;
+        let mut writer = self.write()?;
+        for db in &dbs {
+            writer.clear(db)?;
+        }
+        if let Some(db) = shared {
+            let is_db_name = |key: &[u8]| {
+                names
+                    .iter()
+                    .any(|name| name.as_deref().map(str::as_bytes) == Some(key))
+            };
+            writer.delete_range(&db, Bound::Unbounded, Bound::Unbounded, |key, _| {
+                !is_db_name(key)
+            })?;
+        }
+        writer.commit()
+    }
+}
+
+/// LMDB-specific accessors.
+#[cfg(feature = "lmdb")]
+impl Rkv<LmdbEnvironment> {
+    /// Like `Rkv::new`, but keeping up to `size` read transactions around when their
+    /// readers end, to renew them for later reads in the same thread. Renewed readers
+    /// see the latest committed state, like new ones. See `Lmdb::set_reader_cache_size`.
+    pub fn with_reader_cache(path: &Path, size: usize) -> Result<Rkv<LmdbEnvironment>, StoreError> {
+        let mut builder = Lmdb::new();
+        builder
+            .set_max_dbs(DEFAULT_MAX_DBS)
+            .set_reader_cache_size(size);
+        Rkv::from_builder(path, builder)
+    }
+}
+
+/// SafeMode-specific accessors.
+impl Rkv<SafeModeEnvironment> {
+    /// Take a read-only view of the last committed transaction that, unlike a `Reader`
+    /// from `read()`, owns its data instead of borrowing the environment. It can be
+    /// moved to another thread and queried with the usual store methods, and it doesn't
+    /// count as an open reader. Stores opened after it was taken aren't part of it.
+    pub fn read_snapshot(&self) -> Result<OwnedReader, StoreError> {
+        let snapshot = SafeModeRoSnapshot::new(&self.env).map_err(Into::<StoreError>::into)?;
+        Ok(Reader::new(snapshot))
+    }
+
+    /// Free the memory still held by stores deleted with `drop_store`, and rewrite the
+    /// database file. Deleted pairs don't leave anything behind, so this only reclaims
+    /// space after dropping stores, or writes the file with `Durability::NoSync`.
+    ///
+    /// It fails with `StoreError::SafeModeError(DbsIllegalOpen)` while a reader or a
+    /// writer is active. Stores opened before have to be opened again afterwards, and
+    /// using them fails with `StoreError::SafeModeError(DbIsForeignError)`.
+    pub fn compact(&self) -> Result<(), StoreError> {
+        self.env.compact().map_err(|e| e.into())
     }
 }
 
@@ -247,6 +666,34 @@ where
         self.env.sync(force).map_err(|e| e.into())
     }
 
+    /// Make sure that all committed data is durable on disk, whatever the backend and
+    /// its durability settings. This is a no-op for read-only environments.
+    ///
+    /// For LMDB, this is a forced `sync`. The SafeMode backend writes its databases to
+    /// disk, which it otherwise only does on commit unless opened with
+    /// `Durability::NoSync`, and waits for the operating system to flush them.
+    pub fn flush(&self) -> Result<(), StoreError> {
+        if self.env.is_read_only() {
+            return Ok(());
+        }
+        self.sync(true)
+    }
+
+    /// Copy this environment to `path`, e.g. to take a hot backup while writers are active.
+    ///
+    /// The copy is a consistent snapshot of the last committed transaction. The target
+    /// directory is created if needed, but the database file(s) in it must not already
+    /// exist. If `compact` is true, LMDB omits free pages and renumbers the remaining
+    /// ones while copying; other backends always write a compact copy.
+    pub fn copy_to<P>(&self, path: P, compact: bool) -> Result<(), StoreError>
+    where
+        P: AsRef<Path>,
+    {
+        self.env
+            .copy_to(path.as_ref(), compact)
+            .map_err(|e| e.into())
+    }
+
     /// Retrieve statistics about this environment.
     ///
     /// It includes:
@@ -272,10 +719,57 @@ where
         self.env.info().map_err(|e| e.into())
     }
 
+    /// Retrieve information about this environment that every backend provides, unlike
+    /// `info`, e.g. for monitoring: its map size, size in bytes, number of readers and
+    /// number of databases. See `EnvInfo`.
+    pub fn env_info(&self) -> Result<EnvInfo, StoreError> {
+        self.env.env_info().map_err(|e| e.into())
+    }
+
+    /// Retrieve the number of free pages, that LMDB reuses before growing the
+    /// environment. SafeMode doesn't use pages, so it always returns `0`.
+    pub fn freelist(&self) -> Result<usize, StoreError> {
+        self.env.freelist().map_err(|e| e.into())
+    }
+
+    /// Retrieve how many read transactions are open, and how many LMDB allows, e.g. to
+    /// spot leaked readers before reads fail with `StoreError::ReadersFull`. Readers
+    /// that ended but still hold their thread's slot don't count.
+    pub fn reader_info(&self) -> Result<ReaderInfo, StoreError> {
+        self.env.reader_info().map_err(|e| e.into())
+    }
+
+    /// Release the LMDB reader slots held by processes that died without ending their
+    /// read transactions, which would otherwise keep old pages from being reused.
+    /// Returns how many slots were released. SafeMode readers can't outlive their
+    /// process, so it always returns `0`.
+    pub fn check_stale_readers(&self) -> Result<usize, StoreError> {
+        self.env.check_stale_readers().map_err(|e| e.into())
+    }
+
+    /// The total size in bytes of this environment's files on disk, e.g. to enforce a
+    /// quota. For LMDB, that's the pages written so far rather than the map size, plus
+    /// the lock file. SafeMode's file only exists after the first commit.
+    pub fn disk_size(&self) -> Result<u64, StoreError> {
+        let mut size = 0;
+        for file in self.env.get_files_on_disk() {
+            match fs::metadata(file) {
+                Ok(metadata) => size += metadata.len(),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(size)
+    }
+
     /// Retrieve the load ratio (# of used pages / total pages) about this environment.
     ///
     /// With the formular: (last_page_no - freelist_pages) / total_pages.
     /// A value of `None` means that the backend doesn't ever need to be resized.
+    ///
+    /// The SafeMode backend doesn't use pages: its ratio is the size of the serialized
+    /// databases over the map size, which acts as a soft ceiling. It returns `None` when
+    /// no map size was set, and setting a map size of `0` removes the ceiling.
     pub fn load_ratio(&self) -> Result<Option<f32>, StoreError> {
         self.env.load_ratio().map_err(|e| e.into())
     }
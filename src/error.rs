@@ -37,6 +37,24 @@ pub enum DataError {
 
     #[error("invalid uuid bytes")]
     InvalidUuid,
+
+    #[error("integer overflow")]
+    IntegerOverflow,
+
+    #[error("unknown type name: {0}")]
+    UnknownTypeName(String),
+
+    #[error("invalid record on line {line}: {reason}")]
+    InvalidRecord { line: usize, reason: String },
+
+    #[error("expected a list, got a value of type {0}")]
+    NotAList(Type),
+
+    #[error("value of {size} bytes is larger than the maximum of {max}")]
+    ValueTooLarge { size: usize, max: usize },
+
+    #[error("invalid JSON: {0}")]
+    InvalidJson(serde_json::Error),
 }
 
 #[derive(Debug, Error)]
@@ -50,9 +68,24 @@ pub enum StoreError {
     #[error("key/value pair not found")]
     KeyValuePairNotFound,
 
+    #[error("key/value pair already exists")]
+    KeyValuePairExists,
+
     #[error("unsupported size of key/DB name/data")]
     KeyValuePairBadSize,
 
+    #[error("key is {len} bytes long, but at most {max} bytes are supported")]
+    KeyTooLong { len: usize, max: usize },
+
+    #[error("key is not valid UTF-8")]
+    NonUtf8Key,
+
+    #[error("store has no changelog")]
+    NoChangelog,
+
+    #[error("appended key doesn't sort after the store's existing keys")]
+    KeyOutOfOrder,
+
     #[error("file is not a valid database")]
     FileInvalid,
 
@@ -81,11 +114,36 @@ pub enum StoreError {
     #[error("safe mode backend error: {0}")]
     SafeModeError(SafeModeError),
 
+    #[cfg(feature = "encryption")]
+    #[error("couldn't decrypt the database: wrong key, or the file was tampered with")]
+    DecryptionFailed,
+
     #[error("read transaction already exists in thread {0:?}")]
     ReadTransactionAlreadyExists(ThreadId),
 
     #[error("attempted to open DB during transaction in thread {0:?}")]
     OpenAttemptedDuringTransaction(ThreadId),
+
+    #[error("attempted to write to a read-only environment")]
+    EnvironmentReadOnly,
+
+    #[error("store, reader or writer belongs to another backend")]
+    BackendMismatch,
+
+    #[error("store exists with flags that don't match the requested ones")]
+    IncompatibleFlags,
+
+    #[error("timed out waiting for the active write transaction to end")]
+    WriteTimeout,
+
+    #[error("store belongs to another environment than the transaction")]
+    StoreForeign,
+
+    #[error("store was renamed or dropped after it was opened")]
+    StoreDropped,
+
+    #[error("store {0:?} was created with flags that can't be exported")]
+    FlagsNotExportable(Option<String>),
 }
 
 impl StoreError {
@@ -141,6 +199,15 @@ pub enum MigrateError {
 
     #[error("destination is not empty")]
     DestinationNotEmpty,
+
+    #[error("migration cancelled")]
+    Cancelled,
+
+    #[error("key exists in both the source and the destination")]
+    KeyConflict,
+
+    #[error("databases with duplicate values can't be migrated this way")]
+    DupSortUnsupported,
 }
 
 impl<T> From<sync::PoisonError<T>> for MigrateError {
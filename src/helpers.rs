@@ -17,7 +17,7 @@ use url::Url;
 
 use crate::{error::StoreError, value::Value};
 
-pub(crate) fn read_transform(value: Result<&[u8], StoreError>) -> Result<Value, StoreError> {
+pub(crate) fn read_transform(value: Result<&[u8], StoreError>) -> Result<Value<'_>, StoreError> {
     match value {
         Ok(bytes) => Value::from_tagged_slice(bytes).map_err(StoreError::DataError),
         Err(e) => Err(e),
@@ -26,11 +26,37 @@ pub(crate) fn read_transform(value: Result<&[u8], StoreError>) -> Result<Value,
 
 // Workaround the UNC path on Windows, see https://github.com/rust-lang/rust/issues/42869.
 // Otherwise, `Env::from_builder()` will panic with error_no(123).
+// Paths that don't exist yet are canonicalized by canonicalizing their closest existing
+// ancestor, and appending the missing components to it.
 pub(crate) fn canonicalize_path<'p, P>(path: P) -> io::Result<PathBuf>
 where
     P: Into<&'p Path>,
 {
-    let canonical = path.into().canonicalize()?;
+    let mut existing = path.into();
+    let mut missing = vec![];
+    let canonical = loop {
+        match existing.canonicalize() {
+            Ok(canonical) => break canonical,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                match (existing.parent(), existing.file_name()) {
+                    (Some(parent), Some(name)) => {
+                        missing.push(name);
+                        existing = if parent.as_os_str().is_empty() {
+                            Path::new(".")
+                        } else {
+                            parent
+                        };
+                    }
+                    _ => return Err(err),
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    };
+    let canonical = missing
+        .into_iter()
+        .rev()
+        .fold(canonical, |path, name| path.join(name));
 
     Ok(if cfg!(target_os = "windows") {
         let map_err = |_| io::Error::new(io::ErrorKind::Other, "path canonicalization error");
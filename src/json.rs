@@ -0,0 +1,384 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+//! Dumping whole environments as newline-delimited JSON, one record per key/value pair,
+//! and restoring them from such dumps.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+};
+
+use serde_json::{json, Number, Value as JsonValue};
+use uuid::Uuid;
+
+use crate::{
+    backend::{
+        BackendDatabase, BackendDatabaseFlags, BackendIter, BackendRoCursor, DatabaseFlags,
+        SafeModeEnvironment,
+    },
+    error::{DataError, StoreError},
+    readwrite::Readable,
+    store::{single::SingleStore, Options as StoreOptions},
+    value::{OwnedValue, Type, Value},
+    Rkv,
+};
+
+#[cfg(feature = "lmdb")]
+use crate::backend::LmdbEnvironment;
+
+/// A record read from a dump: the store name, the names of its flags, key and value.
+type Record = (Option<String>, Vec<&'static str>, Vec<u8>, OwnedValue);
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+macro_rules! impl_json {
+    ($env:ty) => {
+        impl Rkv<$env> {
+            /// Write every key/value pair of every store to `w` as newline-delimited JSON,
+            /// e.g. for debugging or to feed to another tool:
+            ///
+            /// ```json
+            /// {"store":"s","key_b64":"aGVsbG8=","value":{"type":"I64","data":1234}}
+            /// ```
+            ///
+            /// The store is `null` for the default database. Keys and `Blob` values are
+            /// base64-encoded, non-finite `F64`s are written as strings (e.g. `"NaN"`) and
+            /// `Uuid`s as hyphenated strings. Stores created with `INTEGER_KEY` have it
+            /// listed in a `"flags"` array, so that `import_json` recreates them alike.
+            /// Stores are written in the order of `get_dbs`, and their pairs in key order,
+            /// all from a single read transaction.
+            ///
+            /// Stores created with other flags, e.g. `DUP_SORT`, which holds several values
+            /// per key, can't be restored from such records, so this fails with
+            /// `StoreError::FlagsNotExportable` rather than leave them out.
+            pub fn export_json<W>(&self, w: W) -> Result<(), StoreError>
+            where
+                W: Write,
+            {
+                let mut stores = vec![];
+                for name in self.get_dbs()? {
+                    let flags = self.db_flags(name.as_deref())?;
+                    let flag_names = flags_to_names(flags)
+                        .ok_or_else(|| StoreError::FlagsNotExportable(name.clone()))?;
+                    let opts = StoreOptions {
+                        flags,
+                        ..Default::default()
+                    };
+                    let store = self.open_single(name.as_deref(), opts)?;
+                    stores.push((name, flag_names, store));
+                }
+                let reader = self.read()?;
+                write_records(&reader, &stores, w)
+            }
+
+            /// Restore the records written by `export_json` from `r`, e.g. to rebuild an
+            /// environment on another backend, and return how many were imported.
+            ///
+            /// Stores are created as needed, with the flags listed in their records, and
+            /// existing values are overwritten. Existing stores with other flags fail with
+            /// `StoreError::IncompatibleFlags`. The whole dump is parsed before anything is
+            /// written, then all of it is written in a single transaction, so a malformed
+            /// record leaves the environment untouched. Such records fail with
+            /// `DataError::InvalidRecord`, naming the offending line, or with
+            /// `DataError::UnknownTypeName` for unknown value types. Blank lines are
+            /// skipped.
+            pub fn import_json<R>(&self, r: R) -> Result<usize, StoreError>
+            where
+                R: Read,
+            {
+                let records = read_records(r)?;
+                let mut stores = HashMap::new();
+                for (name, flag_names, _, _) in &records {
+                    if !stores.contains_key(name) {
+                        let opts = StoreOptions {
+                            create: true,
+                            flags: names_to_flags(flag_names),
+                            ..Default::default()
+                        };
+                        let store = self.open_single(name.as_deref(), opts)?;
+                        stores.insert(name.clone(), store);
+                    }
+                }
+                let mut writer = self.write()?;
+                for (name, _, key, value) in &records {
+                    stores[name].put(&mut writer, key, &Value::from(value))?;
+                }
+                writer.commit()?;
+                Ok(records.len())
+            }
+        }
+    };
+}
+
+impl_json!(SafeModeEnvironment);
+#[cfg(feature = "lmdb")]
+impl_json!(LmdbEnvironment);
+
+/// The database flags that records list by name. Stores with others can't be exported.
+fn exported_flags() -> Vec<(&'static str, DatabaseFlags)> {
+    vec![
+        #[cfg(feature = "db-int-key")]
+        ("INTEGER_KEY", DatabaseFlags::INTEGER_KEY),
+    ]
+}
+
+/// The names of the flags, or `None` if they include flags that can't be exported.
+fn flags_to_names<F>(flags: F) -> Option<Vec<&'static str>>
+where
+    F: BackendDatabaseFlags,
+{
+    let names = exported_flags()
+        .into_iter()
+        .filter_map(|(name, flag)| if flags.get(flag) { Some(name) } else { None })
+        .collect::<Vec<_>>();
+    if names_to_flags::<F>(&names) == flags {
+        Some(names)
+    } else {
+        None
+    }
+}
+
+fn names_to_flags<F>(names: &[&str]) -> F
+where
+    F: BackendDatabaseFlags,
+{
+    let mut flags = F::empty();
+    for (name, flag) in exported_flags() {
+        if names.contains(&name) {
+            flags.set(flag, true);
+        }
+    }
+    flags
+}
+
+#[allow(clippy::type_complexity)]
+fn write_records<'r, R, I, C, D, W>(
+    reader: &'r R,
+    stores: &[(Option<String>, Vec<&'static str>, SingleStore<D>)],
+    mut w: W,
+) -> Result<(), StoreError>
+where
+    R: Readable<'r, Database = D, RoCursor = C>,
+    I: BackendIter<'r>,
+    C: BackendRoCursor<'r, Iter = I>,
+    D: BackendDatabase,
+    W: Write,
+{
+    for (name, flag_names, store) in stores {
+        for pair in store.iter_start(reader)? {
+            let (key, value) = pair?;
+            let mut record = json!({
+                "store": name,
+                "key_b64": encode_base64(key),
+                "value": value_to_json(&value),
+            });
+            if !flag_names.is_empty() {
+                record["flags"] = json!(flag_names);
+            }
+            serde_json::to_writer(&mut w, &record).map_err(io::Error::from)?;
+            w.write_all(b"\n")?;
+        }
+    }
+    w.flush()?;
+    Ok(())
+}
+
+fn value_to_json(value: &Value) -> JsonValue {
+    let data = match value {
+        Value::Bool(v) => json!(v),
+        Value::U64(v) => json!(v),
+        Value::I64(v) | Value::Instant(v) => json!(v),
+        Value::F64(v) => match Number::from_f64(v.into_inner()) {
+            Some(n) => JsonValue::Number(n),
+            None => JsonValue::String(v.to_string()),
+        },
+        Value::Uuid(v) => json!(Uuid::from_bytes(**v).hyphenated().to_string()),
+        Value::Str(v) | Value::Json(v) => json!(v),
+        Value::Blob(v) => json!(encode_base64(v)),
+    };
+    json!({
+        "type": format!("{:?}", value.value_type()),
+        "data": data,
+    })
+}
+
+fn read_records<R>(r: R) -> Result<Vec<Record>, StoreError>
+where
+    R: Read,
+{
+    let mut records = vec![];
+    let mut store_flags = HashMap::new();
+    for (i, line) in BufReader::new(r).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |reason: String| DataError::InvalidRecord {
+            line: i + 1,
+            reason,
+        };
+        let record: JsonValue = serde_json::from_str(&line).map_err(|e| invalid(e.to_string()))?;
+        let name = match &record["store"] {
+            JsonValue::Null => None,
+            JsonValue::String(name) => Some(name.clone()),
+            _ => return Err(invalid("store isn't a string or null".into()).into()),
+        };
+        let flag_names = match &record["flags"] {
+            JsonValue::Null => vec![],
+            JsonValue::Array(names) => names
+                .iter()
+                .map(|name| {
+                    exported_flags()
+                        .into_iter()
+                        .map(|(exported, _)| exported)
+                        .find(|exported| name.as_str() == Some(exported))
+                        .ok_or_else(|| invalid(format!("unknown flag {}", name)))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err(invalid("flags isn't an array".into()).into()),
+        };
+        if *store_flags
+            .entry(name.clone())
+            .or_insert_with(|| flag_names.clone())
+            != flag_names
+        {
+            return Err(invalid("flags differ from the store's earlier records".into()).into());
+        }
+        let key = record["key_b64"]
+            .as_str()
+            .ok_or_else(|| "missing key_b64".to_string())
+            .and_then(decode_base64)
+            .map_err(|e| invalid(format!("key_b64: {}", e)))?;
+        let value = match record["value"]["type"].as_str() {
+            Some(type_name) => {
+                json_to_value(type_name_to_type(type_name)?, &record["value"]["data"])
+                    .map_err(|e| invalid(format!("value: {}", e)))?
+            }
+            None => return Err(invalid("missing value type".into()).into()),
+        };
+        records.push((name, flag_names, key, value));
+    }
+    Ok(records)
+}
+
+fn type_name_to_type(name: &str) -> Result<Type, DataError> {
+    Ok(match name {
+        "Bool" => Type::Bool,
+        "U64" => Type::U64,
+        "I64" => Type::I64,
+        "F64" => Type::F64,
+        "Instant" => Type::Instant,
+        "Uuid" => Type::Uuid,
+        "Str" => Type::Str,
+        "Json" => Type::Json,
+        "Blob" => Type::Blob,
+        _ => return Err(DataError::UnknownTypeName(name.to_string())),
+    })
+}
+
+fn json_to_value(t: Type, data: &JsonValue) -> Result<OwnedValue, String> {
+    let unexpected = || format!("expected {} data, got {}", t, data);
+    let value = match t {
+        Type::Bool => data.as_bool().map(OwnedValue::Bool),
+        Type::U64 => data.as_u64().map(OwnedValue::U64),
+        Type::I64 => data.as_i64().map(OwnedValue::I64),
+        Type::Instant => data.as_i64().map(OwnedValue::Instant),
+        Type::F64 => match data {
+            JsonValue::String(s) => s.parse().ok().map(OwnedValue::F64),
+            _ => data.as_f64().map(OwnedValue::F64),
+        },
+        Type::Uuid => data
+            .as_str()
+            .and_then(|s| Uuid::parse_str(s).ok())
+            .map(OwnedValue::Uuid),
+        Type::Str => data.as_str().map(|s| OwnedValue::Str(s.to_string())),
+        Type::Json => data.as_str().map(|s| OwnedValue::Json(s.to_string())),
+        Type::Blob => match data.as_str() {
+            Some(s) => Some(OwnedValue::Blob(decode_base64(s)?)),
+            None => None,
+        },
+    };
+    value.ok_or_else(unexpected)
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn decode_base64(encoded: &str) -> Result<Vec<u8>, String> {
+    let encoded = encoded.as_bytes();
+    if encoded.len() % 4 != 0 {
+        return Err("invalid base64 length".to_string());
+    }
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3);
+    let chunks = encoded.chunks(4);
+    let last = chunks.len().saturating_sub(1);
+    for (i, chunk) in chunks.enumerate() {
+        let padding = chunk.iter().rev().take_while(|c| **c == b'=').count();
+        if padding > 2 || (padding > 0 && i != last) {
+            return Err("invalid base64 padding".to_string());
+        }
+        let mut n = 0u32;
+        for c in &chunk[..4 - padding] {
+            let sextet = BASE64_ALPHABET
+                .iter()
+                .position(|a| a == c)
+                .ok_or_else(|| format!("invalid base64 character {:?}", *c as char))?;
+            n = n << 6 | sextet as u32;
+        }
+        n <<= 6 * padding;
+        decoded.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"foob"), "Zm9vYg==");
+        assert_eq!(encode_base64(&[0xfb, 0xff]), "+/8=");
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert_eq!(decode_base64("Zg==").unwrap(), b"f");
+        assert_eq!(decode_base64("Zm8=").unwrap(), b"fo");
+        assert_eq!(decode_base64("Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(decode_base64("+/8=").unwrap(), &[0xfb, 0xff]);
+        assert!(decode_base64("Zm9").is_err());
+        assert!(decode_base64("Zg==Zg==").is_err());
+        assert!(decode_base64("Z===").is_err());
+        assert!(decode_base64("Zm9!").is_err());
+    }
+}
@@ -202,9 +202,11 @@
 //!
 //! ```
 
+mod any;
 mod env;
 mod error;
 mod helpers;
+mod json;
 mod manager;
 mod readwrite;
 
@@ -214,16 +216,28 @@ pub mod migrator;
 pub mod store;
 pub mod value;
 
+pub use any::{AnyReader, AnyRkv, AnySingleStore, AnyWriter, Backend};
 pub use backend::{DatabaseFlags, EnvironmentFlags, WriteFlags};
 pub use env::Rkv;
 pub use error::{DataError, MigrateError, StoreError};
 pub use manager::Manager;
 #[cfg(feature = "lmdb")]
 pub use migrator::Migrator;
-pub use readwrite::{Readable, Reader, Writer};
-pub use store::{keys::EncodableKey, single::SingleStore, CloseOptions, Options as StoreOptions};
+pub use readwrite::{OwnedReader, Readable, Reader, Writer};
+pub use store::{
+    expiring::ExpiringStore,
+    integerordered::OrderedIntegerStore,
+    keys::{CompositeKey, EncodableKey, OrderedInt},
+    list::ListStore,
+    log::LogStore,
+    single::{Op, SingleStore},
+    CloseOptions, Options as StoreOptions,
+};
 pub use value::{OwnedValue, Value};
 
+#[cfg(feature = "compression")]
+pub use store::compressed::{CompressedStore, Compression};
+
 #[cfg(feature = "db-dup-sort")]
 pub use store::multi::MultiStore;
 
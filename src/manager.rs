@@ -45,6 +45,11 @@ lazy_static! {
 /// A process is only permitted to have one open handle to each Rkv environment. This
 /// manager exists to enforce that constraint: don't open environments directly.
 ///
+/// There's one manager singleton per backend, see `Manager::<E>::singleton`. A path
+/// may be open in the managers of different backends at once, because each backend
+/// stores its data in different files; that's how the migrator converts an
+/// environment in place.
+///
 /// By default, path canonicalization is enabled for identifying RKV instances. This
 /// is true by default, because it helps enforce the constraints guaranteed by
 /// this manager. However, path canonicalization might crash in some fringe
@@ -80,6 +85,20 @@ where
         Ok(self.environments.get(&canonical).cloned())
     }
 
+    /// Return the (canonicalized) paths of the open envs, in sorted order.
+    pub fn iter_paths(&self) -> impl Iterator<Item = &Path> {
+        self.environments.keys().map(PathBuf::as_path)
+    }
+
+    /// Return the number of open envs.
+    pub fn len(&self) -> usize {
+        self.environments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.environments.is_empty()
+    }
+
     /// Return the open env at `path`, or create it by calling `f`.
     pub fn get_or_create<'p, F, P>(&mut self, path: P, f: F) -> Result<SharedRkv<E>>
     where
@@ -162,6 +181,23 @@ where
         } else {
             canonicalize_path(path)?
         };
+        self.close_canonical(canonical, options)
+    }
+
+    /// Tries to close all environments.
+    /// Environments that other users still hold are left open, and an error is returned.
+    pub fn try_close_all(&mut self, options: CloseOptions) -> CloseResult<()> {
+        let paths: Vec<PathBuf> = self.environments.keys().cloned().collect();
+        let mut result = Ok(());
+        for path in paths {
+            if let Err(err) = self.close_canonical(path, options) {
+                result = result.and(Err(err));
+            }
+        }
+        result
+    }
+
+    fn close_canonical(&mut self, canonical: PathBuf, options: CloseOptions) -> CloseResult<()> {
         match self.environments.entry(canonical) {
             Entry::Vacant(_) => Ok(()),
             Entry::Occupied(e) if Arc::strong_count(e.get()) > 1 => {
@@ -190,7 +226,7 @@ impl Manager<SafeModeEnvironment> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "lmdb"))]
 mod tests {
     use super::*;
     use crate::*;
@@ -199,11 +235,9 @@ mod tests {
 
     use tempfile::Builder;
 
-    #[cfg(feature = "lmdb")]
     use backend::Lmdb;
 
     /// Test that one can mutate managed Rkv instances in surprising ways.
-    #[cfg(feature = "lmdb")]
     #[test]
     fn test_mutate_managed_rkv() {
         let mut manager = Manager::<LmdbEnvironment>::new();
@@ -244,4 +278,108 @@ mod tests {
             .expect("success");
         assert!(!Arc::ptr_eq(&path2_arc, &arc));
     }
+
+    #[test]
+    fn test_iter_paths() {
+        let mut manager = Manager::<LmdbEnvironment>::new();
+        assert!(manager.is_empty());
+
+        let roots: Vec<_> = (0..3)
+            .map(|i| {
+                let root = Builder::new()
+                    .prefix(&format!("test_iter_paths_{}", i))
+                    .tempdir()
+                    .expect("tempdir");
+                fs::create_dir_all(root.path()).expect("dir created");
+                root
+            })
+            .collect();
+        for root in &roots {
+            manager
+                .get_or_create(root.path(), Rkv::new::<Lmdb>)
+                .expect("created");
+        }
+        // Getting an env again doesn't add another entry.
+        manager
+            .get_or_create(roots[0].path(), Rkv::new::<Lmdb>)
+            .expect("created");
+
+        assert_eq!(manager.len(), 3);
+        let mut expected: Vec<_> = roots
+            .iter()
+            .map(|root| canonicalize_path(root.path()).expect("canonical"))
+            .collect();
+        expected.sort();
+        assert_eq!(manager.iter_paths().collect::<Vec<_>>(), expected);
+
+        manager
+            .try_close(roots[1].path(), CloseOptions::default())
+            .expect("closed");
+        assert_eq!(manager.len(), 2);
+        let closed = canonicalize_path(roots[1].path()).expect("canonical");
+        assert!(!manager.iter_paths().any(|path| path == closed));
+    }
+
+    #[test]
+    fn test_try_close() {
+        let mut manager = Manager::<LmdbEnvironment>::new();
+
+        let root = Builder::new()
+            .prefix("test_try_close")
+            .tempdir()
+            .expect("tempdir");
+        fs::create_dir_all(root.path()).expect("dir created");
+        let arc = manager
+            .get_or_create(root.path(), Rkv::new::<Lmdb>)
+            .expect("created");
+
+        // Closing is refused while the environment is shared.
+        assert!(matches!(
+            manager.try_close(root.path(), CloseOptions::default()),
+            Err(CloseError::EnvironmentStillOpen)
+        ));
+        assert!(manager.get(root.path()).expect("success").is_some());
+
+        drop(arc);
+        manager
+            .try_close(root.path(), CloseOptions::default())
+            .expect("closed");
+        assert!(manager.get(root.path()).expect("success").is_none());
+    }
+
+    #[test]
+    fn test_try_close_all() {
+        let mut manager = Manager::<LmdbEnvironment>::new();
+
+        let root1 = Builder::new()
+            .prefix("test_try_close_all_1")
+            .tempdir()
+            .expect("tempdir");
+        fs::create_dir_all(root1.path()).expect("dir created");
+        let root2 = Builder::new()
+            .prefix("test_try_close_all_2")
+            .tempdir()
+            .expect("tempdir");
+        fs::create_dir_all(root2.path()).expect("dir created");
+        manager
+            .get_or_create(root1.path(), Rkv::new::<Lmdb>)
+            .expect("created");
+        let arc2 = manager
+            .get_or_create(root2.path(), Rkv::new::<Lmdb>)
+            .expect("created");
+
+        // Only the unshared environment is closed.
+        assert!(matches!(
+            manager.try_close_all(CloseOptions::default()),
+            Err(CloseError::EnvironmentStillOpen)
+        ));
+        assert!(manager.get(root1.path()).expect("success").is_none());
+        assert!(manager.get(root2.path()).expect("success").is_some());
+
+        drop(arc2);
+        manager
+            .try_close_all(CloseOptions::default())
+            .expect("closed");
+        assert!(manager.get(root2.path()).expect("success").is_none());
+    }
 }
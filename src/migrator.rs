@@ -16,7 +16,8 @@
 //! see `arch_migrator` if this is needed. However, this utility is ultimately intended to
 //! handle all possible migrations.
 //!
-//! The destination environment should be empty of data, otherwise an error is returned.
+//! The destination environment should be empty of data, otherwise an error is returned,
+//! unless merging into it with `migrate_<src>_to_<dst>_with` (see below).
 //!
 //! There are 3 versions of the migration methods:
 //! * `migrate_<src>_to_<dst>`, where `<src>` and `<dst>` are the source and destination
@@ -31,30 +32,158 @@
 //!   (corrupted), unavailable (path not accessible or incompatible with configuration),
 //!   or empty (database has no records).
 //!
+//! Additionally, there are these variants of `migrate_<src>_to_<dst>`:
+//! * `resumable_migrate_<src>_to_<dst>`, which records its progress in the destination
+//!   environment so that an interrupted (or cancelled) migration can be resumed instead of
+//!   restarted.
+//! * `migrate_<src>_to_<dst>_with_progress`, which reports the migration's progress to a
+//!   callback that can also stop it early.
+//! * `migrate_<src>_to_<dst>_with`, which merges the source into a destination environment
+//!   that may already contain data, using a `MergeStrategy` to resolve keys present in both.
+//!
 //! The tool currently has these limitations:
 //!
 //! 1. It doesn't support migration from environments created with
 //!    `EnvironmentFlags::NO_SUB_DIR`. To migrate such an environment, create a temporary
 //!    directory, copy the environment's data files in the temporary directory, then
 //!    migrate the temporary directory as the source environment.
-//! 2. It doesn't support migration from databases created with DatabaseFlags::DUP_SORT`
-//!    (with or without `DatabaseFlags::DUP_FIXED`) nor with `DatabaseFlags::INTEGER_KEY`.
-//!    This effectively means that migration is limited to `SingleStore`s.
-//! 3. It doesn't allow for existing data in the destination environment, which means that
-//!    it cannot overwrite nor append data.
+//! 2. Only the `migrate_<src>_to_<dst>` methods (and the `open_and_` and `easy_` variants)
+//!    support migration from databases created with `DatabaseFlags::DUP_SORT`; the others
+//!    fail with `MigrateError::DupSortUnsupported`. `DatabaseFlags::DUP_FIXED` isn't carried
+//!    over to the destination. Databases created with `DatabaseFlags::INTEGER_KEY` are
+//!    supported by all methods, and their key encoding is preserved.
+//! 3. Only the `_with` methods allow for existing data in the destination environment,
+//!    besides data left behind by an unfinished resumable migration.
+
+use std::{
+    ops::ControlFlow,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
+#[cfg(any(feature = "db-dup-sort", feature = "db-int-key"))]
+use crate::DatabaseFlags;
 use crate::{
-    backend::{LmdbEnvironment, SafeModeEnvironment},
+    backend::{BackendDatabaseFlags, LmdbEnvironment, SafeModeEnvironment},
     error::MigrateError,
     Rkv, StoreOptions,
 };
 
 pub use crate::backend::{LmdbArchMigrateError, LmdbArchMigrateResult, LmdbArchMigrator};
 
+/// The name of the store in which resumable migrations record their progress.
+pub const CHECKPOINT_STORE: &str = "__rkv_migration_checkpoint";
+
+/// How many key/value pairs a resumable migration copies per transaction.
+const BATCH_SIZE: usize = 1000;
+
+/// A flag shared with a resumable migration, to ask it to stop early.
+#[derive(Debug, Default, Clone)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        Default::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// How `migrate_<src>_to_<dst>_with` resolves a key present in both the source and the
+/// destination store.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum MergeStrategy {
+    /// The source value replaces the destination value.
+    Overwrite,
+    /// The destination value is kept.
+    Skip,
+    /// The migration fails with `MigrateError::KeyConflict`, without writing anything.
+    Fail,
+}
+
+/// Progress of a migration, reported by `migrate_<src>_to_<dst>_with_progress`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct MigrationProgress<'a> {
+    /// The store being migrated.
+    pub store: Option<&'a str>,
+    /// How many key/value pairs of the store were migrated so far.
+    pub keys_migrated: usize,
+    /// How many key/value pairs the store has in total, if known.
+    pub total_keys: Option<usize>,
+}
+
+/// Options for opening a source store, whose flags must match the existing ones.
+fn source_store_options<F>(src_flags: F) -> StoreOptions<F>
+where
+    F: BackendDatabaseFlags,
+{
+    StoreOptions {
+        flags: src_flags,
+        ..Default::default()
+    }
+}
+
+/// Options for creating the destination copy of a store, carrying over the flags that
+/// affect how the source store's data is laid out.
+fn migrated_store_options<S, D>(src_flags: &S) -> StoreOptions<D>
+where
+    S: BackendDatabaseFlags,
+    D: BackendDatabaseFlags,
+{
+    let mut opts: StoreOptions<D> = StoreOptions::create();
+    set_dup_sort(&mut opts.flags, is_dup_sort(src_flags));
+    set_integer_key(&mut opts.flags, is_integer_key(src_flags));
+    opts
+}
+
+#[cfg(feature = "db-dup-sort")]
+fn is_dup_sort<F: BackendDatabaseFlags>(flags: &F) -> bool {
+    flags.get(DatabaseFlags::DUP_SORT)
+}
+
+#[cfg(not(feature = "db-dup-sort"))]
+fn is_dup_sort<F: BackendDatabaseFlags>(_flags: &F) -> bool {
+    false
+}
+
+#[cfg(feature = "db-dup-sort")]
+fn set_dup_sort<F: BackendDatabaseFlags>(flags: &mut F, value: bool) {
+    flags.set(DatabaseFlags::DUP_SORT, value);
+}
+
+#[cfg(not(feature = "db-dup-sort"))]
+fn set_dup_sort<F: BackendDatabaseFlags>(_flags: &mut F, _value: bool) {}
+
+#[cfg(feature = "db-int-key")]
+fn is_integer_key<F: BackendDatabaseFlags>(flags: &F) -> bool {
+    flags.get(DatabaseFlags::INTEGER_KEY)
+}
+
+#[cfg(not(feature = "db-int-key"))]
+fn is_integer_key<F: BackendDatabaseFlags>(_flags: &F) -> bool {
+    false
+}
+
+#[cfg(feature = "db-int-key")]
+fn set_integer_key<F: BackendDatabaseFlags>(flags: &mut F, value: bool) {
+    flags.set(DatabaseFlags::INTEGER_KEY, value);
+}
+
+#[cfg(not(feature = "db-int-key"))]
+fn set_integer_key<F: BackendDatabaseFlags>(_flags: &mut F, _value: bool) {}
+
 // FIXME: should parametrize this instead.
 
 macro_rules! fn_migrator {
-    ($name:tt, $src_env:ty, $dst_env:ty) => {
+    ($name:tt, $progress:tt, $src_env:ty, $dst_env:ty) => {
         /// Migrate all data in all of databases from the source environment to the destination
         /// environment. This includes all key/value pairs in the main database that aren't
         /// metadata about subdatabases and all key/value pairs in all subdatabases.
@@ -67,6 +196,25 @@ macro_rules! fn_migrator {
         where
             S: std::ops::Deref<Target = Rkv<$src_env>>,
             D: std::ops::Deref<Target = Rkv<$dst_env>>,
+        {
+            Self::$progress(src_env, dst_env, |_| ControlFlow::Continue(()))
+        }
+    };
+
+    (progress $name:tt, $src_env:ty, $dst_env:ty) => {
+        /// Same as the `migrate_x_to_y` migration method above, but calls `progress_fn` after
+        /// each key/value pair is copied. Returning `ControlFlow::Break` from it stops the
+        /// migration with `MigrateError::Cancelled`. Each store is committed once it's fully
+        /// copied, so the destination environment is left with the stores completed so far.
+        pub fn $name<S, D, F>(
+            src_env: S,
+            dst_env: D,
+            mut progress_fn: F,
+        ) -> Result<(), MigrateError>
+        where
+            S: std::ops::Deref<Target = Rkv<$src_env>>,
+            D: std::ops::Deref<Target = Rkv<$dst_env>>,
+            F: FnMut(MigrationProgress) -> ControlFlow<()>,
         {
             let src_dbs = src_env.get_dbs().unwrap();
             if src_dbs.is_empty() {
@@ -77,13 +225,55 @@ macro_rules! fn_migrator {
                 return Err(MigrateError::DestinationNotEmpty);
             }
             for name in src_dbs {
-                let src_store = src_env.open_single(name.as_deref(), StoreOptions::default())?;
-                let dst_store = dst_env.open_single(name.as_deref(), StoreOptions::create())?;
+                let name = name.as_deref();
+                let src_flags = src_env.db_flags(name)?;
+                let src_store = src_env.open_single(name, source_store_options(src_flags))?;
+                let opts = migrated_store_options(&src_flags);
+                let total_keys = Some(src_env.db_entries(name)?);
+                let mut keys_migrated = 0;
+                let mut report = |keys_migrated| {
+                    let progress = MigrationProgress {
+                        store: name,
+                        keys_migrated,
+                        total_keys,
+                    };
+                    match progress_fn(progress) {
+                        ControlFlow::Continue(()) => Ok(()),
+                        ControlFlow::Break(()) => Err(MigrateError::Cancelled),
+                    }
+                };
+                #[cfg(feature = "db-dup-sort")]
+                {
+                    if is_dup_sort(&src_flags) {
+                        // Iterating the source yields every value of every key.
+                        let dst_store = dst_env.open_multi(name, opts)?;
+                        let reader = src_env.read()?;
+                        let mut writer = dst_env.write()?;
+                        let mut iter = src_store.iter_start(&reader)?;
+                        while let Some(Ok((key, value))) = iter.next() {
+                            dst_store.put(&mut writer, key, &value).expect("wrote");
+                            keys_migrated += 1;
+                            report(keys_migrated)?;
+                        }
+                        writer.commit()?;
+                        continue;
+                    }
+                }
+                let integer_key = is_integer_key(&src_flags);
+                let dst_store = dst_env.open_single(name, opts)?;
                 let reader = src_env.read()?;
                 let mut writer = dst_env.write()?;
                 let mut iter = src_store.iter_start(&reader)?;
+                // The source is iterated in key order, so its pairs can be appended. That's
+                // not the case for integer keys, which SafeMode doesn't keep in numeric order.
                 while let Some(Ok((key, value))) = iter.next() {
-                    dst_store.put(&mut writer, key, &value).expect("wrote");
+                    if integer_key {
+                        dst_store.put(&mut writer, key, &value).expect("wrote");
+                    } else {
+                        dst_store.append(&mut writer, key, &value).expect("wrote");
+                    }
+                    keys_migrated += 1;
+                    report(keys_migrated)?;
                 }
                 writer.commit()?;
             }
@@ -91,6 +281,185 @@ macro_rules! fn_migrator {
         }
     };
 
+    (with $name:tt, $src_env:ty, $dst_env:ty) => {
+        /// Same as the `migrate_x_to_y` migration method above, but the destination environment
+        /// may already contain data, which is merged with the source data according to the
+        /// given strategy. All data is written in a single transaction, so the destination
+        /// environment is left untouched if the migration fails.
+        pub fn $name<S, D>(
+            strategy: MergeStrategy,
+            src_env: S,
+            dst_env: D,
+        ) -> Result<(), MigrateError>
+        where
+            S: std::ops::Deref<Target = Rkv<$src_env>>,
+            D: std::ops::Deref<Target = Rkv<$dst_env>>,
+        {
+            let src_dbs = src_env.get_dbs()?;
+            if src_dbs.is_empty() {
+                return Err(MigrateError::SourceEmpty);
+            }
+            let mut stores = vec![];
+            for name in &src_dbs {
+                let name = name.as_deref();
+                let src_flags = src_env.db_flags(name)?;
+                if is_dup_sort(&src_flags) {
+                    return Err(MigrateError::DupSortUnsupported);
+                }
+                let src_store = src_env.open_single(name, source_store_options(src_flags))?;
+                let dst_store = dst_env.open_single(name, migrated_store_options(&src_flags))?;
+                stores.push((src_store, dst_store));
+            }
+            let reader = src_env.read()?;
+            let mut writer = dst_env.write()?;
+            for (src_store, dst_store) in stores {
+                let mut iter = src_store.iter_start(&reader)?;
+                while let Some((key, value)) = iter.next().transpose()? {
+                    match strategy {
+                        MergeStrategy::Overwrite => dst_store.put(&mut writer, key, &value)?,
+                        MergeStrategy::Skip => {
+                            dst_store.put_if_absent(&mut writer, key, &value)?;
+                        }
+                        MergeStrategy::Fail => {
+                            if !dst_store.put_if_absent(&mut writer, key, &value)? {
+                                return Err(MigrateError::KeyConflict);
+                            }
+                        }
+                    }
+                }
+            }
+            writer.commit()?;
+            Ok(())
+        }
+    };
+
+    (resumable $name:tt, $src_env:ty, $dst_env:ty) => {
+        /// Same as the `migrate_x_to_y` migration method above, but copies data in batches,
+        /// each committed along with a checkpoint of the migration's progress. Calling this
+        /// again after the migration was interrupted resumes it from the last checkpoint.
+        ///
+        /// The destination environment should be empty of data, or only contain data from an
+        /// unfinished migration of the same source stores, otherwise an error is returned. It
+        /// also needs room for one more store than the source, for `CHECKPOINT_STORE`, which
+        /// is dropped once the migration completes.
+        ///
+        /// The cancel token is checked after each committed batch. Once it's cancelled,
+        /// `MigrateError::Cancelled` is returned, and the migration can be resumed later.
+        pub fn $name<S, D>(src_env: S, dst_env: D, cancel: &CancelToken) -> Result<(), MigrateError>
+        where
+            S: std::ops::Deref<Target = Rkv<$src_env>>,
+            D: std::ops::Deref<Target = Rkv<$dst_env>>,
+        {
+            use crate::{StoreError, Value};
+
+            let src_dbs = src_env.get_dbs()?;
+            if src_dbs.is_empty() {
+                return Err(MigrateError::SourceEmpty);
+            }
+            for name in &src_dbs {
+                if is_dup_sort(&src_env.db_flags(name.as_deref())?) {
+                    return Err(MigrateError::DupSortUnsupported);
+                }
+            }
+            let sources =
+                bincode::serialize(&src_dbs).map_err(|e| StoreError::DataError(e.into()))?;
+
+            let dst_dbs = dst_env.get_dbs()?;
+            let checkpoint_name = Some(CHECKPOINT_STORE.to_string());
+            let other_dst_dbs = dst_dbs
+                .iter()
+                .filter(|&name| name != &checkpoint_name)
+                .count();
+            if other_dst_dbs > 0 && !dst_dbs.contains(&checkpoint_name) {
+                return Err(MigrateError::DestinationNotEmpty);
+            }
+
+            let checkpoint = dst_env.open_single(CHECKPOINT_STORE, StoreOptions::create())?;
+            let (start, mut last_key) = {
+                let reader = dst_env.read()?;
+                match checkpoint.get(&reader, "sources")? {
+                    None if other_dst_dbs == 0 => {}
+                    Some(Value::Blob(blob)) if blob == &sources[..] => {}
+                    _ => return Err(MigrateError::DestinationNotEmpty),
+                }
+                let start = match checkpoint.get(&reader, "store")? {
+                    Some(Value::U64(index)) => index as usize,
+                    _ => 0,
+                };
+                let last_key = match checkpoint.get(&reader, "key")? {
+                    Some(Value::Blob(key)) => Some(key.to_vec()),
+                    _ => None,
+                };
+                (start, last_key)
+            };
+
+            let mut writer = dst_env.write()?;
+            checkpoint.put(&mut writer, "sources", &Value::Blob(&sources))?;
+            writer.commit()?;
+
+            let mut committed = false;
+            for (index, name) in src_dbs.iter().enumerate().skip(start) {
+                let name = name.as_deref();
+                let src_flags = src_env.db_flags(name)?;
+                let integer_key = is_integer_key(&src_flags);
+                let src_store = src_env.open_single(name, source_store_options(src_flags))?;
+                let dst_store = dst_env.open_single(name, migrated_store_options(&src_flags))?;
+                loop {
+                    if committed && cancel.is_cancelled() {
+                        return Err(MigrateError::Cancelled);
+                    }
+                    let reader = src_env.read()?;
+                    let mut writer = dst_env.write()?;
+                    let mut iter = match &last_key {
+                        Some(key) => src_store.iter_from(&reader, key.clone())?,
+                        None => src_store.iter_start(&reader)?,
+                    };
+                    let mut copied = 0;
+                    let mut done = true;
+                    while let Some(entry) = iter.next() {
+                        let (key, value) = entry?;
+                        if last_key.as_deref() == Some(key) {
+                            continue;
+                        }
+                        if copied == BATCH_SIZE {
+                            done = false;
+                            break;
+                        }
+                        if integer_key {
+                            dst_store.put(&mut writer, key, &value)?;
+                        } else {
+                            dst_store.append(&mut writer, key, &value)?;
+                        }
+                        last_key = Some(key.to_vec());
+                        copied += 1;
+                    }
+                    if done {
+                        checkpoint.put(&mut writer, "store", &Value::U64(index as u64 + 1))?;
+                        match checkpoint.delete(&mut writer, "key") {
+                            Ok(()) | Err(StoreError::KeyValuePairNotFound) => {}
+                            Err(err) => return Err(err.into()),
+                        }
+                        last_key = None;
+                    } else {
+                        let key = last_key.as_deref().unwrap_or_default();
+                        checkpoint.put(&mut writer, "store", &Value::U64(index as u64))?;
+                        checkpoint.put(&mut writer, "key", &Value::Blob(key))?;
+                    }
+                    writer.commit()?;
+                    committed = true;
+                    if done {
+                        break;
+                    }
+                }
+            }
+
+            // With the checkpoint gone, the destination is no longer mistaken for an
+            // unfinished migration, so running this again fails with `DestinationNotEmpty`.
+            dst_env.drop_store(CHECKPOINT_STORE)?;
+            Ok(())
+        }
+    };
+
     (open $migrate:tt, $name:tt, $builder:tt, $src_env:ty, $dst_env:ty) => {
         /// Same as the the `migrate_x_to_y` migration method above, but automatically attempts
         /// to open the source environment. Finally, deletes all of its supporting files if
@@ -123,6 +492,7 @@ macro_rules! fn_migrator {
         /// migration and doesn't delete any files if the following conditions apply:
         /// - Source environment is invalid/corrupted, unavailable, or empty.
         /// - Destination environment is not empty.
+        ///
         /// Use this instead of the other migration methods if:
         /// - You're not concerned by throwing away old data and starting fresh with a new store.
         /// - You'll never want to overwrite data in the new store from the old store.
@@ -165,7 +535,10 @@ macro_rules! fns_migrator {
     };
     ($name:tt, $src:tt, $dst:tt) => {
         paste::item! {
-            fn_migrator!($name, [<$src:camel Environment>], [<$dst:camel Environment>]);
+            fn_migrator!($name, [<$name _with_progress>], [<$src:camel Environment>], [<$dst:camel Environment>]);
+            fn_migrator!(progress [<$name _with_progress>], [<$src:camel Environment>], [<$dst:camel Environment>]);
+            fn_migrator!(with [<$name _with>], [<$src:camel Environment>], [<$dst:camel Environment>]);
+            fn_migrator!(resumable [<resumable_ $name>], [<$src:camel Environment>], [<$dst:camel Environment>]);
             fn_migrator!(open $name, [<open_and_ $name>], [<$src:camel>], [<$src:camel Environment>], [<$dst:camel Environment>]);
             fn_migrator!(easy [<open_and_ $name>], [<easy_ $name>], [<$src:camel Environment>], [<$dst:camel Environment>]);
         }
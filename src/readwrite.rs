@@ -8,18 +8,47 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
+use std::{collections::BTreeMap, ops::Bound, thread};
+
+use log::warn;
+
 use crate::{
     backend::{
-        BackendDatabase, BackendRoCursor, BackendRoCursorTransaction, BackendRoTransaction,
-        BackendRwCursorTransaction, BackendRwTransaction,
+        BackendDatabase, BackendRoCursor, BackendRoCursorTransaction, BackendRoRenewTransaction,
+        BackendRoTransaction, BackendRwCursorTransaction, BackendRwNestedTransaction,
+        BackendRwTransaction, DatabaseStat, SafeModeRoSnapshot,
     },
-    error::StoreError,
+    error::{DataError, StoreError},
     helpers::read_transform,
+    store::single::{Op, SingleStore},
     value::Value,
 };
 
 pub struct Reader<T>(T);
-pub struct Writer<T>(T);
+pub struct Writer<T>(T, ImplicitAbortCheck);
+
+/// Warns when a writer with changes is dropped without being committed or aborted, if
+/// the environment was built with `set_warn_on_implicit_abort(true)`. It's a field of
+/// the writer, rather than a `Drop` impl on it, so that `commit` and `abort` can still
+/// move the transaction out of the writer.
+struct ImplicitAbortCheck {
+    enabled: bool,
+    dirty: bool,
+}
+
+impl Drop for ImplicitAbortCheck {
+    fn drop(&mut self) {
+        if cfg!(debug_assertions) && self.enabled && self.dirty && !thread::panicking() {
+            warn!("Writer with uncommitted changes dropped; call `commit` or `abort` explicitly");
+        }
+    }
+}
+
+/// A SafeMode reader that owns its snapshots of the databases, as returned by
+/// `Rkv::read_snapshot`. The snapshots share their data with the environment, so taking
+/// one is cheap, and it's `Send` and `Sync`, so e.g. an `Arc<OwnedReader>` can be queried
+/// by many threads at once, all seeing the same consistent view.
+pub type OwnedReader = Reader<SafeModeRoSnapshot>;
 
 pub trait Readable<'r> {
     type Database: BackendDatabase;
@@ -29,7 +58,24 @@ pub trait Readable<'r> {
     where
         K: AsRef<[u8]>;
 
+    /// Get a value's tagged bytes as stored, without decoding them.
+    fn get_bytes<K>(&'r self, db: &Self::Database, k: &K) -> Result<Option<&'r [u8]>, StoreError>
+    where
+        K: AsRef<[u8]>;
+
+    /// Check whether a key is present without decoding its value.
+    fn exists<K>(&'r self, db: &Self::Database, k: &K) -> Result<bool, StoreError>
+    where
+        K: AsRef<[u8]>;
+
     fn open_ro_cursor(&'r self, db: &Self::Database) -> Result<Self::RoCursor, StoreError>;
+
+    fn stat(&'r self, db: &Self::Database) -> Result<DatabaseStat, StoreError>;
+
+    fn db_flags(
+        &'r self,
+        db: &Self::Database,
+    ) -> Result<<Self::Database as BackendDatabase>::Flags, StoreError>;
 }
 
 impl<'r, T> Readable<'r> for Reader<T>
@@ -50,9 +96,42 @@ where
         }
     }
 
+    fn get_bytes<K>(&'r self, db: &T::Database, k: &K) -> Result<Option<&'r [u8]>, StoreError>
+    where
+        K: AsRef<[u8]>,
+    {
+        match self.0.get(db, k.as_ref()).map_err(|e| e.into()) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(StoreError::KeyValuePairNotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn exists<K>(&'r self, db: &T::Database, k: &K) -> Result<bool, StoreError>
+    where
+        K: AsRef<[u8]>,
+    {
+        match self.0.get(db, k.as_ref()).map_err(|e| e.into()) {
+            Ok(_) => Ok(true),
+            Err(StoreError::KeyValuePairNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
     fn open_ro_cursor(&'r self, db: &T::Database) -> Result<T::RoCursor, StoreError> {
         self.0.open_ro_cursor(db).map_err(|e| e.into())
     }
+
+    fn stat(&'r self, db: &T::Database) -> Result<DatabaseStat, StoreError> {
+        self.0.stat(db).map_err(|e| e.into())
+    }
+
+    fn db_flags(
+        &'r self,
+        db: &T::Database,
+    ) -> Result<<T::Database as BackendDatabase>::Flags, StoreError> {
+        self.0.db_flags(db).map_err(|e| e.into())
+    }
 }
 
 impl<T> Reader<T> {
@@ -61,6 +140,31 @@ impl<T> Reader<T> {
     }
 }
 
+impl<'r, T> Reader<T>
+where
+    T: BackendRoCursorTransaction<'r>,
+{
+    /// Read a known set of keys from a store, returning a map of the keys that are
+    /// present to their values. Keys that aren't in the store are left out of the map.
+    pub fn get_map<K, I>(
+        &'r self,
+        store: &SingleStore<T::Database>,
+        keys: I,
+    ) -> Result<BTreeMap<Vec<u8>, Value<'r>>, StoreError>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = K>,
+    {
+        let mut map = BTreeMap::new();
+        for k in keys {
+            if let Some(v) = store.get(self, &k)? {
+                map.insert(k.as_ref().to_vec(), v);
+            }
+        }
+        Ok(map)
+    }
+}
+
 impl<T> Reader<T>
 where
     T: BackendRoTransaction,
@@ -70,6 +174,21 @@ where
     }
 }
 
+impl<T> Reader<T>
+where
+    T: BackendRoRenewTransaction,
+{
+    /// Move the reader to a snapshot of the latest commit, e.g. between the requests of
+    /// a long-lived handler, instead of dropping it and opening another one. Until then,
+    /// every read sees the same consistent snapshot, so helpers that need to read should
+    /// be passed the reader, rather than opening their own, which with LMDB fails with
+    /// `StoreError::ReadTransactionAlreadyExists` unless the environment was built with
+    /// `EnvironmentFlags::NO_TLS`. Values read before can't be used afterwards.
+    pub fn renew(&mut self) -> Result<(), StoreError> {
+        self.0.renew().map_err(|e| e.into())
+    }
+}
+
 impl<'r, T> Readable<'r> for Writer<T>
 where
     T: BackendRwCursorTransaction<'r>,
@@ -88,14 +207,53 @@ where
         }
     }
 
+    fn get_bytes<K>(&'r self, db: &T::Database, k: &K) -> Result<Option<&'r [u8]>, StoreError>
+    where
+        K: AsRef<[u8]>,
+    {
+        match self.0.get(db, k.as_ref()).map_err(|e| e.into()) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(StoreError::KeyValuePairNotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn exists<K>(&'r self, db: &T::Database, k: &K) -> Result<bool, StoreError>
+    where
+        K: AsRef<[u8]>,
+    {
+        match self.0.get(db, k.as_ref()).map_err(|e| e.into()) {
+            Ok(_) => Ok(true),
+            Err(StoreError::KeyValuePairNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
     fn open_ro_cursor(&'r self, db: &T::Database) -> Result<T::RoCursor, StoreError> {
         self.0.open_ro_cursor(db).map_err(|e| e.into())
     }
+
+    fn stat(&'r self, db: &T::Database) -> Result<DatabaseStat, StoreError> {
+        self.0.stat(db).map_err(|e| e.into())
+    }
+
+    fn db_flags(
+        &'r self,
+        db: &T::Database,
+    ) -> Result<<T::Database as BackendDatabase>::Flags, StoreError> {
+        self.0.db_flags(db).map_err(|e| e.into())
+    }
 }
 
 impl<T> Writer<T> {
-    pub(crate) fn new(txn: T) -> Writer<T> {
-        Writer(txn)
+    pub(crate) fn new(txn: T, warn_on_implicit_abort: bool) -> Writer<T> {
+        Writer(
+            txn,
+            ImplicitAbortCheck {
+                enabled: warn_on_implicit_abort,
+                dirty: false,
+            },
+        )
     }
 }
 
@@ -103,14 +261,101 @@ impl<T> Writer<T>
 where
     T: BackendRwTransaction,
 {
-    pub fn commit(self) -> Result<(), StoreError> {
+    pub fn commit(mut self) -> Result<(), StoreError> {
+        self.1.dirty = false;
         self.0.commit().map_err(|e| e.into())
     }
 
-    pub fn abort(self) {
+    pub fn abort(mut self) {
+        self.1.dirty = false;
         self.0.abort();
     }
 
+    /// Apply `ops` to `store` in order, like the corresponding calls to `put`, `delete`
+    /// and `clear` would, e.g. to apply a diff. Later operations see the changes of
+    /// earlier ones, and the first failure stops the batch, leaving the operations
+    /// before it applied.
+    pub fn extend<'v, K, I>(
+        &mut self,
+        store: &SingleStore<T::Database>,
+        ops: I,
+    ) -> Result<(), StoreError>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = Op<'v, K>>,
+    {
+        for op in ops {
+            match op {
+                Op::Put(k, v) => store.put(self, k, &v)?,
+                Op::Delete(k) => store.delete(self, k)?,
+                Op::Clear => store.clear(self)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Begin a write transaction nested in this one, e.g. for a speculative batch that
+    /// may have to be undone. Committing the nested writer folds its changes into this
+    /// one, while aborting or dropping it only discards its own changes. This writer
+    /// can't be used until the nested one ends.
+    ///
+    /// LMDB doesn't support nested transactions in environments opened with the
+    /// `WRITE_MAP` flag, which fail with `LmdbError(BadTxn)`.
+    pub fn begin_nested<'p>(&'p mut self) -> Result<Writer<T::Nested>, StoreError>
+    where
+        T: BackendRwNestedTransaction<'p>,
+    {
+        // This writer can't tell whether the nested one commits its changes into it.
+        self.1.dirty = true;
+        let warn_on_implicit_abort = self.1.enabled;
+        let txn = self.0.begin_nested_txn().map_err(|e| e.into())?;
+        Ok(Writer::new(txn, warn_on_implicit_abort))
+    }
+
+    /// Like `Readable::get`, but doesn't require the transaction to support cursors,
+    /// so stores can read their own bookkeeping values while writing.
+    pub(crate) fn read_value<K>(
+        &self,
+        db: &T::Database,
+        k: &K,
+    ) -> Result<Option<Value<'_>>, StoreError>
+    where
+        K: AsRef<[u8]>,
+    {
+        let bytes = self.0.get(db, k.as_ref()).map_err(|e| e.into());
+        match read_transform(bytes).map(Some) {
+            Err(StoreError::KeyValuePairNotFound) => Ok(None),
+            result => result,
+        }
+    }
+
+    /// Like `read_value`, but returns the stored bytes without decoding them.
+    pub(crate) fn read_bytes<K>(&self, db: &T::Database, k: &K) -> Result<Option<&[u8]>, StoreError>
+    where
+        K: AsRef<[u8]>,
+    {
+        match self.0.get(db, k.as_ref()).map_err(|e| e.into()) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(StoreError::KeyValuePairNotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Fail before the backend does, with a clearer error, if `k` is too long.
+    fn check_key_size(&self, k: &[u8]) -> Result<(), StoreError> {
+        match self.0.max_key_size() {
+            Some(max) if k.len() > max => Err(StoreError::KeyTooLong { len: k.len(), max }),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_value_size(&self, size: usize) -> Result<(), StoreError> {
+        match self.0.max_value_size() {
+            Some(max) if size > max => Err(DataError::ValueTooLarge { size, max }.into()),
+            _ => Ok(()),
+        }
+    }
+
     pub(crate) fn put<K>(
         &mut self,
         db: &T::Database,
@@ -121,9 +366,70 @@ where
     where
         K: AsRef<[u8]>,
     {
-        // TODO: don't allocate twice.
+        self.check_key_size(k.as_ref())?;
+        v.validate()?;
+        // Serialize the value directly into the space reserved by the backend, rather
+        // than into a temporary buffer that the backend would then have to copy.
+        let len = v.serialized_size()? as usize;
+        self.check_value_size(len)?;
+        self.1.dirty = true;
+        let mut written = Ok(());
+        self.0
+            .reserve(db, k.as_ref(), len, flags, |buf| {
+                written = v.write_bytes(buf)
+            })
+            .map_err(|e| e.into())?;
+        if let Err(err) = written {
+            // Don't leave the key holding a partially written value, which would be
+            // persisted if the caller committed anyway.
+            #[cfg(not(feature = "db-dup-sort"))]
+            let deleted = self.0.del(db, k.as_ref());
+            #[cfg(feature = "db-dup-sort")]
+            let deleted = self.0.del(db, k.as_ref(), None);
+            deleted.map_err(|e| e.into())?;
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    /// Write already tagged bytes, e.g. a value encoded by the store itself.
+    pub(crate) fn put_bytes<K>(
+        &mut self,
+        db: &T::Database,
+        k: &K,
+        bytes: &[u8],
+        flags: T::Flags,
+    ) -> Result<(), StoreError>
+    where
+        K: AsRef<[u8]>,
+    {
+        self.check_key_size(k.as_ref())?;
+        self.check_value_size(bytes.len())?;
+        self.1.dirty = true;
+        self.0
+            .put(db, k.as_ref(), bytes, flags)
+            .map_err(|e| e.into())
+    }
+
+    /// Databases that allow duplicate values can't have their space reserved, so the
+    /// value is serialized into a temporary buffer which the backend copies.
+    #[cfg(feature = "db-dup-sort")]
+    pub(crate) fn put_dup<K>(
+        &mut self,
+        db: &T::Database,
+        k: &K,
+        v: &Value,
+        flags: T::Flags,
+    ) -> Result<(), StoreError>
+    where
+        K: AsRef<[u8]>,
+    {
+        self.check_key_size(k.as_ref())?;
+        let bytes = v.to_bytes()?;
+        self.check_value_size(bytes.len())?;
+        self.1.dirty = true;
         self.0
-            .put(db, k.as_ref(), &v.to_bytes()?, flags)
+            .put(db, k.as_ref(), &bytes, flags)
             .map_err(|e| e.into())
     }
 
@@ -132,6 +438,7 @@ where
     where
         K: AsRef<[u8]>,
     {
+        self.1.dirty = true;
         self.0.del(db, k.as_ref()).map_err(|e| e.into())
     }
 
@@ -145,10 +452,40 @@ where
     where
         K: AsRef<[u8]>,
     {
+        self.1.dirty = true;
         self.0.del(db, k.as_ref(), v).map_err(|e| e.into())
     }
 
+    pub(crate) fn delete_range<F>(
+        &mut self,
+        db: &T::Database,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+        pred: F,
+    ) -> Result<Vec<Vec<u8>>, StoreError>
+    where
+        F: FnMut(&[u8], &[u8]) -> bool,
+    {
+        self.1.dirty = true;
+        self.0.del_range(db, from, to, pred).map_err(|e| e.into())
+    }
+
+    /// Call `f` with every pair in the range, without changing any of them.
+    pub(crate) fn scan_range<F>(
+        &self,
+        db: &T::Database,
+        from: Bound<&[u8]>,
+        to: Bound<&[u8]>,
+        f: F,
+    ) -> Result<(), StoreError>
+    where
+        F: FnMut(&[u8], &[u8]),
+    {
+        self.0.scan_range(db, from, to, f).map_err(|e| e.into())
+    }
+
     pub(crate) fn clear(&mut self, db: &T::Database) -> Result<(), StoreError> {
+        self.1.dirty = true;
         self.0.clear_db(db).map_err(|e| e.into())
     }
 }
@@ -8,9 +8,16 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
+pub mod expiring;
+pub mod integerordered;
 pub mod keys;
+pub mod list;
+pub mod log;
 pub mod single;
 
+#[cfg(feature = "compression")]
+pub mod compressed;
+
 #[cfg(feature = "db-dup-sort")]
 pub mod multi;
 
@@ -20,12 +27,23 @@ pub mod integer;
 #[cfg(all(feature = "db-dup-sort", feature = "db-int-key"))]
 pub mod integermulti;
 
-use crate::backend::BackendDatabaseFlags;
+use crate::backend::{BackendDatabaseFlags, WriteFlags};
 
 #[derive(Default, Debug, Copy, Clone)]
 pub struct Options<F> {
     pub create: bool,
     pub flags: F,
+    /// Reject keys that aren't valid UTF-8. Only applies to single stores.
+    pub utf8_keys: bool,
+    /// Record every change in a companion changelog store, which is what
+    /// `SingleStore::changes_since` reads. Only applies to single stores.
+    pub changelog: bool,
+    /// A write flag applied by every `SingleStore` method that writes values, e.g.
+    /// `Some(WriteFlags::NO_OVERWRITE)` for a store whose values are never replaced.
+    /// `put_with_flags` still takes its own, and `put_get_old`, `compare_and_swap` and
+    /// `increment`, which replace values by design, don't apply it. Only applies to
+    /// single stores.
+    pub default_write_flags: Option<WriteFlags>,
 }
 
 impl<F> Options<F>
@@ -36,6 +54,9 @@ where
         Options {
             create: true,
             flags: F::empty(),
+            utf8_keys: false,
+            changelog: false,
+            default_write_flags: None,
         }
     }
 }
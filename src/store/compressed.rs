@@ -0,0 +1,123 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use crate::{
+    backend::{BackendDatabase, BackendFlags, BackendRwTransaction},
+    error::StoreError,
+    readwrite::{Readable, Writer},
+    store::single::SingleStore,
+    value::{OwnedValue, Value},
+};
+
+type EmptyResult = Result<(), StoreError>;
+
+/// The tag of compressed values, followed by the zstd-compressed tagged bytes of the
+/// original value. It's outside of the range of `Type` tags, so readers that don't
+/// know about compression fail with `DataError::UnknownType` instead of misreading.
+const COMPRESSED_TAG: u8 = 0x80;
+
+/// How a `CompressedStore` compresses its values.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Compression {
+    /// The zstd compression level, from 1 (fastest) to 22 (smallest).
+    pub level: i32,
+    /// `Blob` and `Str` values shorter than this many bytes are stored uncompressed,
+    /// since compressing them would cost more than it saves.
+    pub threshold: usize,
+}
+
+impl Default for Compression {
+    fn default() -> Compression {
+        Compression {
+            level: zstd::DEFAULT_COMPRESSION_LEVEL,
+            threshold: 1024,
+        }
+    }
+}
+
+/// A single store that transparently compresses large `Blob` and `Str` values with
+/// zstd. Values that were written uncompressed, e.g. by a `SingleStore` before, can
+/// still be read. Since compressed values are decompressed into new buffers, they're
+/// returned as `OwnedValue`s.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct CompressedStore<D> {
+    inner: SingleStore<D>,
+    compression: Compression,
+}
+
+impl<D> CompressedStore<D>
+where
+    D: BackendDatabase,
+{
+    pub(crate) fn new(db: D, compression: Compression) -> CompressedStore<D> {
+        CompressedStore {
+            inner: SingleStore::new(db),
+            compression,
+        }
+    }
+
+    pub fn get<'r, R, K>(&self, reader: &'r R, k: K) -> Result<Option<OwnedValue>, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+        K: AsRef<[u8]>,
+    {
+        let bytes = match reader.get_bytes(self.inner.db(), &k)? {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        let value = match bytes.split_first() {
+            Some((&COMPRESSED_TAG, compressed)) => {
+                let decompressed = zstd::decode_all(compressed)?;
+                OwnedValue::from(&Value::from_tagged_slice(&decompressed)?)
+            }
+            _ => OwnedValue::from(&Value::from_tagged_slice(bytes)?),
+        };
+        Ok(Some(value))
+    }
+
+    /// Write a value, compressing it if it's a `Blob` or `Str` at least as long as the
+    /// compression threshold, and compressing it actually makes it smaller.
+    pub fn put<T, K>(&self, writer: &mut Writer<T>, k: K, v: &Value) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        let len = match v {
+            Value::Blob(v) => v.len(),
+            Value::Str(v) => v.len(),
+            _ => return self.inner.put(writer, k, v),
+        };
+        if len < self.compression.threshold {
+            return self.inner.put(writer, k, v);
+        }
+        let bytes = v.to_bytes()?;
+        let mut compressed = vec![COMPRESSED_TAG];
+        zstd::stream::copy_encode(&bytes[..], &mut compressed, self.compression.level)?;
+        if compressed.len() >= bytes.len() {
+            return self.inner.put(writer, k, v);
+        }
+        writer.put_bytes(self.inner.db(), &k, &compressed, T::Flags::empty())
+    }
+
+    pub fn delete<T, K>(&self, writer: &mut Writer<T>, k: K) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.inner.delete(writer, k)
+    }
+
+    pub fn clear<T>(&self, writer: &mut Writer<T>) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        self.inner.clear(writer)
+    }
+}
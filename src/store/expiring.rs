@@ -0,0 +1,133 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use std::{convert::TryFrom, ops::Bound, time::SystemTime};
+
+use crate::{
+    backend::{BackendDatabase, BackendFlags, BackendRwTransaction},
+    error::StoreError,
+    readwrite::{Readable, Writer},
+    store::single::SingleStore,
+    value::{millis_since_epoch, Value},
+};
+
+type EmptyResult = Result<(), StoreError>;
+
+/// A single store whose entries expire, e.g. for caches. Each value is stored after
+/// its expiry time, as big-endian milliseconds since the Unix epoch.
+///
+/// Expired entries are hidden from `get`, but they're only deleted by `purge_expired`,
+/// so reading never has to write.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct ExpiringStore<D> {
+    inner: SingleStore<D>,
+}
+
+impl<D> ExpiringStore<D>
+where
+    D: BackendDatabase,
+{
+    pub(crate) fn new(db: D) -> ExpiringStore<D> {
+        ExpiringStore {
+            inner: SingleStore::new(db),
+        }
+    }
+
+    /// Get a value, or `None` if it's missing or expired.
+    pub fn get<'r, R, K>(&self, reader: &'r R, k: K) -> Result<Option<Value<'r>>, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.get_at(reader, k, SystemTime::now())
+    }
+
+    /// Like `get`, but as of `now` rather than the current time.
+    pub fn get_at<'r, R, K>(
+        &self,
+        reader: &'r R,
+        k: K,
+        now: SystemTime,
+    ) -> Result<Option<Value<'r>>, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+        K: AsRef<[u8]>,
+    {
+        let bytes = match reader.get_bytes(self.inner.db(), &k)? {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        let (expiry, value) = split_expiry(bytes)?;
+        if expiry <= millis_since_epoch(now) {
+            return Ok(None);
+        }
+        Ok(Some(Value::from_tagged_slice(value)?))
+    }
+
+    /// Write a value that expires at `expiry`.
+    pub fn put<T, K>(
+        &self,
+        writer: &mut Writer<T>,
+        k: K,
+        v: &Value,
+        expiry: SystemTime,
+    ) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        let mut bytes = millis_since_epoch(expiry).to_be_bytes().to_vec();
+        bytes.append(&mut v.to_bytes()?);
+        writer.put_bytes(self.inner.db(), &k, &bytes, T::Flags::empty())
+    }
+
+    pub fn delete<T, K>(&self, writer: &mut Writer<T>, k: K) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.inner.delete(writer, k)
+    }
+
+    /// Delete every entry that expired as of `now`, returning how many were deleted.
+    pub fn purge_expired<T>(
+        &self,
+        writer: &mut Writer<T>,
+        now: SystemTime,
+    ) -> Result<usize, StoreError>
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        let now = millis_since_epoch(now);
+        let keys = writer.delete_range(
+            self.inner.db(),
+            Bound::Unbounded,
+            Bound::Unbounded,
+            |_, bytes| split_expiry(bytes).map_or(false, |(expiry, _)| expiry <= now),
+        )?;
+        Ok(keys.len())
+    }
+
+    pub fn clear<T>(&self, writer: &mut Writer<T>) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        self.inner.clear(writer)
+    }
+}
+
+fn split_expiry(bytes: &[u8]) -> Result<(i64, &[u8]), StoreError> {
+    if bytes.len() < 8 {
+        return Err(StoreError::DatabaseCorrupted);
+    }
+    let (expiry, value) = bytes.split_at(8);
+    let expiry = <[u8; 8]>::try_from(expiry).map_err(|_| StoreError::DatabaseCorrupted)?;
+    Ok((i64::from_be_bytes(expiry), value))
+}
@@ -55,6 +55,15 @@ where
         self.inner.put(writer, Key::new(&k)?, v)
     }
 
+    /// Insert a key/value pair at the end of the store. The key must be greater than
+    /// every key already in the store, see `SingleStore::append`.
+    pub fn append<T>(&self, writer: &mut Writer<T>, k: K, v: &Value) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        self.inner.append(writer, Key::new(&k)?, v)
+    }
+
     pub fn delete<T>(&self, writer: &mut Writer<T>, k: K) -> EmptyResult
     where
         T: BackendRwTransaction<Database = D>,
@@ -110,8 +119,8 @@ mod tests {
             }};
         }
 
-        test_integer_keys!(u32, std::u32::MIN);
-        test_integer_keys!(u32, std::u32::MAX);
+        test_integer_keys!(u32, u32::MIN);
+        test_integer_keys!(u32, u32::MAX);
     }
 
     #[test]
@@ -148,6 +157,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_append() {
+        let root = Builder::new()
+            .prefix("test_integer_append")
+            .tempdir()
+            .expect("tempdir");
+        fs::create_dir_all(root.path()).expect("dir created");
+
+        let k = Rkv::new::<backend::SafeMode>(root.path()).expect("new succeeded");
+        let s = k.open_integer("s", StoreOptions::create()).expect("open");
+
+        let mut writer = k.write().expect("writer");
+        // Keys are compared numerically, not by their (little-endian) encoding.
+        for key in [1, 2, 256, 65536].iter() {
+            s.append(&mut writer, *key, &Value::U64(u64::from(*key)))
+                .expect("appended");
+        }
+        assert!(matches!(
+            s.append(&mut writer, 3, &Value::U64(3)),
+            Err(StoreError::KeyOutOfOrder)
+        ));
+        writer.commit().expect("committed");
+
+        let reader = k.read().expect("reader");
+        assert_eq!(s.get(&reader, 256).expect("read"), Some(Value::U64(256)));
+        assert_eq!(s.get(&reader, 3).expect("read"), None);
+    }
+
     #[test]
     fn test_dup() {
         let root = Builder::new()
@@ -393,8 +430,8 @@ mod tests_safe {
             }};
         }
 
-        test_integer_keys!(u32, std::u32::MIN);
-        test_integer_keys!(u32, std::u32::MAX);
+        test_integer_keys!(u32, u32::MIN);
+        test_integer_keys!(u32, u32::MAX);
     }
 
     #[test]
@@ -142,8 +142,8 @@ mod tests {
             }};
         }
 
-        test_integer_keys!(u32, std::u32::MIN);
-        test_integer_keys!(u32, std::u32::MAX);
+        test_integer_keys!(u32, u32::MIN);
+        test_integer_keys!(u32, u32::MAX);
     }
 
     #[test]
@@ -446,8 +446,8 @@ mod tests_safe {
             }};
         }
 
-        test_integer_keys!(u32, std::u32::MIN);
-        test_integer_keys!(u32, std::u32::MAX);
+        test_integer_keys!(u32, u32::MIN);
+        test_integer_keys!(u32, u32::MAX);
     }
 
     #[test]
@@ -0,0 +1,206 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use std::marker::PhantomData;
+
+use crate::{
+    backend::{BackendDatabase, BackendIter, BackendRoCursor, BackendRwTransaction},
+    error::StoreError,
+    readwrite::{Readable, Writer},
+    store::{
+        keys::OrderedInt,
+        single::{self, SingleStore},
+    },
+    value::Value,
+};
+
+type EmptyResult = Result<(), StoreError>;
+
+/// An integer store whose keys are encoded by `OrderedInt`, so that it iterates in
+/// numeric key order. Unlike `IntegerStore`, it doesn't use the `INTEGER_KEY` flag,
+/// so the two can't open each other's databases.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct OrderedIntegerStore<D, K> {
+    inner: SingleStore<D>,
+    phantom: PhantomData<K>,
+}
+
+/// Iterates over the entries of an `OrderedIntegerStore` in numeric key order, decoding
+/// the keys.
+pub struct Iter<'i, I, K> {
+    iter: single::Iter<'i, I>,
+    phantom: PhantomData<K>,
+}
+
+impl<D, K> OrderedIntegerStore<D, K>
+where
+    D: BackendDatabase,
+    K: OrderedInt,
+{
+    pub(crate) fn new(db: D) -> OrderedIntegerStore<D, K> {
+        OrderedIntegerStore {
+            inner: SingleStore::new(db),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn get<'r, R>(&self, reader: &'r R, k: K) -> Result<Option<Value<'r>>, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+    {
+        self.inner.get(reader, k.to_key_bytes())
+    }
+
+    pub fn iter_start<'r, R, I, C>(&self, reader: &'r R) -> Result<Iter<'r, I, K>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+    {
+        Ok(Iter {
+            iter: self.inner.iter_start(reader)?,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Iterate from the first key greater than or equal to `k`.
+    pub fn iter_from<'r, R, I, C>(&self, reader: &'r R, k: K) -> Result<Iter<'r, I, K>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+    {
+        Ok(Iter {
+            iter: self.inner.iter_from(reader, k.to_key_bytes())?,
+            phantom: PhantomData,
+        })
+    }
+
+    pub fn put<T>(&self, writer: &mut Writer<T>, k: K, v: &Value) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        self.inner.put(writer, k.to_key_bytes(), v)
+    }
+
+    /// Insert a key/value pair at the end of the store. The key must be greater than
+    /// every key already in the store, see `SingleStore::append`.
+    pub fn append<T>(&self, writer: &mut Writer<T>, k: K, v: &Value) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        self.inner.append(writer, k.to_key_bytes(), v)
+    }
+
+    pub fn delete<T>(&self, writer: &mut Writer<T>, k: K) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        self.inner.delete(writer, k.to_key_bytes())
+    }
+
+    pub fn clear<T>(&self, writer: &mut Writer<T>) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        self.inner.clear(writer)
+    }
+}
+
+impl<'i, I, K> Iterator for Iter<'i, I, K>
+where
+    I: BackendIter<'i>,
+    K: OrderedInt,
+{
+    type Item = Result<(K, Value<'i>), StoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.iter.next()?.and_then(|(key, value)| {
+            // Keys of another size were written with another key type.
+            let key = K::from_key_bytes(key).ok_or(StoreError::DatabaseCorrupted)?;
+            Ok((key, value))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    use std::fs;
+
+    use tempfile::Builder;
+
+    #[test]
+    fn test_append() {
+        let root = Builder::new()
+            .prefix("test_ordered_integer_append")
+            .tempdir()
+            .expect("tempdir");
+        fs::create_dir_all(root.path()).expect("dir created");
+
+        let k = Rkv::new::<backend::SafeMode>(root.path()).expect("new succeeded");
+        let s = k
+            .open_ordered_integer("s", StoreOptions::create())
+            .expect("open");
+
+        let mut writer = k.write().expect("writer");
+        for key in [1u32, 2, 256, 65536].iter() {
+            s.append(&mut writer, *key, &Value::U64(u64::from(*key)))
+                .expect("appended");
+        }
+        assert!(matches!(
+            s.append(&mut writer, 3, &Value::U64(3)),
+            Err(StoreError::KeyOutOfOrder)
+        ));
+        writer.commit().expect("committed");
+
+        let reader = k.read().expect("reader");
+        assert_eq!(s.get(&reader, 256).expect("read"), Some(Value::U64(256)));
+        assert_eq!(s.get(&reader, 3).expect("read"), None);
+    }
+
+    #[test]
+    fn test_del() {
+        let root = Builder::new()
+            .prefix("test_ordered_integer_del")
+            .tempdir()
+            .expect("tempdir");
+        fs::create_dir_all(root.path()).expect("dir created");
+
+        let k = Rkv::new::<backend::SafeMode>(root.path()).expect("new succeeded");
+        let s = k
+            .open_ordered_integer("s", StoreOptions::create())
+            .expect("open");
+
+        {
+            let mut writer = k.write().expect("writer");
+            s.put(&mut writer, -1i64, &Value::Str("hello!"))
+                .expect("write");
+            s.put(&mut writer, 1, &Value::Str("hello!")).expect("write");
+            s.delete(&mut writer, -1).expect("deleted");
+            s.delete(&mut writer, 2).expect_err("not deleted");
+            writer.commit().expect("committed");
+        }
+
+        {
+            let mut writer = k.write().expect("writer");
+            let reader = k.read().expect("reader");
+            assert_eq!(s.get(&reader, -1).expect("read"), None);
+            assert_eq!(s.get(&reader, 1).expect("read"), Some(Value::Str("hello!")));
+            s.clear(&mut writer).expect("cleared");
+            writer.commit().expect("committed");
+        }
+
+        let reader = k.read().expect("reader");
+        assert_eq!(s.get(&reader, 1).expect("read"), None);
+    }
+}
@@ -8,6 +8,7 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
+mod composite;
 mod encodables;
 mod primitives;
 
@@ -15,6 +16,7 @@ use std::marker::PhantomData;
 
 use crate::error::DataError;
 
+pub use composite::*;
 pub use encodables::*;
 pub use primitives::*;
 
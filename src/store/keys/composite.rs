@@ -0,0 +1,88 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use std::{convert::TryFrom, mem::size_of};
+
+/// The size of the length that precedes each component.
+const LEN_SIZE: usize = size_of::<u32>();
+
+/// A key made of several components, e.g. a user ID and a session ID, without a
+/// separator that the components themselves could contain.
+///
+/// Each component is preceded by its length, as a big-endian `u32`. So the bytes of a
+/// key with some leading components are a prefix of the keys that start with the same
+/// components, and only of those: pass it to `SingleStore::iter_prefix` to scan them.
+/// Keys sort component by component, by length first, then by bytes.
+#[derive(Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone)]
+pub struct CompositeKey {
+    bytes: Vec<u8>,
+}
+
+impl CompositeKey {
+    pub fn new() -> CompositeKey {
+        CompositeKey::default()
+    }
+
+    /// Append a component, which must be shorter than 4 GiB.
+    pub fn push<C>(mut self, component: C) -> CompositeKey
+    where
+        C: AsRef<[u8]>,
+    {
+        let component = component.as_ref();
+        let len = u32::try_from(component.len()).expect("component shorter than 4 GiB");
+        self.bytes.extend_from_slice(&len.to_be_bytes());
+        self.bytes.extend_from_slice(component);
+        self
+    }
+
+    /// Split the bytes of a key into its components, or return `None` if they weren't
+    /// encoded by a `CompositeKey`.
+    pub fn split(mut bytes: &[u8]) -> Option<Vec<&[u8]>> {
+        let mut components = vec![];
+        while !bytes.is_empty() {
+            if bytes.len() < LEN_SIZE {
+                return None;
+            }
+            let (len, rest) = bytes.split_at(LEN_SIZE);
+            let len = u32::from_be_bytes(<[u8; LEN_SIZE]>::try_from(len).ok()?) as usize;
+            if rest.len() < len {
+                return None;
+            }
+            let (component, rest) = rest.split_at(len);
+            components.push(component);
+            bytes = rest;
+        }
+        Some(components)
+    }
+}
+
+impl AsRef<[u8]> for CompositeKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composite_key() {
+        let key = CompositeKey::new().push("user").push([0xff]).push("");
+        assert_eq!(key.as_ref(), b"\0\0\0\x04user\0\0\0\x01\xff\0\0\0\0");
+        assert_eq!(
+            CompositeKey::split(key.as_ref()),
+            Some(vec![&b"user"[..], &[0xff], b""])
+        );
+        assert_eq!(CompositeKey::split(b""), Some(vec![]));
+        assert_eq!(CompositeKey::split(b"\0\0\0"), None);
+        assert_eq!(CompositeKey::split(b"\0\0\0\x02a"), None);
+    }
+}
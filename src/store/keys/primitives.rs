@@ -8,8 +8,72 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
+use std::{convert::TryFrom, mem::size_of};
+
 use crate::store::keys::EncodableKey;
 
 pub trait PrimitiveInt: EncodableKey {}
 
 impl PrimitiveInt for u32 {}
+
+/// Integers that can be used as the keys of ordered integer stores.
+///
+/// Keys are encoded as fixed-width big-endian bytes, with the sign bit of signed integers
+/// flipped, so that their byte order is their numeric order, and ordered integer stores
+/// iterate in numeric order on every backend.
+pub trait OrderedInt: Copy {
+    /// Encode the integer as a key.
+    fn to_key_bytes(self) -> Vec<u8>;
+
+    /// Decode a key encoded by `to_key_bytes`, or return `None` if it has the wrong size.
+    fn from_key_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {$(
+        impl OrderedInt for $t {
+            fn to_key_bytes(self) -> Vec<u8> {
+                self.to_be_bytes().to_vec()
+            }
+
+            fn from_key_bytes(bytes: &[u8]) -> Option<$t> {
+                let bytes = <[u8; size_of::<$t>()]>::try_from(bytes).ok()?;
+                Some(<$t>::from_be_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_signed {
+    ($($t:ty => $u:ty),*) => {$(
+        impl OrderedInt for $t {
+            fn to_key_bytes(self) -> Vec<u8> {
+                (self as $u ^ !(<$u>::MAX >> 1)).to_key_bytes()
+            }
+
+            fn from_key_bytes(bytes: &[u8]) -> Option<$t> {
+                <$u>::from_key_bytes(bytes).map(|u| (u ^ !(<$u>::MAX >> 1)) as $t)
+            }
+        }
+    )*};
+}
+
+impl_unsigned!(u8, u16, u32, u64);
+impl_signed!(i8 => u8, i16 => u16, i32 => u32, i64 => u64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_bytes() {
+        assert_eq!(1u32.to_key_bytes(), [0, 0, 0, 1]);
+        assert_eq!(256u16.to_key_bytes(), [1, 0]);
+        assert_eq!(0i8.to_key_bytes(), [0x80]);
+        assert_eq!((-1i16).to_key_bytes(), [0x7f, 0xff]);
+        assert_eq!(i64::MIN.to_key_bytes(), [0; 8]);
+        assert_eq!(u64::from_key_bytes(&[0, 0, 0, 0, 0, 0, 1, 0]), Some(256));
+        assert_eq!(i32::from_key_bytes(&[0x7f, 0xff, 0xff, 0xff]), Some(-1));
+        assert_eq!(u32::from_key_bytes(&[0, 1]), None);
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use crate::{
+    backend::{BackendDatabase, BackendFlags, BackendRwTransaction},
+    error::{DataError, StoreError},
+    readwrite::{Readable, Writer},
+    store::single::SingleStore,
+    value::{OwnedValue, Type, Value},
+};
+
+type EmptyResult = Result<(), StoreError>;
+
+/// The tag of lists, followed by the bincode-encoded tagged bytes of their values. Like
+/// `CompressedStore`'s tag, it's outside of the range of `Type` tags, so a `SingleStore`
+/// fails with `DataError::UnknownType` instead of misreading a list.
+const LIST_TAG: u8 = 0x81;
+
+/// A single store whose values are small lists of values, each stored as one blob under
+/// its key, for when the lists don't warrant a `MultiStore`. A missing key reads as an
+/// empty list. Lists are decoded into new buffers, so they're returned as `OwnedValue`s.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct ListStore<D> {
+    inner: SingleStore<D>,
+}
+
+impl<D> ListStore<D>
+where
+    D: BackendDatabase,
+{
+    pub(crate) fn new(db: D) -> ListStore<D> {
+        ListStore {
+            inner: SingleStore::new(db),
+        }
+    }
+
+    pub fn get_list<'r, R, K>(&self, reader: &'r R, k: K) -> Result<Vec<OwnedValue>, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+        K: AsRef<[u8]>,
+    {
+        decode_list(reader.get_bytes(self.inner.db(), &k)?)
+    }
+
+    /// Replace the list at `k`. Writing an empty list deletes the key.
+    pub fn put_list<T, K>(&self, writer: &mut Writer<T>, k: K, values: &[Value]) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        let values = values
+            .iter()
+            .map(Value::to_bytes)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.write_encoded(writer, k, values)
+    }
+
+    /// Append a value to the end of the list at `k`, within the writer's transaction.
+    pub fn push<T, K>(&self, writer: &mut Writer<T>, k: K, v: &Value) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        let mut values = decode_encoded(writer.read_bytes(self.inner.db(), &k)?)?;
+        values.push(v.to_bytes()?);
+        self.write_encoded(writer, k, values)
+    }
+
+    /// Remove and return the last value of the list at `k`, or `None` if it's empty.
+    pub fn pop<T, K>(&self, writer: &mut Writer<T>, k: K) -> Result<Option<OwnedValue>, StoreError>
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        let mut values = decode_encoded(writer.read_bytes(self.inner.db(), &k)?)?;
+        let last = match values.pop() {
+            Some(last) => OwnedValue::from(&Value::from_tagged_slice(&last)?),
+            None => return Ok(None),
+        };
+        self.write_encoded(writer, k, values)?;
+        Ok(Some(last))
+    }
+
+    pub fn delete<T, K>(&self, writer: &mut Writer<T>, k: K) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.inner.delete(writer, k)
+    }
+
+    pub fn clear<T>(&self, writer: &mut Writer<T>) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        self.inner.clear(writer)
+    }
+
+    fn write_encoded<T, K>(&self, writer: &mut Writer<T>, k: K, values: Vec<Vec<u8>>) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        if values.is_empty() {
+            return match self.inner.delete(writer, k) {
+                Err(StoreError::KeyValuePairNotFound) => Ok(()),
+                result => result,
+            };
+        }
+        let mut bytes = vec![LIST_TAG];
+        bincode::serialize_into(&mut bytes, &values).map_err(DataError::EncodingError)?;
+        writer.put_bytes(self.inner.db(), &k, &bytes, T::Flags::empty())
+    }
+}
+
+/// Split a stored list into the tagged bytes of its values.
+fn decode_encoded(bytes: Option<&[u8]>) -> Result<Vec<Vec<u8>>, StoreError> {
+    match bytes.map(|bytes| bytes.split_first()) {
+        None => Ok(vec![]),
+        Some(Some((&LIST_TAG, encoded))) => {
+            bincode::deserialize(encoded).map_err(|_| StoreError::DatabaseCorrupted)
+        }
+        Some(Some((&tag, _))) => match Type::from_tag(tag) {
+            Ok(t) => Err(DataError::NotAList(t).into()),
+            Err(err) => Err(err.into()),
+        },
+        Some(None) => Err(DataError::Empty.into()),
+    }
+}
+
+fn decode_list(bytes: Option<&[u8]>) -> Result<Vec<OwnedValue>, StoreError> {
+    decode_encoded(bytes)?
+        .iter()
+        .map(|bytes| Ok(OwnedValue::from(&Value::from_tagged_slice(bytes)?)))
+        .collect()
+}
@@ -0,0 +1,140 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use std::{convert::TryFrom, marker::PhantomData};
+
+use crate::{
+    backend::{
+        BackendDatabase, BackendFlags, BackendIter, BackendRoCursor, BackendRwTransaction,
+        BackendWriteFlags, WriteFlags,
+    },
+    error::StoreError,
+    helpers::read_transform,
+    readwrite::{Readable, Writer},
+    value::Value,
+};
+
+/// The key under which the next sequence number is persisted. Being a single byte
+/// long, it sorts before every (eight byte) entry key, so entries can always be
+/// written with the `APPEND` flag.
+const NEXT_SEQUENCE_KEY: &[u8] = &[0];
+
+/// An append-only store whose entries are keyed by monotonically increasing
+/// sequence numbers, e.g. for event logs. Keys are encoded as big-endian `u64`s so
+/// that their byte order matches their numeric order.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct LogStore<D> {
+    db: D,
+}
+
+pub struct Iter<'i, I> {
+    iter: I,
+    to: u64,
+    phantom: PhantomData<&'i ()>,
+}
+
+impl<D> LogStore<D>
+where
+    D: BackendDatabase,
+{
+    pub(crate) fn new(db: D) -> LogStore<D> {
+        LogStore { db }
+    }
+
+    /// The sequence number that will be assigned to the next appended entry.
+    pub fn next_sequence<'r, R>(&self, reader: &'r R) -> Result<u64, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+    {
+        to_sequence(reader.get(&self.db, &NEXT_SEQUENCE_KEY)?)
+    }
+
+    pub fn get<'r, R>(&self, reader: &'r R, seq: u64) -> Result<Option<Value<'r>>, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+    {
+        reader.get(&self.db, &seq.to_be_bytes())
+    }
+
+    /// Append a value to the log, returning the sequence number assigned to it.
+    pub fn append<T>(&self, writer: &mut Writer<T>, v: &Value) -> Result<u64, StoreError>
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        let seq = to_sequence(writer.read_value(&self.db, &NEXT_SEQUENCE_KEY)?)?;
+        let mut flags = T::Flags::empty();
+        flags.set(WriteFlags::APPEND, true);
+        writer.put(&self.db, &seq.to_be_bytes(), v, flags)?;
+        writer.put(
+            &self.db,
+            &NEXT_SEQUENCE_KEY,
+            &Value::U64(seq + 1),
+            T::Flags::empty(),
+        )?;
+        Ok(seq)
+    }
+
+    /// Iterate over the entries whose sequence numbers are in the range `from..to`.
+    pub fn range<'r, R, I, C>(
+        &self,
+        reader: &'r R,
+        from: u64,
+        to: u64,
+    ) -> Result<Iter<'r, I>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+    {
+        let cursor = reader.open_ro_cursor(&self.db)?;
+        let iter = cursor.into_iter_from(from.to_be_bytes());
+
+        Ok(Iter {
+            iter,
+            to,
+            phantom: PhantomData,
+        })
+    }
+}
+
+fn to_sequence(value: Option<Value<'_>>) -> Result<u64, StoreError> {
+    match value {
+        Some(Value::U64(seq)) => Ok(seq),
+        Some(_) => Err(StoreError::DatabaseCorrupted),
+        None => Ok(0),
+    }
+}
+
+impl<'i, I> Iterator for Iter<'i, I>
+where
+    I: BackendIter<'i>,
+{
+    type Item = Result<(u64, Value<'i>), StoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.next() {
+            None => None,
+            Some(Ok((key, bytes))) => {
+                let seq = match <[u8; 8]>::try_from(key) {
+                    Ok(key) => u64::from_be_bytes(key),
+                    Err(_) => return Some(Err(StoreError::DatabaseCorrupted)),
+                };
+                if seq >= self.to {
+                    return None;
+                }
+                match read_transform(Ok(bytes)) {
+                    Ok(val) => Some(Ok((seq, val))),
+                    Err(err) => Some(Err(err)),
+                }
+            }
+            Some(Err(err)) => Some(Err(err.into())),
+        }
+    }
+}
@@ -65,6 +65,40 @@ where
         reader.get(&self.db, &k)
     }
 
+    /// Provides a cursor to every key/value pair in the store, in key order and then
+    /// value order for the values of each key.
+    pub fn iter_start<'r, R, I, C>(&self, reader: &'r R) -> Result<Iter<'r, I>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+    {
+        let cursor = reader.open_ro_cursor(&self.db)?;
+        let iter = cursor.into_iter();
+
+        Ok(Iter {
+            iter,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Like `iter_start`, but starts from the first key greater than or equal to `k`.
+    pub fn iter_from<'r, R, I, C, K>(&self, reader: &'r R, k: K) -> Result<Iter<'r, I>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+        K: AsRef<[u8]> + 'r,
+    {
+        let cursor = reader.open_ro_cursor(&self.db)?;
+        let iter = cursor.into_iter_from(k);
+
+        Ok(Iter {
+            iter,
+            phantom: PhantomData,
+        })
+    }
+
     /// Insert a value at the specified key.
     /// This put will allow duplicate entries.  If you wish to have duplicate entries
     /// rejected, use the `put_with_flags` function and specify NO_DUP_DATA
@@ -73,7 +107,7 @@ where
         T: BackendRwTransaction<Database = D>,
         K: AsRef<[u8]>,
     {
-        writer.put(&self.db, &k, v, T::Flags::empty())
+        writer.put_dup(&self.db, &k, v, T::Flags::empty())
     }
 
     pub fn put_with_flags<T, K>(
@@ -87,7 +121,7 @@ where
         T: BackendRwTransaction<Database = D>,
         K: AsRef<[u8]>,
     {
-        writer.put(&self.db, &k, v, flags)
+        writer.put_dup(&self.db, &k, v, flags)
     }
 
     pub fn delete_all<T, K>(&self, writer: &mut Writer<T>, k: K) -> EmptyResult
@@ -8,14 +8,23 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
-use std::marker::PhantomData;
+use std::{
+    convert::TryFrom,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+    str,
+};
 
 use crate::{
-    backend::{BackendDatabase, BackendFlags, BackendIter, BackendRoCursor, BackendRwTransaction},
-    error::StoreError,
+    backend::{
+        BackendDatabase, BackendFlags, BackendIter, BackendRoCursor, BackendRwTransaction,
+        BackendWriteFlags, CursorOp, DatabaseStat, WriteFlags,
+    },
+    error::{DataError, StoreError},
     helpers::read_transform,
     readwrite::{Readable, Writer},
-    value::Value,
+    store::log::LogStore,
+    value::{OwnedValue, Type, Value},
 };
 
 type EmptyResult = Result<(), StoreError>;
@@ -23,19 +32,192 @@ type EmptyResult = Result<(), StoreError>;
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub struct SingleStore<D> {
     db: D,
+    utf8_keys: bool,
+    write_flags: Option<WriteFlags>,
+    changelog: Option<LogStore<D>>,
+}
+
+/// The kind of change recorded in a store's changelog.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ChangeOp {
+    Put = 0,
+    Delete = 1,
+    /// The whole store was cleared. The change's key is empty.
+    Clear = 2,
+}
+
+/// An operation of a batch applied with `Writer::extend`.
+#[derive(Debug, PartialEq)]
+pub enum Op<'v, K> {
+    Put(K, Value<'v>),
+    Delete(K),
+    Clear,
+}
+
+/// A change recorded in a store's changelog.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Change {
+    pub generation: u64,
+    pub op: ChangeOp,
+    pub key: Vec<u8>,
 }
 
 pub struct Iter<'i, I> {
     iter: I,
+    utf8_keys: bool,
+    phantom: PhantomData<&'i ()>,
+}
+
+/// Like `Iter`, but yields keys as string slices.
+pub struct StrIter<'i, I> {
+    iter: Iter<'i, I>,
+}
+
+/// Like `Iter`, but yields owned keys and values, which outlive the reader.
+pub struct OwnedIter<'i, I> {
+    iter: Iter<'i, I>,
+}
+
+/// Like `Iter`, but ends at the first key that doesn't start with a prefix.
+pub struct PrefixIter<'i, I> {
+    iter: I,
+    prefix: Vec<u8>,
+    utf8_keys: bool,
+    done: bool,
     phantom: PhantomData<&'i ()>,
 }
 
+/// Like `Iter`, but decodes each value separately from reading its entry, so a value
+/// that can't be decoded doesn't end the scan.
+pub struct LossyIter<'i, I> {
+    iter: I,
+    phantom: PhantomData<&'i ()>,
+}
+
+/// Like `Iter`, but yields only the values.
+pub struct ValuesIter<'i, I> {
+    iter: I,
+    phantom: PhantomData<&'i ()>,
+}
+
+/// A cursor that can be positioned at any key, and then stepped forward or backward
+/// from there. Stepping a cursor that hasn't been positioned yet starts
+/// from the first or last entry.
+pub struct Cursor<'c, C> {
+    cursor: C,
+    utf8_keys: bool,
+    phantom: PhantomData<&'c ()>,
+}
+
 impl<D> SingleStore<D>
 where
     D: BackendDatabase,
 {
     pub(crate) fn new(db: D) -> SingleStore<D> {
-        SingleStore { db }
+        SingleStore {
+            db,
+            utf8_keys: false,
+            write_flags: None,
+            changelog: None,
+        }
+    }
+
+    pub(crate) fn db(&self) -> &D {
+        &self.db
+    }
+
+    pub(crate) fn with_changelog(mut self, changelog: LogStore<D>) -> SingleStore<D> {
+        self.changelog = Some(changelog);
+        self
+    }
+
+    pub(crate) fn with_utf8_keys(mut self, utf8_keys: bool) -> SingleStore<D> {
+        self.utf8_keys = utf8_keys;
+        self
+    }
+
+    pub(crate) fn with_write_flags(mut self, write_flags: Option<WriteFlags>) -> SingleStore<D> {
+        self.write_flags = write_flags;
+        self
+    }
+
+    /// The flags that every write starts from: the store's default ones, if any.
+    fn write_flags<F: BackendWriteFlags>(&self) -> F {
+        let mut flags = F::empty();
+        if let Some(flag) = self.write_flags {
+            flags.set(flag, true);
+        }
+        flags
+    }
+
+    fn check_key<K>(&self, k: &K) -> EmptyResult
+    where
+        K: AsRef<[u8]>,
+    {
+        if self.utf8_keys {
+            str::from_utf8(k.as_ref()).map_err(|_| StoreError::NonUtf8Key)?;
+        }
+        Ok(())
+    }
+
+    fn log_change<T>(&self, writer: &mut Writer<T>, op: ChangeOp, k: &[u8]) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+    {
+        if let Some(changelog) = &self.changelog {
+            let mut entry = Vec::with_capacity(k.len() + 1);
+            entry.push(op as u8);
+            entry.extend_from_slice(k);
+            changelog.append(writer, &Value::Blob(&entry))?;
+        }
+        Ok(())
+    }
+
+    /// The generation the store's next change will be recorded at. Pass it to
+    /// `changes_since` later on to get the changes made in the meantime.
+    pub fn generation<'r, R>(&self, reader: &'r R) -> Result<u64, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+    {
+        match &self.changelog {
+            Some(changelog) => changelog.next_sequence(reader),
+            None => Err(StoreError::NoChangelog),
+        }
+    }
+
+    /// Return the changes recorded at or after `generation`, oldest first. Requires the
+    /// store to have been opened with the `changelog` option.
+    pub fn changes_since<'r, R, I, C>(
+        &self,
+        reader: &'r R,
+        generation: u64,
+    ) -> Result<Vec<Change>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+    {
+        let changelog = self.changelog.as_ref().ok_or(StoreError::NoChangelog)?;
+        let mut changes = vec![];
+        for entry in changelog.range(reader, generation, u64::MAX)? {
+            let (generation, value) = entry?;
+            let (op, key) = match value {
+                Value::Blob(entry) if !entry.is_empty() => (entry[0], &entry[1..]),
+                _ => return Err(StoreError::DatabaseCorrupted),
+            };
+            let op = match op {
+                0 => ChangeOp::Put,
+                1 => ChangeOp::Delete,
+                2 => ChangeOp::Clear,
+                _ => return Err(StoreError::DatabaseCorrupted),
+            };
+            changes.push(Change {
+                generation,
+                op,
+                key: key.to_vec(),
+            });
+        }
+        Ok(changes)
     }
 
     pub fn get<'r, R, K>(&self, reader: &'r R, k: K) -> Result<Option<Value<'r>>, StoreError>
@@ -43,16 +225,273 @@ where
         R: Readable<'r, Database = D>,
         K: AsRef<[u8]>,
     {
+        self.check_key(&k)?;
         reader.get(&self.db, &k)
     }
 
-    // TODO: flags
+    /// Look up many keys at once, all from the reader's snapshot. The values are
+    /// returned in the order of the keys, with `None` for missing keys.
+    pub fn get_many<'r, R, I, K>(
+        &self,
+        reader: &'r R,
+        keys: I,
+    ) -> Result<Vec<Option<Value<'r>>>, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        keys.into_iter().map(|k| self.get(reader, k)).collect()
+    }
+
+    /// Check whether a key is present. Unlike `get`, this doesn't decode the value, so
+    /// it succeeds even if the stored value can't be decoded.
+    pub fn exists<'r, R, K>(&self, reader: &'r R, k: K) -> Result<bool, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.check_key(&k)?;
+        reader.exists(&self.db, &k)
+    }
+
+    /// Get a value's bytes verbatim, without decoding them, e.g. to read data that was
+    /// written by another tool, or with `put_raw`.
+    pub fn get_raw<'r, R, K>(&self, reader: &'r R, k: K) -> Result<Option<&'r [u8]>, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.check_key(&k)?;
+        reader.get_bytes(&self.db, &k)
+    }
+
+    /// Statistics about the store, such as its number of entries and size.
+    pub fn stat<'r, R>(&self, reader: &'r R) -> Result<DatabaseStat, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+    {
+        reader.stat(&self.db)
+    }
+
+    /// The flags the store's database was created with, e.g. to check that it has
+    /// `DatabaseFlags::DUP_SORT`.
+    pub fn flags<'r, R>(&self, reader: &'r R) -> Result<D::Flags, StoreError>
+    where
+        R: Readable<'r, Database = D>,
+    {
+        reader.db_flags(&self.db)
+    }
+
+    /// Write a value, with the store's default write flags, if it was opened with any.
     pub fn put<T, K>(&self, writer: &mut Writer<T>, k: K, v: &Value) -> EmptyResult
     where
         T: BackendRwTransaction<Database = D>,
         K: AsRef<[u8]>,
     {
-        writer.put(&self.db, &k, v, T::Flags::empty())
+        self.put_with_flags(writer, k, v, self.write_flags::<T::Flags>())
+    }
+
+    /// Like `put`, but with an `OwnedValue`, e.g. one read with `get` and converted to
+    /// release its borrow of the writer.
+    pub fn put_owned<T, K>(&self, writer: &mut Writer<T>, k: K, v: &OwnedValue) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.put(writer, k, &Value::from(v))
+    }
+
+    /// Like `put`, but with write flags, e.g. `WriteFlags::NO_OVERWRITE` to fail with
+    /// `StoreError::KeyValuePairExists` instead of replacing an existing value.
+    pub fn put_with_flags<T, K, F>(
+        &self,
+        writer: &mut Writer<T>,
+        k: K,
+        v: &Value,
+        flags: F,
+    ) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+        F: Into<T::Flags>,
+    {
+        self.check_key(&k)?;
+        writer.put(&self.db, &k, v, flags.into())?;
+        self.log_change(writer, ChangeOp::Put, k.as_ref())
+    }
+
+    /// Like `put`, but returns the value the key held before, or `None` if it was absent.
+    /// The old value is read within the writer's transaction, and returned as an
+    /// `OwnedValue`, since overwriting it may reuse its storage.
+    pub fn put_get_old<T, K>(
+        &self,
+        writer: &mut Writer<T>,
+        k: K,
+        v: &Value,
+    ) -> Result<Option<OwnedValue>, StoreError>
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.check_key(&k)?;
+        let old = writer
+            .read_value(&self.db, &k)?
+            .as_ref()
+            .map(OwnedValue::from);
+        // Replacing the value is the point, so the store's default flags don't apply.
+        writer.put(&self.db, &k, v, T::Flags::empty())?;
+        self.log_change(writer, ChangeOp::Put, k.as_ref())?;
+        Ok(old)
+    }
+
+    /// Write bytes verbatim, without the type tag of a `Value`, e.g. for other tools to
+    /// read. Reading them back with `get` or iterating over them fails with a
+    /// `DataError` unless they happen to be a valid tagged value, so use `get_raw`.
+    pub fn put_raw<T, K>(&self, writer: &mut Writer<T>, k: K, bytes: &[u8]) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.check_key(&k)?;
+        writer.put_bytes(&self.db, &k, bytes, self.write_flags())?;
+        self.log_change(writer, ChangeOp::Put, k.as_ref())
+    }
+
+    /// Like `put`, but takes anything convertible to a `Value`, e.g. `1234_i64` or `"hello"`.
+    pub fn put_into<'v, T, K, V>(&self, writer: &mut Writer<T>, k: K, v: V) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+        V: Into<Value<'v>>,
+    {
+        self.put(writer, k, &v.into())
+    }
+
+    /// Insert many key/value pairs within the writer's transaction. Useful when
+    /// bulk-loading a store.
+    pub fn put_many<'v, T, K, I>(&self, writer: &mut Writer<T>, items: I) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = (K, Value<'v>)>,
+    {
+        // There's no scratch buffer to reuse across values: `Writer::put` serializes
+        // each one straight into the space the backend reserves for it, which also
+        // saves copying it out of such a buffer.
+        for (k, v) in items {
+            self.check_key(&k)?;
+            writer.put(&self.db, &k, &v, self.write_flags())?;
+            self.log_change(writer, ChangeOp::Put, k.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Insert a key/value pair at the end of the store, which is much faster than `put`
+    /// when bulk-loading sorted data. The key must sort after every key already in the
+    /// store, otherwise `StoreError::KeyOutOfOrder` is returned.
+    pub fn append<T, K>(&self, writer: &mut Writer<T>, k: K, v: &Value) -> EmptyResult
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.check_key(&k)?;
+        let mut flags: T::Flags = self.write_flags();
+        flags.set(WriteFlags::APPEND, true);
+        match writer.put(&self.db, &k, v, flags) {
+            Err(StoreError::KeyValuePairExists) => Err(StoreError::KeyOutOfOrder),
+            result => result,
+        }?;
+        self.log_change(writer, ChangeOp::Put, k.as_ref())
+    }
+
+    /// Insert a key/value pair only if the key isn't already present. Returns `true` if
+    /// the pair was inserted, or `false` if the key already exists.
+    pub fn put_if_absent<T, K>(
+        &self,
+        writer: &mut Writer<T>,
+        k: K,
+        v: &Value,
+    ) -> Result<bool, StoreError>
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.check_key(&k)?;
+        let mut flags: T::Flags = self.write_flags();
+        flags.set(WriteFlags::NO_OVERWRITE, true);
+        match writer.put(&self.db, &k, v, flags) {
+            Ok(()) => {
+                self.log_change(writer, ChangeOp::Put, k.as_ref())?;
+                Ok(true)
+            }
+            Err(StoreError::KeyValuePairExists) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Replace the value stored for a key with `new`, but only if the current value
+    /// equals `expected`, where an `expected` of `None` means the key must be absent.
+    /// Returns `true` if the value was replaced.
+    pub fn compare_and_swap<T, K>(
+        &self,
+        writer: &mut Writer<T>,
+        k: K,
+        expected: Option<&Value>,
+        new: &Value,
+    ) -> Result<bool, StoreError>
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        let expected = match expected {
+            None => return self.put_if_absent(writer, k, new),
+            Some(expected) => expected,
+        };
+        self.check_key(&k)?;
+        if writer.read_value(&self.db, &k)?.as_ref() != Some(expected) {
+            return Ok(false);
+        }
+        // Replacing the expected value is the point, so the store's default flags
+        // don't apply.
+        writer.put(&self.db, &k, new, T::Flags::empty())?;
+        self.log_change(writer, ChangeOp::Put, k.as_ref())?;
+        Ok(true)
+    }
+
+    /// Add `delta` to the integer stored for a key, treating a missing key as 0, and
+    /// return the new value. The result is always stored as a `Value::I64`, and an
+    /// existing value must be a `Value::I64` or `Value::U64`.
+    pub fn increment<T, K>(
+        &self,
+        writer: &mut Writer<T>,
+        k: K,
+        delta: i64,
+    ) -> Result<i64, StoreError>
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        self.check_key(&k)?;
+        let current = match writer.read_value(&self.db, &k)? {
+            None => 0,
+            Some(Value::I64(v)) => v,
+            Some(Value::U64(v)) => i64::try_from(v).map_err(|_| DataError::IntegerOverflow)?,
+            Some(v) => {
+                return Err(DataError::UnexpectedType {
+                    expected: Type::I64,
+                    actual: v.value_type(),
+                }
+                .into())
+            }
+        };
+        let new = current
+            .checked_add(delta)
+            .ok_or(DataError::IntegerOverflow)?;
+        // Replacing the counter is the point, so the store's default flags don't apply.
+        writer.put(&self.db, &k, &Value::I64(new), T::Flags::empty())?;
+        self.log_change(writer, ChangeOp::Put, k.as_ref())?;
+        Ok(new)
     }
 
     #[cfg(not(feature = "db-dup-sort"))]
@@ -61,7 +500,9 @@ where
         T: BackendRwTransaction<Database = D>,
         K: AsRef<[u8]>,
     {
-        writer.delete(&self.db, &k)
+        self.check_key(&k)?;
+        writer.delete(&self.db, &k)?;
+        self.log_change(writer, ChangeOp::Delete, k.as_ref())
     }
 
     #[cfg(feature = "db-dup-sort")]
@@ -70,7 +511,73 @@ where
         T: BackendRwTransaction<Database = D>,
         K: AsRef<[u8]>,
     {
-        writer.delete(&self.db, &k, None)
+        self.check_key(&k)?;
+        writer.delete(&self.db, &k, None)?;
+        self.log_change(writer, ChangeOp::Delete, k.as_ref())
+    }
+
+    /// Like `delete`, but returns `Ok(false)` instead of failing with
+    /// `StoreError::KeyValuePairNotFound` if the key isn't present.
+    pub fn delete_if_exists<T, K>(&self, writer: &mut Writer<T>, k: K) -> Result<bool, StoreError>
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+    {
+        match self.delete(writer, k) {
+            Ok(()) => Ok(true),
+            Err(StoreError::KeyValuePairNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Delete every key within `range`, returning the number of deleted keys.
+    pub fn delete_range<T, K, B>(
+        &self,
+        writer: &mut Writer<T>,
+        range: B,
+    ) -> Result<usize, StoreError>
+    where
+        T: BackendRwTransaction<Database = D>,
+        K: AsRef<[u8]>,
+        B: RangeBounds<K>,
+    {
+        let from = as_bytes_bound(range.start_bound());
+        let to = as_bytes_bound(range.end_bound());
+        let keys = writer.delete_range(&self.db, from, to, |_, _| true)?;
+        for k in &keys {
+            self.log_change(writer, ChangeOp::Delete, k)?;
+        }
+        Ok(keys.len())
+    }
+
+    /// Delete every pair for which `f` returns false, e.g. expired sessions, returning
+    /// the number of deleted pairs. Every value is decoded and passed to `f` before any
+    /// pair is deleted, so if one can't be decoded, this fails with its error without
+    /// having deleted anything.
+    pub fn retain<T, F>(&self, writer: &mut Writer<T>, mut f: F) -> Result<usize, StoreError>
+    where
+        T: BackendRwTransaction<Database = D>,
+        F: FnMut(&[u8], &Value) -> bool,
+    {
+        let mut keys = vec![];
+        let mut error = None;
+        writer.scan_range(&self.db, Bound::Unbounded, Bound::Unbounded, |k, bytes| {
+            if error.is_some() {
+                return;
+            }
+            match Value::from_tagged_slice(bytes) {
+                Ok(v) if !f(k, &v) => keys.push(k.to_vec()),
+                Ok(_) => {}
+                Err(err) => error = Some(err),
+            }
+        })?;
+        if let Some(err) = error {
+            return Err(err.into());
+        }
+        for k in &keys {
+            self.delete(writer, k)?;
+        }
+        Ok(keys.len())
     }
 
     pub fn iter_start<'r, R, I, C>(&self, reader: &'r R) -> Result<Iter<'r, I>, StoreError>
@@ -84,6 +591,82 @@ where
 
         Ok(Iter {
             iter,
+            utf8_keys: self.utf8_keys,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Like `iter_start`, but yields keys as string slices, failing with
+    /// `StoreError::NonUtf8Key` for any key that isn't valid UTF-8.
+    pub fn iter_start_str<'r, R, I, C>(&self, reader: &'r R) -> Result<StrIter<'r, I>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+    {
+        let iter = self.iter_start(reader)?;
+        Ok(StrIter { iter })
+    }
+
+    /// Like `iter_start`, but yields copies of the keys and values, so that they can be
+    /// kept after the reader ends, e.g. to process a batch without holding a read
+    /// transaction open.
+    pub fn iter_start_owned<'r, R, I, C>(
+        &self,
+        reader: &'r R,
+    ) -> Result<OwnedIter<'r, I>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+    {
+        let iter = self.iter_start(reader)?;
+        Ok(OwnedIter { iter })
+    }
+
+    /// Like `iter_start`, but yields each key along with the result of decoding its
+    /// value, e.g. to skip corrupt values and keep going in a recovery tool. Only
+    /// failures to read the entries themselves are yielded as errors. The keys aren't
+    /// checked, even for stores opened with `utf8_keys`.
+    pub fn iter_start_lossy<'r, R, I, C>(
+        &self,
+        reader: &'r R,
+    ) -> Result<LossyIter<'r, I>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+    {
+        let cursor = reader.open_ro_cursor(&self.db)?;
+        Ok(LossyIter {
+            iter: cursor.into_iter(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Like `iter_start`, but yields only the values, e.g. to aggregate them. The keys
+    /// aren't checked, even for stores opened with `utf8_keys`.
+    pub fn iter_values<'r, R, I, C>(&self, reader: &'r R) -> Result<ValuesIter<'r, I>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+    {
+        let cursor = reader.open_ro_cursor(&self.db)?;
+        Ok(ValuesIter {
+            iter: cursor.into_iter(),
+            phantom: PhantomData,
+        })
+    }
+
+    pub fn cursor<'r, R, C>(&self, reader: &'r R) -> Result<Cursor<'r, C>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        C: BackendRoCursor<'r>,
+    {
+        Ok(Cursor {
+            cursor: reader.open_ro_cursor(&self.db)?,
+            utf8_keys: self.utf8_keys,
             phantom: PhantomData,
         })
     }
@@ -95,11 +678,103 @@ where
         C: BackendRoCursor<'r, Iter = I>,
         K: AsRef<[u8]> + 'r,
     {
+        self.check_key(&k)?;
         let cursor = reader.open_ro_cursor(&self.db)?;
         let iter = cursor.into_iter_from(k);
 
         Ok(Iter {
             iter,
+            utf8_keys: self.utf8_keys,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Like `iter_from`, but starts at the first key strictly greater than `k`, e.g. to
+    /// resume paginating after the last key of the previous page.
+    pub fn iter_after<'r, R, I, C, K>(&self, reader: &'r R, k: K) -> Result<Iter<'r, I>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+        K: AsRef<[u8]> + 'r,
+    {
+        self.check_key(&k)?;
+        let cursor = reader.open_ro_cursor(&self.db)?;
+        let iter = cursor.into_iter_after(k);
+
+        Ok(Iter {
+            iter,
+            utf8_keys: self.utf8_keys,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Read up to `limit` pairs, starting after the key `after`, or at the first key
+    /// without one, e.g. to serve a paginated API. Along with them, return the key to
+    /// pass as `after` for the next page, which is the last key read, or `None` if
+    /// there are no more pairs. The pairs are owned, so the reader can be dropped
+    /// between pages. A `limit` of `0` reads no pairs, and returns no next key.
+    #[allow(clippy::type_complexity)]
+    pub fn page<'r, R, I, C>(
+        &self,
+        reader: &'r R,
+        after: Option<&[u8]>,
+        limit: usize,
+    ) -> Result<(Vec<(Vec<u8>, OwnedValue)>, Option<Vec<u8>>), StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+    {
+        // `iter_after` needs a key that lives as long as the reader.
+        #[allow(clippy::unnecessary_to_owned)]
+        let mut iter = match after {
+            Some(after) => self.iter_after(reader, after.to_vec())?,
+            None => self.iter_start(reader)?,
+        };
+        let mut pairs = vec![];
+        for pair in iter.by_ref().take(limit) {
+            let (key, value) = pair?;
+            pairs.push((key.to_vec(), OwnedValue::from(&value)));
+        }
+        let next = match iter.next() {
+            Some(_) => pairs.last().map(|(key, _)| key.clone()),
+            None => None,
+        };
+        Ok((pairs, next))
+    }
+
+    /// Iterate over the keys that start with `prefix`, in order, e.g. the keys that
+    /// share the leading components of a `CompositeKey`. The iteration ends at the first
+    /// key past the prefix, rather than walking the rest of the store, so unlike a range
+    /// it doesn't need an upper bound, which prefixes of `0xff` bytes don't have. An
+    /// empty prefix iterates over the whole store.
+    pub fn iter_prefix<'r, R, I, C, K>(
+        &self,
+        reader: &'r R,
+        prefix: K,
+    ) -> Result<PrefixIter<'r, I>, StoreError>
+    where
+        R: Readable<'r, Database = D, RoCursor = C>,
+        I: BackendIter<'r>,
+        C: BackendRoCursor<'r, Iter = I>,
+        K: AsRef<[u8]>,
+    {
+        let prefix = prefix.as_ref().to_vec();
+        let cursor = reader.open_ro_cursor(&self.db)?;
+        // LMDB can't seek to an empty key, so an empty prefix, which every key starts
+        // with, starts from the first key instead.
+        let iter = if prefix.is_empty() {
+            cursor.into_iter()
+        } else {
+            cursor.into_iter_from(prefix.clone())
+        };
+
+        Ok(PrefixIter {
+            iter,
+            prefix,
+            utf8_keys: self.utf8_keys,
+            done: false,
             phantom: PhantomData,
         })
     }
@@ -109,7 +784,8 @@ where
         D: BackendDatabase,
         T: BackendRwTransaction<Database = D>,
     {
-        writer.clear(&self.db)
+        writer.clear(&self.db)?;
+        self.log_change(writer, ChangeOp::Clear, &[])
     }
 }
 
@@ -122,6 +798,9 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         match self.iter.next() {
             None => None,
+            Some(Ok((key, _))) if self.utf8_keys && str::from_utf8(key).is_err() => {
+                Some(Err(StoreError::NonUtf8Key))
+            }
             Some(Ok((key, bytes))) => match read_transform(Ok(bytes)) {
                 Ok(val) => Some(Ok((key, val))),
                 Err(err) => Some(Err(err)),
@@ -130,3 +809,156 @@ where
         }
     }
 }
+
+impl<'i, I> Iterator for PrefixIter<'i, I>
+where
+    I: BackendIter<'i>,
+{
+    type Item = Result<(&'i [u8], Value<'i>), StoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.iter.next()? {
+            Ok((key, _)) if !key.starts_with(&self.prefix) => {
+                self.done = true;
+                None
+            }
+            Ok((key, _)) if self.utf8_keys && str::from_utf8(key).is_err() => {
+                Some(Err(StoreError::NonUtf8Key))
+            }
+            Ok((key, bytes)) => Some(read_transform(Ok(bytes)).map(|val| (key, val))),
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+impl<'i, I> Iterator for LossyIter<'i, I>
+where
+    I: BackendIter<'i>,
+{
+    #[allow(clippy::type_complexity)]
+    type Item = Result<(&'i [u8], Result<Value<'i>, DataError>), StoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.next()? {
+            Ok((key, bytes)) => Some(Ok((key, Value::from_tagged_slice(bytes)))),
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+impl<'i, I> Iterator for ValuesIter<'i, I>
+where
+    I: BackendIter<'i>,
+{
+    type Item = Result<Value<'i>, StoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.next()? {
+            Ok((_, bytes)) => Some(read_transform(Ok(bytes))),
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+fn as_bytes_bound<K>(bound: Bound<&K>) -> Bound<&[u8]>
+where
+    K: AsRef<[u8]>,
+{
+    match bound {
+        Bound::Included(k) => Bound::Included(k.as_ref()),
+        Bound::Excluded(k) => Bound::Excluded(k.as_ref()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+impl<'c, C> Cursor<'c, C>
+where
+    C: BackendRoCursor<'c>,
+{
+    /// Move to the entry whose key is `k`.
+    pub fn seek_exact<K>(&mut self, k: K) -> Option<Result<(&'c [u8], Value<'c>), StoreError>>
+    where
+        K: AsRef<[u8]>,
+    {
+        if self.utf8_keys && str::from_utf8(k.as_ref()).is_err() {
+            return Some(Err(StoreError::NonUtf8Key));
+        }
+        self.seek(CursorOp::SeekExact(k.as_ref()))
+    }
+
+    /// Move to the first entry whose key is greater than or equal to `k`.
+    pub fn seek_range<K>(&mut self, k: K) -> Option<Result<(&'c [u8], Value<'c>), StoreError>>
+    where
+        K: AsRef<[u8]>,
+    {
+        if self.utf8_keys && str::from_utf8(k.as_ref()).is_err() {
+            return Some(Err(StoreError::NonUtf8Key));
+        }
+        self.seek(CursorOp::SeekRange(k.as_ref()))
+    }
+
+    // Not an `Iterator`, whose by-value `last` would shadow ours.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Option<Result<(&'c [u8], Value<'c>), StoreError>> {
+        self.seek(CursorOp::Next)
+    }
+
+    pub fn prev(&mut self) -> Option<Result<(&'c [u8], Value<'c>), StoreError>> {
+        self.seek(CursorOp::Prev)
+    }
+
+    pub fn first(&mut self) -> Option<Result<(&'c [u8], Value<'c>), StoreError>> {
+        self.seek(CursorOp::First)
+    }
+
+    pub fn last(&mut self) -> Option<Result<(&'c [u8], Value<'c>), StoreError>> {
+        self.seek(CursorOp::Last)
+    }
+
+    fn seek(&mut self, op: CursorOp) -> Option<Result<(&'c [u8], Value<'c>), StoreError>> {
+        match self.cursor.seek(op) {
+            None => None,
+            Some(Ok((key, _))) if self.utf8_keys && str::from_utf8(key).is_err() => {
+                Some(Err(StoreError::NonUtf8Key))
+            }
+            Some(Ok((key, bytes))) => match read_transform(Ok(bytes)) {
+                Ok(val) => Some(Ok((key, val))),
+                Err(err) => Some(Err(err)),
+            },
+            Some(Err(err)) => Some(Err(err.into())),
+        }
+    }
+}
+
+impl<'i, I> Iterator for StrIter<'i, I>
+where
+    I: BackendIter<'i>,
+{
+    type Item = Result<(&'i str, Value<'i>), StoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.next() {
+            None => None,
+            Some(Ok((key, val))) => match str::from_utf8(key) {
+                Ok(key) => Some(Ok((key, val))),
+                Err(_) => Some(Err(StoreError::NonUtf8Key)),
+            },
+            Some(Err(err)) => Some(Err(err)),
+        }
+    }
+}
+
+impl<'i, I> Iterator for OwnedIter<'i, I>
+where
+    I: BackendIter<'i>,
+{
+    type Item = Result<(Vec<u8>, OwnedValue), StoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pair = self.iter.next()?;
+        Some(pair.map(|(key, val)| (key.to_vec(), OwnedValue::from(&val))))
+    }
+}
@@ -8,11 +8,19 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
-use std::fmt;
+use std::{
+    cmp::Ordering,
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    io,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use arrayref::array_ref;
-use bincode::{deserialize, serialize, serialized_size};
+use bincode::{serialize, serialize_into, serialized_size, Options};
 use ordered_float::OrderedFloat;
+use serde::{de::DeserializeOwned, Deserialize};
 use uuid::{Bytes, Uuid};
 
 use crate::error::DataError;
@@ -78,33 +86,51 @@ impl fmt::Display for Type {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+/// Values are ordered by their type first, in the order of the `Type` tags (`Bool` <
+/// `U64` < `I64` < `F64` < `Instant` < `Uuid` < `Str` < `Json` < `Blob`), then by their
+/// contents. `F64`s are ordered totally, with `NaN` greater than every other float.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Value<'v> {
     Bool(bool),
     U64(u64),
     I64(i64),
     F64(OrderedFloat<f64>),
-    Instant(i64), // Millisecond-precision timestamp.
+    Instant(i64), // Milliseconds since the Unix epoch.
     Uuid(&'v Bytes),
     Str(&'v str),
     Json(&'v str),
     Blob(&'v [u8]),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+/// Compared, ordered and hashed like the equivalent `Value`, so unlike plain `f64`s,
+/// `F64(NaN)` equals itself.
+#[derive(Clone, Debug)]
 pub enum OwnedValue {
     Bool(bool),
     U64(u64),
     I64(i64),
     F64(f64),
-    Instant(i64), // Millisecond-precision timestamp.
+    Instant(i64), // Milliseconds since the Unix epoch.
     Uuid(Uuid),
     Str(String),
     Json(String), // TODO
     Blob(Vec<u8>),
 }
 
-fn uuid(bytes: &[u8]) -> Result<Value, DataError> {
+/// Like bincode's `deserialize`, with the same encoding, but never reading past the
+/// end of `data`, so a corrupt length prefix fails rather than allocating for it.
+fn deserialize<'d, T>(data: &'d [u8]) -> bincode::Result<T>
+where
+    T: Deserialize<'d>,
+{
+    bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(data.len() as u64)
+        .deserialize(data)
+}
+
+fn uuid(bytes: &[u8]) -> Result<Value<'_>, DataError> {
     if bytes.len() == 16 {
         Ok(Value::Uuid(array_ref![bytes, 0, 16]))
     } else {
@@ -119,6 +145,124 @@ impl<'v> Value<'v> {
         Value::from_type_and_data(t, data)
     }
 
+    /// An `Instant` that is `millis` milliseconds after the Unix epoch, or before it
+    /// if negative.
+    pub fn instant_from_millis(millis: i64) -> Value<'static> {
+        Value::Instant(millis)
+    }
+
+    /// The time of an `Instant`, or `None` for other values and for instants that
+    /// can't be represented as a `SystemTime` on this platform.
+    pub fn as_instant(&self) -> Option<SystemTime> {
+        match self {
+            Value::Instant(millis) if *millis >= 0 => {
+                UNIX_EPOCH.checked_add(Duration::from_millis(millis.unsigned_abs()))
+            }
+            Value::Instant(millis) => {
+                UNIX_EPOCH.checked_sub(Duration::from_millis(millis.unsigned_abs()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn value_type(&self) -> Type {
+        match self {
+            Value::Bool(_) => Type::Bool,
+            Value::U64(_) => Type::U64,
+            Value::I64(_) => Type::I64,
+            Value::F64(_) => Type::F64,
+            Value::Instant(_) => Type::Instant,
+            Value::Uuid(_) => Type::Uuid,
+            Value::Str(_) => Type::Str,
+            Value::Json(_) => Type::Json,
+            Value::Blob(_) => Type::Blob,
+        }
+    }
+
+    /// Fail with `DataError::UnexpectedType` unless this is a value of type `expected`.
+    pub fn expect_type(&self, expected: Type) -> Result<(), DataError> {
+        let actual = self.value_type();
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(DataError::UnexpectedType { expected, actual })
+        }
+    }
+
+    fn unexpected(&self, expected: Type) -> DataError {
+        DataError::UnexpectedType {
+            expected,
+            actual: self.value_type(),
+        }
+    }
+
+    /// Like the other `try_as_*` methods, return the contents of a value of the named
+    /// type, or fail with `DataError::UnexpectedType`, so a lookup can be checked with `?`.
+    pub fn try_as_bool(&self) -> Result<bool, DataError> {
+        match self {
+            Value::Bool(v) => Ok(*v),
+            _ => Err(self.unexpected(Type::Bool)),
+        }
+    }
+
+    pub fn try_as_u64(&self) -> Result<u64, DataError> {
+        match self {
+            Value::U64(v) => Ok(*v),
+            _ => Err(self.unexpected(Type::U64)),
+        }
+    }
+
+    pub fn try_as_i64(&self) -> Result<i64, DataError> {
+        match self {
+            Value::I64(v) => Ok(*v),
+            _ => Err(self.unexpected(Type::I64)),
+        }
+    }
+
+    pub fn try_as_f64(&self) -> Result<f64, DataError> {
+        match self {
+            Value::F64(v) => Ok(v.into_inner()),
+            _ => Err(self.unexpected(Type::F64)),
+        }
+    }
+
+    pub fn try_as_uuid(&self) -> Result<&'v Bytes, DataError> {
+        match self {
+            Value::Uuid(v) => Ok(v),
+            _ => Err(self.unexpected(Type::Uuid)),
+        }
+    }
+
+    pub fn try_as_str(&self) -> Result<&'v str, DataError> {
+        match self {
+            Value::Str(v) => Ok(v),
+            _ => Err(self.unexpected(Type::Str)),
+        }
+    }
+
+    pub fn try_as_json(&self) -> Result<&'v str, DataError> {
+        match self {
+            Value::Json(v) => Ok(v),
+            _ => Err(self.unexpected(Type::Json)),
+        }
+    }
+
+    /// Parse a `Json` value into a `T`, failing with `DataError::InvalidJson` if it
+    /// doesn't hold one.
+    pub fn as_json<T>(&self) -> Result<T, DataError>
+    where
+        T: DeserializeOwned,
+    {
+        serde_json::from_str(self.try_as_json()?).map_err(DataError::InvalidJson)
+    }
+
+    pub fn try_as_blob(&self) -> Result<&'v [u8], DataError> {
+        match self {
+            Value::Blob(v) => Ok(v),
+            _ => Err(self.unexpected(Type::Blob)),
+        }
+    }
+
     fn from_type_and_data(t: Type, data: &'v [u8]) -> Result<Value<'v>, DataError> {
         if t == Type::Uuid {
             return deserialize(data)
@@ -149,7 +293,18 @@ impl<'v> Value<'v> {
         })
     }
 
+    /// With the `json-validation` feature, fail with `DataError::InvalidJson` if this is
+    /// a `Json` value that isn't well-formed JSON, so it can't be written.
+    pub(crate) fn validate(&self) -> Result<(), DataError> {
+        #[cfg(feature = "json-validation")]
+        if let Value::Json(v) = self {
+            serde_json::from_str::<serde::de::IgnoredAny>(v).map_err(DataError::InvalidJson)?;
+        }
+        Ok(())
+    }
+
     pub fn to_bytes(&self) -> Result<Vec<u8>, DataError> {
+        self.validate()?;
         match self {
             Value::Bool(v) => serialize(&(Type::Bool.to_tag(), *v)),
             Value::U64(v) => serialize(&(Type::U64.to_tag(), *v)),
@@ -164,6 +319,25 @@ impl<'v> Value<'v> {
         .map_err(DataError::EncodingError)
     }
 
+    /// Like `to_bytes`, but writes the tagged bytes into an existing slice (e.g. one
+    /// reserved by the storage backend) instead of allocating a new buffer. The slice
+    /// must be at least `serialized_size` bytes long.
+    pub(crate) fn write_bytes(&self, buf: &mut [u8]) -> Result<(), DataError> {
+        let mut w = SliceWriter { buf, pos: 0 };
+        match self {
+            Value::Bool(v) => serialize_into(&mut w, &(Type::Bool.to_tag(), *v)),
+            Value::U64(v) => serialize_into(&mut w, &(Type::U64.to_tag(), *v)),
+            Value::I64(v) => serialize_into(&mut w, &(Type::I64.to_tag(), *v)),
+            Value::F64(v) => serialize_into(&mut w, &(Type::F64.to_tag(), v.0)),
+            Value::Instant(v) => serialize_into(&mut w, &(Type::Instant.to_tag(), *v)),
+            Value::Str(v) => serialize_into(&mut w, &(Type::Str.to_tag(), v)),
+            Value::Json(v) => serialize_into(&mut w, &(Type::Json.to_tag(), v)),
+            Value::Blob(v) => serialize_into(&mut w, &(Type::Blob.to_tag(), v)),
+            Value::Uuid(v) => serialize_into(&mut w, &(Type::Uuid.to_tag(), v)),
+        }
+        .map_err(DataError::EncodingError)
+    }
+
     pub fn serialized_size(&self) -> Result<u64, DataError> {
         match self {
             Value::Bool(v) => serialized_size(&(Type::Bool.to_tag(), *v)),
@@ -180,6 +354,102 @@ impl<'v> Value<'v> {
     }
 }
 
+/// A minimal `io::Write` over a fixed-size slice. bincode writes values one field
+/// (and, for blobs, one byte) at a time, and the standard library's `impl Write for
+/// &mut [u8]` is several times slower than this for such small writes.
+struct SliceWriter<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+}
+
+impl<'b> io::Write for SliceWriter<'b> {
+    #[inline]
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.write_all(data)?;
+        Ok(data.len())
+    }
+
+    #[inline]
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        let end = self.pos + data.len();
+        match self.buf.get_mut(self.pos..end) {
+            Some(dest) => {
+                dest.copy_from_slice(data);
+                self.pos = end;
+                Ok(())
+            }
+            None => Err(io::ErrorKind::WriteZero.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// How many bytes of a `Blob` are shown by `Display`.
+const BLOB_PREVIEW_LEN: usize = 16;
+
+/// A human-readable rendering, e.g. for log lines. Strings are quoted and escaped, JSON
+/// is shown as is, instants as UTC timestamps like `2018-06-06T20:47:53.700Z`, and
+/// blobs as their length and a hex preview of their first bytes.
+impl<'v> fmt::Display for Value<'v> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Bool(v) => write!(f, "{}", v),
+            Value::U64(v) => write!(f, "{}", v),
+            Value::I64(v) => write!(f, "{}", v),
+            Value::F64(v) => write!(f, "{}", v),
+            Value::Instant(v) => write_timestamp(f, *v),
+            Value::Uuid(v) => write!(f, "{}", Uuid::from_bytes(**v).hyphenated()),
+            Value::Str(v) => write!(f, "{:?}", v),
+            Value::Json(v) => f.write_str(v),
+            Value::Blob(v) => {
+                write!(f, "blob[{}]", v.len())?;
+                if !v.is_empty() {
+                    f.write_str(" ")?;
+                }
+                for b in v.iter().take(BLOB_PREVIEW_LEN) {
+                    write!(f, "{:02x}", b)?;
+                }
+                if v.len() > BLOB_PREVIEW_LEN {
+                    f.write_str("...")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Write milliseconds since the Unix epoch as an ISO 8601 UTC timestamp.
+fn write_timestamp(f: &mut fmt::Formatter, millis: i64) -> fmt::Result {
+    let days = millis.div_euclid(86_400_000);
+    let millis_of_day = millis.rem_euclid(86_400_000);
+    // Convert days since the epoch to a proleptic Gregorian date, from Howard Hinnant's
+    // `civil_from_days` algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    write!(
+        f,
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        millis_of_day / 3_600_000,
+        millis_of_day / 60_000 % 60,
+        millis_of_day / 1000 % 60,
+        millis_of_day % 1000
+    )
+}
+
 impl<'v> From<&'v Value<'v>> for OwnedValue {
     fn from(value: &Value) -> OwnedValue {
         match value {
@@ -197,7 +467,7 @@ impl<'v> From<&'v Value<'v>> for OwnedValue {
 }
 
 impl<'v> From<&'v OwnedValue> for Value<'v> {
-    fn from(value: &OwnedValue) -> Value {
+    fn from(value: &OwnedValue) -> Value<'_> {
         match value {
             OwnedValue::Bool(v) => Value::Bool(*v),
             OwnedValue::U64(v) => Value::U64(*v),
@@ -212,6 +482,86 @@ impl<'v> From<&'v OwnedValue> for Value<'v> {
     }
 }
 
+impl PartialEq for OwnedValue {
+    fn eq(&self, other: &OwnedValue) -> bool {
+        Value::from(self) == Value::from(other)
+    }
+}
+
+impl Eq for OwnedValue {}
+
+impl PartialOrd for OwnedValue {
+    fn partial_cmp(&self, other: &OwnedValue) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OwnedValue {
+    fn cmp(&self, other: &OwnedValue) -> Ordering {
+        Value::from(self).cmp(&Value::from(other))
+    }
+}
+
+impl Hash for OwnedValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Value::from(self).hash(state)
+    }
+}
+
+impl<'v> From<bool> for Value<'v> {
+    fn from(v: bool) -> Value<'v> {
+        Value::Bool(v)
+    }
+}
+
+impl<'v> From<u64> for Value<'v> {
+    fn from(v: u64) -> Value<'v> {
+        Value::U64(v)
+    }
+}
+
+impl<'v> From<i64> for Value<'v> {
+    fn from(v: i64) -> Value<'v> {
+        Value::I64(v)
+    }
+}
+
+impl<'v> From<f64> for Value<'v> {
+    fn from(v: f64) -> Value<'v> {
+        Value::F64(OrderedFloat(v))
+    }
+}
+
+/// Converts to an `Instant`, truncating to millisecond precision. Times too far from
+/// the Unix epoch for an `i64` of milliseconds saturate.
+impl<'v> From<SystemTime> for Value<'v> {
+    fn from(v: SystemTime) -> Value<'v> {
+        Value::Instant(millis_since_epoch(v))
+    }
+}
+
+/// Milliseconds since the Unix epoch, saturating like `Value::from(SystemTime)`.
+pub(crate) fn millis_since_epoch(t: SystemTime) -> i64 {
+    match t.duration_since(UNIX_EPOCH) {
+        Ok(after) => i64::try_from(after.as_millis()).unwrap_or(i64::MAX),
+        Err(before) => i64::try_from(before.duration().as_millis())
+            .map(|millis| -millis)
+            .unwrap_or(i64::MIN),
+    }
+}
+
+impl<'v> From<&'v str> for Value<'v> {
+    fn from(v: &'v str) -> Value<'v> {
+        Value::Str(v)
+    }
+}
+
+impl<'v> From<&'v [u8]> for Value<'v> {
+    fn from(v: &'v [u8]) -> Value<'v> {
+        Value::Blob(v)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -249,4 +599,237 @@ mod tests {
             17
         );
     }
+    #[test]
+    fn test_value_corrupt_length() {
+        // A string claiming to be 2^64 - 1 bytes long fails rather than allocating.
+        let mut bytes = vec![Type::Str.to_tag()];
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        bytes.extend_from_slice(b"foo");
+        assert!(matches!(
+            Value::from_tagged_slice(&bytes),
+            Err(DataError::DecodingError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_value_write_bytes() {
+        let values = [
+            Value::I64(-1000),
+            Value::Bool(true),
+            Value::F64(OrderedFloat(10000.1)),
+            Value::Str("¡Hola"),
+            Value::Blob(b"hello!"),
+        ];
+        for v in &values {
+            let mut buf = vec![0; v.serialized_size().unwrap() as usize];
+            v.write_bytes(&mut buf).unwrap();
+            assert_eq!(buf, v.to_bytes().unwrap());
+        }
+
+        let mut short = [0; 4];
+        assert!(Value::Str("hello!").write_bytes(&mut short).is_err());
+    }
+
+    #[test]
+    fn test_value_instant() {
+        let now = SystemTime::now();
+        let value = Value::from(now);
+        let bytes = value.to_bytes().unwrap();
+        let decoded = Value::from_tagged_slice(&bytes).unwrap();
+        let instant = decoded.as_instant().unwrap();
+        // Instants are truncated to millisecond precision.
+        assert!(instant <= now);
+        assert!(now.duration_since(instant).unwrap() < Duration::from_millis(1));
+
+        let before_epoch = UNIX_EPOCH - Duration::from_millis(1500);
+        assert_eq!(Value::from(before_epoch), Value::Instant(-1500));
+        assert_eq!(Value::Instant(-1500).as_instant(), Some(before_epoch));
+
+        assert_eq!(
+            Value::instant_from_millis(1_528_318_073_700).as_instant(),
+            Some(UNIX_EPOCH + Duration::from_millis(1_528_318_073_700))
+        );
+        assert_eq!(Value::I64(1_528_318_073_700).as_instant(), None);
+    }
+
+    #[test]
+    fn test_value_as_json() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Config {
+            name: String,
+            retries: u32,
+        }
+
+        let value = Value::Json(r#"{"name": "sync", "retries": 3}"#);
+        assert_eq!(
+            value.as_json::<Config>().unwrap(),
+            Config {
+                name: "sync".to_string(),
+                retries: 3,
+            }
+        );
+        assert!(matches!(
+            Value::Json(r#"{"name": "sync"}"#).as_json::<Config>(),
+            Err(DataError::InvalidJson(_))
+        ));
+        assert!(matches!(
+            Value::Str("{}").as_json::<Config>(),
+            Err(DataError::UnexpectedType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_value_from_primitives() {
+        let conversions = [
+            (Value::from(true), Value::Bool(true)),
+            (Value::from(1000u64), Value::U64(1000)),
+            (Value::from(-1000i64), Value::I64(-1000)),
+            (Value::from(10000.1f64), Value::F64(OrderedFloat(10000.1))),
+            (Value::from("¡Hola"), Value::Str("¡Hola")),
+            (Value::from(&b"hello!"[..]), Value::Blob(b"hello!")),
+        ];
+        for (converted, explicit) in &conversions {
+            assert_eq!(converted, explicit);
+            assert_eq!(converted.to_bytes().unwrap(), explicit.to_bytes().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_value_ord() {
+        let uuid = [1; 16];
+        let values = [
+            Value::Bool(false),
+            Value::Bool(true),
+            Value::U64(0),
+            Value::U64(u64::MAX),
+            Value::I64(i64::MIN),
+            Value::I64(-1),
+            Value::I64(i64::MAX),
+            Value::F64(OrderedFloat(f64::NEG_INFINITY)),
+            Value::F64(OrderedFloat(-1.5)),
+            Value::F64(OrderedFloat(2.5)),
+            Value::F64(OrderedFloat(f64::INFINITY)),
+            Value::F64(OrderedFloat(f64::NAN)),
+            Value::Instant(-1),
+            Value::Instant(1_528_318_073_700),
+            Value::Uuid(&[0; 16]),
+            Value::Uuid(&uuid),
+            Value::Str(""),
+            Value::Str("a"),
+            Value::Str("b"),
+            Value::Json("{}"),
+            Value::Blob(b""),
+            Value::Blob(b"\x00"),
+        ];
+        for (i, a) in values.iter().enumerate() {
+            for (j, b) in values.iter().enumerate() {
+                assert_eq!(a.cmp(b), i.cmp(&j), "{:?} vs {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_owned_value_ord_hash() {
+        use std::collections::{BTreeSet, HashSet};
+
+        let values = vec![
+            OwnedValue::Str("b".to_string()),
+            OwnedValue::F64(f64::NAN),
+            OwnedValue::I64(2),
+            OwnedValue::Str("b".to_string()),
+            OwnedValue::F64(f64::NAN),
+            OwnedValue::U64(2),
+            OwnedValue::I64(-2),
+        ];
+        let sorted: Vec<_> = values
+            .iter()
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        assert_eq!(
+            sorted,
+            vec![
+                OwnedValue::U64(2),
+                OwnedValue::I64(-2),
+                OwnedValue::I64(2),
+                OwnedValue::F64(f64::NAN),
+                OwnedValue::Str("b".to_string()),
+            ]
+        );
+        assert_eq!(values.into_iter().collect::<HashSet<_>>().len(), 5);
+    }
+
+    #[test]
+    fn test_value_display() {
+        let uuid = *Uuid::parse_str("936da01f-9abd-4d9d-80c7-02af85c822a8")
+            .unwrap()
+            .as_bytes();
+        let formatted = [
+            (Value::Bool(true), "true"),
+            (Value::U64(1234), "1234"),
+            (Value::I64(-1234), "-1234"),
+            (Value::F64(OrderedFloat(1.5)), "1.5"),
+            (Value::F64(OrderedFloat(f64::NAN)), "NaN"),
+            (
+                Value::Instant(1_528_318_073_700),
+                "2018-06-06T20:47:53.700Z",
+            ),
+            (Value::Instant(0), "1970-01-01T00:00:00.000Z"),
+            (Value::Instant(-1), "1969-12-31T23:59:59.999Z"),
+            (Value::Instant(951_782_400_000), "2000-02-29T00:00:00.000Z"),
+            (Value::Uuid(&uuid), "936da01f-9abd-4d9d-80c7-02af85c822a8"),
+            (Value::Str("héllo, \"yöu\"\n"), r#""héllo, \"yöu\"\n""#),
+            (Value::Json(r#"{"a": [1, 2]}"#), r#"{"a": [1, 2]}"#),
+            (Value::Blob(b""), "blob[0]"),
+            (Value::Blob(b"hello"), "blob[5] 68656c6c6f"),
+            (
+                Value::Blob(&[0xab; 20]),
+                "blob[20] abababababababababababababababab...",
+            ),
+        ];
+        for (value, expected) in &formatted {
+            assert_eq!(value.to_string(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_value_try_as() {
+        assert!(Value::Bool(true).try_as_bool().unwrap());
+        assert_eq!(Value::U64(1234).try_as_u64().unwrap(), 1234);
+        assert_eq!(Value::I64(-1234).try_as_i64().unwrap(), -1234);
+        assert_eq!(Value::F64(OrderedFloat(1.5)).try_as_f64().unwrap(), 1.5);
+        assert_eq!(Value::Str("foo").try_as_str().unwrap(), "foo");
+        assert_eq!(Value::Json("{}").try_as_json().unwrap(), "{}");
+        assert_eq!(Value::Blob(b"foo").try_as_blob().unwrap(), b"foo");
+        assert!(Value::Instant(0).expect_type(Type::Instant).is_ok());
+
+        // The error names the expected and the actual type.
+        let err = Value::Str("1234").try_as_i64().unwrap_err();
+        assert!(matches!(
+            err,
+            DataError::UnexpectedType {
+                expected: Type::I64,
+                actual: Type::Str,
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "unexpected type tag: expected i64, got str"
+        );
+        assert!(matches!(
+            Value::I64(1).try_as_u64(),
+            Err(DataError::UnexpectedType {
+                expected: Type::U64,
+                actual: Type::I64,
+            })
+        ));
+        assert!(matches!(
+            Value::Json("{}").expect_type(Type::Str),
+            Err(DataError::UnexpectedType {
+                expected: Type::Str,
+                actual: Type::Json,
+            })
+        ));
+    }
 }
@@ -0,0 +1,127 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+#![cfg(feature = "compression")]
+
+use std::fs;
+
+use tempfile::Builder;
+
+use rkv::{
+    backend::SafeMode, Compression, DataError, OwnedValue, Rkv, StoreError, StoreOptions, Value,
+};
+
+#[test]
+fn test_compressed_store() {
+    let root = Builder::new()
+        .prefix("test_compressed_store")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    let compression = Compression {
+        level: 19,
+        threshold: 64,
+    };
+    let s = k
+        .open_compressed("s", StoreOptions::create(), compression)
+        .expect("opened");
+    let plain = k.open_single("s", StoreOptions::default()).expect("opened");
+
+    let blob = b"0123456789".repeat(1000);
+    let text = "héllo, yöu ".repeat(100);
+
+    let mut writer = k.write().expect("writer");
+    s.put(&mut writer, "blob", &Value::Blob(&blob))
+        .expect("wrote");
+    s.put(&mut writer, "text", &Value::Str(&text))
+        .expect("wrote");
+    s.put(&mut writer, "small", &Value::Blob(b"0123456789"))
+        .expect("wrote");
+    s.put(&mut writer, "int", &Value::I64(1234)).expect("wrote");
+    // Values written without compression can still be read.
+    plain
+        .put(&mut writer, "old", &Value::Blob(&blob))
+        .expect("wrote");
+    writer.commit().expect("committed");
+
+    let reader = k.read().expect("reader");
+    assert_eq!(
+        s.get(&reader, "blob").expect("read"),
+        Some(OwnedValue::Blob(blob.clone()))
+    );
+    assert_eq!(
+        s.get(&reader, "text").expect("read"),
+        Some(OwnedValue::Str(text))
+    );
+    assert_eq!(
+        s.get(&reader, "small").expect("read"),
+        Some(OwnedValue::Blob(b"0123456789".to_vec()))
+    );
+    assert_eq!(
+        s.get(&reader, "int").expect("read"),
+        Some(OwnedValue::I64(1234))
+    );
+    assert_eq!(
+        s.get(&reader, "old").expect("read"),
+        Some(OwnedValue::Blob(blob.clone()))
+    );
+    assert_eq!(s.get(&reader, "missing").expect("read"), None);
+
+    // Readers that don't know about compression can't read compressed values.
+    assert!(matches!(
+        plain.get(&reader, "blob"),
+        Err(StoreError::DataError(DataError::UnknownType(0x80)))
+    ));
+    assert_eq!(
+        plain.get(&reader, "small").expect("read"),
+        Some(Value::Blob(b"0123456789"))
+    );
+}
+
+#[test]
+fn test_compressed_store_shrinks_on_disk() {
+    let root = Builder::new()
+        .prefix("test_compressed_store_shrinks_on_disk")
+        .tempdir()
+        .expect("tempdir");
+    let blob = b"0123456789".repeat(10_000);
+
+    let mut sizes = vec![];
+    for name in &["plain", "compressed"] {
+        let path = root.path().join(name);
+        fs::create_dir_all(&path).expect("dir created");
+        let k = Rkv::new::<SafeMode>(&path).expect("new succeeded");
+        let threshold = if *name == "plain" { usize::MAX } else { 1024 };
+        let s = k
+            .open_compressed(
+                "s",
+                StoreOptions::create(),
+                Compression {
+                    threshold,
+                    ..Compression::default()
+                },
+            )
+            .expect("opened");
+        let mut writer = k.write().expect("writer");
+        s.put(&mut writer, "blob", &Value::Blob(&blob))
+            .expect("wrote");
+        writer.commit().expect("committed");
+
+        let reader = k.read().expect("reader");
+        assert_eq!(
+            s.get(&reader, "blob").expect("read"),
+            Some(OwnedValue::Blob(blob.clone()))
+        );
+        let file = path.join("data.safe.bin");
+        sizes.push(fs::metadata(file).expect("metadata").len());
+    }
+    assert!(sizes[1] * 10 < sizes[0], "{:?}", sizes);
+}
@@ -8,16 +8,45 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
-use std::fs;
+// TODO: change this back to `clippy::cognitive_complexity` when Clippy stable
+// deprecates `clippy::cyclomatic_complexity`.
+#![allow(clippy::complexity)]
+
+use std::{
+    fs,
+    ops::Bound,
+    str,
+    sync::{Arc, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
 
 use tempfile::Builder;
 
+#[cfg(feature = "lmdb")]
+use rkv::{backend::Lmdb, AnyRkv, Backend};
 use rkv::{
-    backend::{Lmdb, SafeMode},
-    Rkv, StoreOptions, Value,
+    backend::{
+        BackendEnvironment, BackendEnvironmentBuilder, BackendRwTransaction, Durability, SafeMode,
+    },
+    store::single::{Change, ChangeOp, Op},
+    CompositeKey, DataError, EnvironmentFlags, OwnedValue, Rkv, StoreError, StoreOptions, Value,
+    WriteFlags,
 };
 
+#[cfg(feature = "lmdb")]
+fn sorted_lines(dump: Vec<u8>) -> Vec<String> {
+    let mut lines: Vec<String> = String::from_utf8(dump)
+        .expect("utf-8")
+        .lines()
+        .map(Into::into)
+        .collect();
+    lines.sort();
+    lines
+}
+
 #[test]
+#[cfg(feature = "lmdb")]
 fn test_open_safe_same_dir_as_lmdb() {
     let root = Builder::new()
         .prefix("test_open_safe_same_dir_as_lmdb")
@@ -173,6 +202,7 @@ fn test_open_safe_same_dir_as_lmdb() {
 }
 
 #[test]
+#[cfg(feature = "lmdb")]
 fn test_open_lmdb_same_dir_as_safe() {
     let root = Builder::new()
         .prefix("test_open_lmdb_same_dir_as_safe")
@@ -326,3 +356,2766 @@ fn test_open_lmdb_same_dir_as_safe() {
         );
     }
 }
+
+#[test]
+#[cfg(feature = "lmdb")]
+fn test_import_json_lmdb_to_safe() {
+    let root = Builder::new()
+        .prefix("test_import_json_lmdb_to_safe")
+        .tempdir()
+        .expect("tempdir");
+    let lmdb_path = root.path().join("lmdb");
+    let safe_path = root.path().join("safe");
+    fs::create_dir_all(&lmdb_path).expect("dir created");
+    fs::create_dir_all(&safe_path).expect("dir created");
+
+    let src = Rkv::new::<Lmdb>(&lmdb_path).expect("new succeeded");
+    let sk = src
+        .open_single("sk", StoreOptions::create())
+        .expect("opened");
+    let other = src
+        .open_single("other", StoreOptions::create())
+        .expect("opened");
+
+    let mut writer = src.write().expect("writer");
+    sk.put(&mut writer, "bool", &Value::Bool(true))
+        .expect("wrote");
+    sk.put(&mut writer, "u64", &Value::U64(u64::MAX))
+        .expect("wrote");
+    sk.put(&mut writer, "i64", &Value::I64(-1234))
+        .expect("wrote");
+    sk.put(&mut writer, "f64", &Value::F64(1.5.into()))
+        .expect("wrote");
+    sk.put(&mut writer, "nan", &Value::F64(f64::NAN.into()))
+        .expect("wrote");
+    sk.put(&mut writer, "instant", &Value::Instant(1_500_000_000_000))
+        .expect("wrote");
+    sk.put(&mut writer, "str", &Value::Str("héllo, yöu"))
+        .expect("wrote");
+    sk.put(&mut writer, "json", &Value::Json(r#"{"foo":[1,2]}"#))
+        .expect("wrote");
+    other
+        .put(
+            &mut writer,
+            [0, 0xff, 0x80],
+            &Value::Blob(&[1, 2, 3, 0, 255]),
+        )
+        .expect("wrote");
+    writer.commit().expect("committed");
+    #[cfg(feature = "db-int-key")]
+    {
+        let int = src
+            .open_integer::<_, u32>("int", StoreOptions::create())
+            .expect("opened");
+        let mut writer = src.write().expect("writer");
+        int.put(&mut writer, 256, &Value::I64(256)).expect("wrote");
+        writer.commit().expect("committed");
+    }
+    let records = if cfg!(feature = "db-int-key") { 10 } else { 9 };
+
+    let mut dump = vec![];
+    src.export_json(&mut dump).expect("exported");
+
+    let dst = Rkv::new::<SafeMode>(&safe_path).expect("new succeeded");
+    assert_eq!(dst.import_json(&dump[..]).expect("imported"), records);
+
+    // Integer stores are created with their flag, which existing ones must have.
+    #[cfg(feature = "db-int-key")]
+    {
+        let int = dst
+            .open_integer::<_, u32>("int", StoreOptions::default())
+            .expect("opened");
+        let reader = dst.read().expect("reader");
+        assert_eq!(int.get(&reader, 256).expect("read"), Some(Value::I64(256)));
+    }
+    assert_eq!(dst.import_json(&dump[..]).expect("imported"), records);
+
+    let mut roundtrip = vec![];
+    dst.export_json(&mut roundtrip).expect("exported");
+    assert_eq!(sorted_lines(roundtrip), sorted_lines(dump));
+
+    let sk = dst
+        .open_single("sk", StoreOptions::default())
+        .expect("opened");
+    let other = dst
+        .open_single("other", StoreOptions::default())
+        .expect("opened");
+    let reader = dst.read().expect("reader");
+    assert_eq!(
+        sk.get(&reader, "u64").expect("read"),
+        Some(Value::U64(u64::MAX))
+    );
+    assert_eq!(
+        sk.get(&reader, "str").expect("read"),
+        Some(Value::Str("héllo, yöu"))
+    );
+    assert_eq!(
+        other.get(&reader, [0, 0xff, 0x80]).expect("read"),
+        Some(Value::Blob(&[1, 2, 3, 0, 255]))
+    );
+}
+
+#[test]
+#[cfg(feature = "lmdb")]
+fn test_any_rkv() {
+    let root = Builder::new()
+        .prefix("test_any_rkv")
+        .tempdir()
+        .expect("tempdir");
+
+    let mut envs = vec![];
+    for backend in [Backend::SafeMode, Backend::Lmdb] {
+        let path = root.path().join(format!("{:?}", backend));
+        fs::create_dir_all(&path).expect("dir created");
+
+        let k = AnyRkv::open(&path, backend).expect("opened");
+        assert_eq!(k.backend(), backend);
+        let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+        assert_eq!(k.get_dbs().expect("dbs"), vec![Some("sk".to_string())]);
+
+        let mut writer = k.write().expect("writer");
+        sk.put(&mut writer, "foo", &Value::I64(1234))
+            .expect("wrote");
+        sk.put(&mut writer, "bar", &Value::Str("baz"))
+            .expect("wrote");
+        sk.delete(&mut writer, "bar").expect("deleted");
+        writer.commit().expect("committed");
+
+        let reader = k.read().expect("reader");
+        assert_eq!(
+            sk.get(&reader, "foo").expect("read"),
+            Some(Value::I64(1234))
+        );
+        assert_eq!(sk.get(&reader, "bar").expect("read"), None);
+        reader.abort();
+
+        envs.push((k, sk));
+    }
+
+    // Stores can't be used with another backend's readers.
+    let reader = envs[0].0.read().expect("reader");
+    assert!(matches!(
+        envs[1].1.get(&reader, "foo"),
+        Err(StoreError::BackendMismatch)
+    ));
+}
+
+/// Scenarios that behave the same with every backend, expanded once per backend by
+/// the modules below, which refer to the backend and its flags through the aliases.
+macro_rules! backend_tests {
+    ($backend:ty, $write_flags:ty, $database_flags:ty) => {
+        type BackendImpl = $backend;
+        type WriteFlagsImpl = $write_flags;
+        type DatabaseFlagsImpl = $database_flags;
+
+        #[test]
+        fn test_single_store_put_many() {
+            let root = Builder::new()
+                .prefix("test_single_store_put_many")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let keys: Vec<String> = (0..5000).map(|i| format!("key{i:05}")).collect();
+
+            {
+                let mut writer = k.write().expect("writer");
+                let items = keys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, key)| (key, Value::U64(i as u64)));
+                sk.put_many(&mut writer, items).expect("wrote");
+                writer.commit().expect("committed");
+            }
+
+            {
+                let r = k.read().unwrap();
+                for (i, key) in keys.iter().enumerate() {
+                    assert_eq!(sk.get(&r, key).expect("read"), Some(Value::U64(i as u64)));
+                }
+                assert_eq!(sk.iter_start(&r).expect("iter").count(), keys.len());
+            }
+        }
+
+        #[test]
+        fn test_single_store_put_if_absent() {
+            let root = Builder::new()
+                .prefix("test_single_store_put_if_absent")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            {
+                let mut writer = k.write().expect("writer");
+                assert!(sk
+                    .put_if_absent(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote"));
+                assert!(!sk
+                    .put_if_absent(&mut writer, "foo", &Value::I64(5678))
+                    .expect("wrote"));
+                assert_eq!(
+                    sk.get(&writer, "foo").expect("read"),
+                    Some(Value::I64(1234))
+                );
+                writer.commit().expect("committed");
+            }
+
+            {
+                let mut writer = k.write().expect("writer");
+                assert!(!sk
+                    .put_if_absent(&mut writer, "foo", &Value::Str("bar"))
+                    .expect("wrote"));
+                assert!(sk
+                    .put_if_absent(&mut writer, "bar", &Value::Str("baz"))
+                    .expect("wrote"));
+                writer.commit().expect("committed");
+            }
+
+            let r = k.read().unwrap();
+            assert_eq!(sk.get(&r, "foo").expect("read"), Some(Value::I64(1234)));
+            assert_eq!(sk.get(&r, "bar").expect("read"), Some(Value::Str("baz")));
+        }
+
+        #[test]
+        fn test_single_store_compare_and_swap() {
+            let root = Builder::new()
+                .prefix("test_single_store_compare_and_swap")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+
+            // Expecting absence only succeeds while the key is missing.
+            assert!(sk
+                .compare_and_swap(&mut writer, "foo", None, &Value::U64(1))
+                .expect("swapped"));
+            assert!(!sk
+                .compare_and_swap(&mut writer, "foo", None, &Value::U64(2))
+                .expect("swapped"));
+            assert_eq!(sk.get(&writer, "foo").expect("read"), Some(Value::U64(1)));
+
+            // Expecting a value only succeeds if the stored value matches it, including its type.
+            assert!(!sk
+                .compare_and_swap(&mut writer, "foo", Some(&Value::U64(2)), &Value::U64(3))
+                .expect("swapped"));
+            assert!(!sk
+                .compare_and_swap(&mut writer, "foo", Some(&Value::I64(1)), &Value::U64(3))
+                .expect("swapped"));
+            assert_eq!(sk.get(&writer, "foo").expect("read"), Some(Value::U64(1)));
+            assert!(sk
+                .compare_and_swap(&mut writer, "foo", Some(&Value::U64(1)), &Value::U64(3))
+                .expect("swapped"));
+            assert_eq!(sk.get(&writer, "foo").expect("read"), Some(Value::U64(3)));
+
+            // Expecting a value for a missing key fails.
+            assert!(!sk
+                .compare_and_swap(&mut writer, "bar", Some(&Value::U64(3)), &Value::U64(4))
+                .expect("swapped"));
+            assert_eq!(sk.get(&writer, "bar").expect("read"), None);
+
+            writer.commit().expect("committed");
+        }
+
+        #[test]
+        fn test_single_store_put_get_old() {
+            let root = Builder::new()
+                .prefix("test_single_store_put_get_old")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            assert_eq!(
+                sk.put_get_old(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote"),
+                None
+            );
+            assert_eq!(
+                sk.put_get_old(&mut writer, "foo", &Value::Str("bar"))
+                    .expect("wrote"),
+                Some(OwnedValue::I64(1234))
+            );
+            writer.commit().expect("committed");
+
+            let mut writer = k.write().expect("writer");
+            assert_eq!(
+                sk.put_get_old(&mut writer, "foo", &Value::Bool(true))
+                    .expect("wrote"),
+                Some(OwnedValue::Str("bar".to_string()))
+            );
+            writer.commit().expect("committed");
+
+            let r = k.read().unwrap();
+            assert_eq!(sk.get(&r, "foo").expect("read"), Some(Value::Bool(true)));
+        }
+
+        #[test]
+        fn test_reader_get_map() {
+            let root = Builder::new()
+                .prefix("test_reader_get_map")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            {
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote");
+                sk.put(&mut writer, "bar", &Value::Str("baz"))
+                    .expect("wrote");
+                sk.put(&mut writer, "qux", &Value::Bool(true))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+            }
+
+            let r = k.read().unwrap();
+            let map = r
+                .get_map(&sk, ["foo", "missing", "bar", "also missing"])
+                .expect("read");
+            assert_eq!(map.len(), 2);
+            assert_eq!(map.get(&b"foo"[..]), Some(&Value::I64(1234)));
+            assert_eq!(map.get(&b"bar"[..]), Some(&Value::Str("baz")));
+            assert!(!map.contains_key(&b"missing"[..]));
+            assert!(!map.contains_key(&b"qux"[..]));
+
+            let empty = r.get_map(&sk, Vec::<&str>::new()).expect("read");
+            assert!(empty.is_empty());
+        }
+
+        #[test]
+        fn test_single_store_increment() {
+            let root = Builder::new()
+                .prefix("test_single_store_increment")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            {
+                let mut writer = k.write().expect("writer");
+
+                // A missing key counts as zero.
+                assert_eq!(
+                    sk.increment(&mut writer, "missing", 5)
+                        .expect("incremented"),
+                    5
+                );
+                assert_eq!(
+                    sk.get(&writer, "missing").expect("read"),
+                    Some(Value::I64(5))
+                );
+
+                // Existing integers are incremented, and stored as I64.
+                sk.put(&mut writer, "i64", &Value::I64(-10)).expect("wrote");
+                sk.put(&mut writer, "u64", &Value::U64(10)).expect("wrote");
+                assert_eq!(
+                    sk.increment(&mut writer, "i64", 1).expect("incremented"),
+                    -9
+                );
+                assert_eq!(
+                    sk.increment(&mut writer, "u64", -11).expect("incremented"),
+                    -1
+                );
+                assert_eq!(sk.get(&writer, "u64").expect("read"), Some(Value::I64(-1)));
+                writer.commit().expect("committed");
+            }
+
+            {
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "str", &Value::Str("10"))
+                    .expect("wrote");
+                match sk.increment(&mut writer, "str", 1) {
+                    Err(StoreError::DataError(DataError::UnexpectedType { .. })) => (),
+                    result => panic!("unexpected result {:?}", result),
+                }
+                assert_eq!(
+                    sk.get(&writer, "str").expect("read"),
+                    Some(Value::Str("10"))
+                );
+
+                sk.put(&mut writer, "max", &Value::I64(i64::MAX))
+                    .expect("wrote");
+                match sk.increment(&mut writer, "max", 1) {
+                    Err(StoreError::DataError(DataError::IntegerOverflow)) => (),
+                    result => panic!("unexpected result {:?}", result),
+                }
+                writer.commit().expect("committed");
+            }
+
+            let r = k.read().unwrap();
+            assert_eq!(sk.get(&r, "missing").expect("read"), Some(Value::I64(5)));
+            assert_eq!(sk.get(&r, "i64").expect("read"), Some(Value::I64(-9)));
+            assert_eq!(sk.get(&r, "max").expect("read"), Some(Value::I64(i64::MAX)));
+        }
+
+        #[test]
+        fn test_single_store_utf8_keys() {
+            let root = Builder::new()
+                .prefix("test_single_store_utf8_keys")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let mut opts = StoreOptions::create();
+            opts.utf8_keys = true;
+            let sk = k.open_single("sk", opts).expect("opened");
+
+            {
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote");
+                sk.put(&mut writer, "héllò", &Value::Str("töűrîst"))
+                    .expect("wrote");
+                assert!(matches!(
+                    sk.put(&mut writer, b"\xff\xfe", &Value::I64(1)),
+                    Err(StoreError::NonUtf8Key)
+                ));
+                assert!(matches!(
+                    sk.get(&writer, b"\xff\xfe"),
+                    Err(StoreError::NonUtf8Key)
+                ));
+                writer.commit().expect("committed");
+            }
+
+            {
+                let r = k.read().unwrap();
+                let entries: Vec<(&str, Value)> = sk
+                    .iter_start_str(&r)
+                    .expect("iter")
+                    .map(|entry| entry.expect("entry"))
+                    .collect();
+                assert_eq!(
+                    entries,
+                    [("foo", Value::I64(1234)), ("héllò", Value::Str("töűrîst"))]
+                );
+            }
+
+            // Keys written through a handle that doesn't enforce UTF-8 are caught when
+            // iterating through one that does.
+            let raw = k
+                .open_single("sk", StoreOptions::default())
+                .expect("opened");
+            {
+                let mut writer = k.write().expect("writer");
+                raw.put(&mut writer, b"\xff\xfe", &Value::I64(1))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+            }
+
+            let r = k.read().unwrap();
+            let mut iter = sk.iter_start(&r).expect("iter");
+            assert!(iter.next().expect("entry").is_ok());
+            assert!(iter.next().expect("entry").is_ok());
+            assert!(matches!(iter.next(), Some(Err(StoreError::NonUtf8Key))));
+            assert!(iter.next().is_none());
+
+            let mut iter = raw.iter_start_str(&r).expect("iter");
+            assert_eq!(iter.next().expect("entry").expect("key").0, "foo");
+            assert_eq!(iter.next().expect("entry").expect("key").0, "héllò");
+            assert!(matches!(iter.next(), Some(Err(StoreError::NonUtf8Key))));
+        }
+
+        #[test]
+        fn test_single_store_exists() {
+            let root = Builder::new()
+                .prefix("test_single_store_exists")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            {
+                let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+                let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+                let mut writer = k.write().expect("writer");
+                assert!(!sk.exists(&writer, "foo").expect("checked"));
+                sk.put(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote");
+                sk.put(&mut writer, "bar", &Value::Bool(true))
+                    .expect("wrote");
+                assert!(sk.exists(&writer, "foo").expect("checked"));
+                sk.delete(&mut writer, "bar").expect("deleted");
+                assert!(!sk.exists(&writer, "bar").expect("checked"));
+                writer.commit().expect("committed");
+
+                let r = k.read().unwrap();
+                assert!(sk.exists(&r, "foo").expect("checked"));
+                assert!(!sk.exists(&r, "bar").expect("checked"));
+            }
+
+            // Store a value that can't be decoded, bypassing the typed API.
+            {
+                let env = BackendImpl::new().set_max_dbs(1).open(root.path()).expect("env");
+                let db = env.open_db(Some("sk")).expect("opened");
+                let mut txn = env.begin_rw_txn().expect("txn");
+                txn.put(&db, b"corrupt", &[0xff], WriteFlagsImpl::empty())
+                    .expect("wrote");
+                txn.commit().expect("committed");
+            }
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k
+                .open_single("sk", StoreOptions::default())
+                .expect("opened");
+            let r = k.read().unwrap();
+            assert!(sk.get(&r, "corrupt").is_err());
+            assert!(sk.exists(&r, "corrupt").expect("checked"));
+        }
+
+        #[test]
+        #[cfg(feature = "db-dup-sort")]
+        fn test_multi_iter() {
+            let root = Builder::new()
+                .prefix("test_multi_iter")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let multistore = k.open_multi("multistore", StoreOptions::create()).unwrap();
+            let empty = k.open_multi("empty", StoreOptions::create()).unwrap();
+
+            {
+                let mut writer = k.write().expect("writer");
+                // Insert out of order; iteration is ordered by key, then by value.
+                for (key, val) in [
+                    ("b", "b2"),
+                    ("a", "a3"),
+                    ("c", "c1"),
+                    ("a", "a1"),
+                    ("b", "b1"),
+                    ("a", "a2"),
+                ] {
+                    multistore
+                        .put(&mut writer, key, &Value::Str(val))
+                        .expect("wrote");
+                }
+                writer.commit().expect("committed");
+            }
+
+            let r = k.read().unwrap();
+            let entries: Vec<(&str, Value)> = multistore
+                .iter_start(&r)
+                .expect("iter")
+                .map(|entry| {
+                    let (key, val) = entry.expect("entry");
+                    (str::from_utf8(key).expect("key"), val)
+                })
+                .collect();
+            assert_eq!(
+                entries,
+                [
+                    ("a", Value::Str("a1")),
+                    ("a", Value::Str("a2")),
+                    ("a", Value::Str("a3")),
+                    ("b", Value::Str("b1")),
+                    ("b", Value::Str("b2")),
+                    ("c", Value::Str("c1")),
+                ]
+            );
+
+            let mut iter = multistore.iter_from(&r, "b").expect("iter");
+            let (key, val) = iter.next().expect("first").expect("ok");
+            assert_eq!((key, val), (&b"b"[..], Value::Str("b1")));
+            assert_eq!(iter.count(), 2);
+
+            assert!(empty.iter_start(&r).expect("iter").next().is_none());
+        }
+
+        #[test]
+        fn test_write_read_only() {
+            let root = Builder::new()
+                .prefix("test_write_read_only")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            {
+                let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+                let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+            }
+
+            let mut builder = Rkv::environment_builder::<BackendImpl>();
+            builder.set_max_dbs(1);
+            builder.set_flags(EnvironmentFlags::READ_ONLY);
+            let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+            let sk = k
+                .open_single("sk", StoreOptions::default())
+                .expect("opened");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                sk.get(&reader, "foo").expect("read"),
+                Some(Value::I64(1234))
+            );
+
+            assert!(matches!(k.write(), Err(StoreError::EnvironmentReadOnly)));
+        }
+
+        #[test]
+        fn test_debug_summary() {
+            let root = Builder::new()
+                .prefix("test_debug_summary")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let users = k
+                .open_single("users", StoreOptions::create())
+                .expect("opened");
+            let _ = k
+                .open_single("empty", StoreOptions::create())
+                .expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            users
+                .put(&mut writer, "alice", &Value::Str("secret-token-1"))
+                .expect("wrote");
+            users
+                .put(&mut writer, "bob", &Value::Str("secret-token-2"))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            let debug = format!("{:?}", k);
+            assert!(debug.contains("\"users\": 2"), "{}", debug);
+            assert!(debug.contains("\"empty\": 0"), "{}", debug);
+            assert!(!debug.contains("alice"), "{}", debug);
+            assert!(!debug.contains("secret-token"), "{}", debug);
+        }
+
+        #[test]
+        fn test_log_store() {
+            let root = Builder::new()
+                .prefix("test_log_store")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            {
+                let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+                let log = k.open_log("log", StoreOptions::create()).expect("opened");
+
+                let mut writer = k.write().expect("writer");
+                assert_eq!(log.next_sequence(&writer).expect("read"), 0);
+                for i in 0..300 {
+                    let seq = log
+                        .append(&mut writer, &Value::U64(i * 10))
+                        .expect("appended");
+                    assert_eq!(seq, i);
+                }
+                writer.commit().expect("committed");
+
+                let reader = k.read().expect("reader");
+                assert_eq!(log.next_sequence(&reader).expect("read"), 300);
+                assert_eq!(log.get(&reader, 255).expect("read"), Some(Value::U64(2550)));
+                assert_eq!(log.get(&reader, 300).expect("read"), None);
+
+                // Big-endian keys keep 255 -> 256 (a carry into the next byte) in order.
+                let entries: Vec<(u64, Value)> = log
+                    .range(&reader, 250, 260)
+                    .expect("range")
+                    .map(|entry| entry.expect("entry"))
+                    .collect();
+                let expected: Vec<(u64, Value)> =
+                    (250..260).map(|i| (i, Value::U64(i * 10))).collect();
+                assert_eq!(entries, expected);
+
+                assert_eq!(log.range(&reader, 0, 300).expect("range").count(), 300);
+                assert_eq!(log.range(&reader, 290, 1000).expect("range").count(), 10);
+                assert_eq!(log.range(&reader, 300, 1000).expect("range").count(), 0);
+            }
+
+            // The next sequence number survives reopening the environment.
+            {
+                let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+                let log = k.open_log("log", StoreOptions::default()).expect("opened");
+
+                let mut writer = k.write().expect("writer");
+                assert_eq!(
+                    log.append(&mut writer, &Value::Str("next"))
+                        .expect("appended"),
+                    300
+                );
+                writer.commit().expect("committed");
+            }
+        }
+
+        #[test]
+        fn test_log_store_multiple_thread() {
+            let root = Builder::new()
+                .prefix("test_log_store_multiple_thread")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let rkv_arc = Arc::new(RwLock::new(
+                Rkv::new::<BackendImpl>(root.path()).expect("new succeeded"),
+            ));
+            let log = rkv_arc
+                .read()
+                .unwrap()
+                .open_log("log", StoreOptions::create())
+                .expect("opened");
+
+            // Each thread appends its own entries. Safe mode doesn't serialize concurrent
+            // writers itself, so take the environment lock exclusively around each append.
+            let num_threads = 8;
+            let appends_per_thread = 25;
+            let handles: Vec<_> = (0..num_threads)
+                .map(|t| {
+                    let rkv_arc = rkv_arc.clone();
+                    thread::spawn(move || {
+                        let mut seqs = Vec::new();
+                        for i in 0..appends_per_thread {
+                            let rkv = rkv_arc.write().expect("rkv");
+                            let mut writer = rkv.write().expect("writer");
+                            let seq = log
+                                .append(&mut writer, &Value::U64(t * 1000 + i))
+                                .expect("appended");
+                            writer.commit().expect("committed");
+                            seqs.push(seq);
+                        }
+                        seqs
+                    })
+                })
+                .collect();
+
+            let mut all_seqs = Vec::new();
+            for handle in handles {
+                let seqs = handle.join().expect("joined");
+                assert!(seqs.windows(2).all(|w| w[0] < w[1]));
+                all_seqs.extend(seqs);
+            }
+            all_seqs.sort_unstable();
+            let total = num_threads * appends_per_thread;
+            assert_eq!(all_seqs, (0..total).collect::<Vec<u64>>());
+
+            let rkv = rkv_arc.read().expect("rkv");
+            let reader = rkv.read().expect("reader");
+            let keys: Vec<u64> = log
+                .range(&reader, 0, u64::MAX)
+                .expect("range")
+                .map(|entry| entry.expect("entry").0)
+                .collect();
+            assert_eq!(keys, (0..total).collect::<Vec<u64>>());
+        }
+
+        #[test]
+        fn test_single_store_changelog() {
+            let root = Builder::new()
+                .prefix("test_single_store_changelog")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let mut opts = StoreOptions::create();
+            opts.changelog = true;
+            let sk = k.open_single("sk", opts).expect("opened");
+            let plain = k
+                .open_single("plain", StoreOptions::create())
+                .expect("opened");
+
+            {
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote");
+                sk.put(&mut writer, "bar", &Value::Bool(true))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+            }
+
+            let generation = sk
+                .generation(&k.read().expect("reader"))
+                .expect("generation");
+            assert_eq!(generation, 2);
+
+            {
+                let mut writer = k.write().expect("writer");
+                sk.increment(&mut writer, "counter", 1)
+                    .expect("incremented");
+                assert!(!sk
+                    .put_if_absent(&mut writer, "foo", &Value::I64(1))
+                    .expect("wrote"));
+                sk.delete(&mut writer, "bar").expect("deleted");
+                writer.commit().expect("committed");
+            }
+            {
+                // Aborted changes aren't recorded.
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "baz", &Value::I64(1)).expect("wrote");
+                writer.abort();
+            }
+            {
+                let mut writer = k.write().expect("writer");
+                sk.clear(&mut writer).expect("cleared");
+                writer.commit().expect("committed");
+            }
+
+            let reader = k.read().expect("reader");
+            let change = |generation, op, key: &str| Change {
+                generation,
+                op,
+                key: key.as_bytes().to_vec(),
+            };
+            assert_eq!(
+                sk.changes_since(&reader, generation).expect("changes"),
+                vec![
+                    change(2, ChangeOp::Put, "counter"),
+                    change(3, ChangeOp::Delete, "bar"),
+                    change(4, ChangeOp::Clear, ""),
+                ]
+            );
+            assert_eq!(sk.changes_since(&reader, 5).expect("changes"), vec![]);
+            assert!(matches!(
+                plain.changes_since(&reader, 0),
+                Err(StoreError::NoChangelog)
+            ));
+        }
+
+        #[test]
+        fn test_single_store_append() {
+            let root = Builder::new()
+                .prefix("test_single_store_append")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            {
+                let mut writer = k.write().expect("writer");
+                for i in 0..100u64 {
+                    sk.append(&mut writer, i.to_be_bytes(), &Value::U64(i))
+                        .expect("appended");
+                }
+                writer.commit().expect("committed");
+            }
+            {
+                let reader = k.read().expect("reader");
+                let values: Vec<Value> = sk
+                    .iter_start(&reader)
+                    .expect("iter")
+                    .map(|entry| entry.expect("entry").1)
+                    .collect();
+                assert_eq!(values, (0..100u64).map(Value::U64).collect::<Vec<_>>());
+            }
+
+            let mut writer = k.write().expect("writer");
+            assert!(matches!(
+                sk.append(&mut writer, 50u64.to_be_bytes(), &Value::U64(0)),
+                Err(StoreError::KeyOutOfOrder)
+            ));
+            assert!(matches!(
+                sk.append(&mut writer, 99u64.to_be_bytes(), &Value::U64(0)),
+                Err(StoreError::KeyOutOfOrder)
+            ));
+            sk.append(&mut writer, 100u64.to_be_bytes(), &Value::U64(100))
+                .expect("appended");
+            assert_eq!(
+                sk.get(&writer, 99u64.to_be_bytes()).expect("read"),
+                Some(Value::U64(99))
+            );
+        }
+
+        #[test]
+        fn test_single_store_put_into() {
+            let root = Builder::new()
+                .prefix("test_single_store_put_into")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put_into(&mut writer, "i64", 1234_i64).expect("wrote");
+            sk.put_into(&mut writer, "u64", 1234_u64).expect("wrote");
+            sk.put_into(&mut writer, "bool", true).expect("wrote");
+            sk.put_into(&mut writer, "f64", 1.5_f64).expect("wrote");
+            sk.put_into(&mut writer, "str", "héllo").expect("wrote");
+            sk.put_into(&mut writer, "blob", &b"bytes"[..])
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                sk.get(&reader, "i64").expect("read"),
+                Some(Value::I64(1234))
+            );
+            assert_eq!(
+                sk.get(&reader, "u64").expect("read"),
+                Some(Value::U64(1234))
+            );
+            assert_eq!(
+                sk.get(&reader, "bool").expect("read"),
+                Some(Value::Bool(true))
+            );
+            assert_eq!(
+                sk.get(&reader, "f64").expect("read"),
+                Some(Value::from(1.5_f64))
+            );
+            assert_eq!(
+                sk.get(&reader, "str").expect("read"),
+                Some(Value::Str("héllo"))
+            );
+            assert_eq!(
+                sk.get(&reader, "blob").expect("read"),
+                Some(Value::Blob(b"bytes"))
+            );
+        }
+
+        #[test]
+        fn test_set_read_only() {
+            let root = Builder::new()
+                .prefix("test_set_read_only")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            {
+                let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+                let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+            }
+
+            let mut builder = Rkv::environment_builder::<BackendImpl>();
+            builder.set_max_dbs(1);
+            builder.set_read_only(true);
+            {
+                let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+                let sk = k
+                    .open_single("sk", StoreOptions::default())
+                    .expect("opened");
+                let reader = k.read().expect("reader");
+                assert_eq!(
+                    sk.get(&reader, "foo").expect("read"),
+                    Some(Value::I64(1234))
+                );
+                assert!(matches!(k.write(), Err(StoreError::EnvironmentReadOnly)));
+            }
+
+            builder.set_read_only(false);
+            let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+            let sk = k
+                .open_single("sk", StoreOptions::default())
+                .expect("opened");
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "bar", &Value::Bool(true))
+                .expect("wrote");
+            writer.commit().expect("committed");
+        }
+
+        #[test]
+        fn test_copy_to() {
+            let root = Builder::new()
+                .prefix("test_copy_to")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1234))
+                .expect("wrote");
+            sk.put(&mut writer, "bar", &Value::Str("baz"))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            // The target directories are created as needed.
+            let backup = root.path().join("backup");
+            let compacted = root.path().join("compacted").join("nested");
+            k.copy_to(&backup, false).expect("copied");
+            k.copy_to(&compacted, true).expect("copied");
+
+            // Writes made after the copy aren't part of it.
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "qux", &Value::Bool(true))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            // Copying over an existing copy fails rather than clobbering it.
+            assert!(k.copy_to(&backup, false).is_err());
+
+            for path in &[backup, compacted] {
+                let copy = Rkv::new::<BackendImpl>(path).expect("opened copy");
+                let sk = copy
+                    .open_single("sk", StoreOptions::default())
+                    .expect("opened");
+                let reader = copy.read().expect("reader");
+                assert_eq!(
+                    sk.get(&reader, "foo").expect("read"),
+                    Some(Value::I64(1234))
+                );
+                assert_eq!(
+                    sk.get(&reader, "bar").expect("read"),
+                    Some(Value::Str("baz"))
+                );
+                assert_eq!(sk.get(&reader, "qux").expect("read"), None);
+            }
+        }
+
+        #[test]
+        fn test_single_store_cursor() {
+            let root = Builder::new()
+                .prefix("test_single_store_cursor")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            for (i, key) in ["a", "c", "e", "g"].iter().enumerate() {
+                sk.put(&mut writer, key, &Value::U64(i as u64))
+                    .expect("wrote");
+            }
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            macro_rules! entry {
+                ($result:expr) => {
+                    $result.map(|result| result.expect("entry"))
+                };
+            }
+
+            // Find a key, then read its neighbors.
+            let mut cursor = sk.cursor(&reader).expect("cursor");
+            assert_eq!(
+                entry!(cursor.seek_exact("e")),
+                Some((&b"e"[..], Value::U64(2)))
+            );
+            assert_eq!(entry!(cursor.prev()), Some((&b"c"[..], Value::U64(1))));
+            assert_eq!(entry!(cursor.next()), Some((&b"e"[..], Value::U64(2))));
+            assert_eq!(entry!(cursor.next()), Some((&b"g"[..], Value::U64(3))));
+            assert_eq!(entry!(cursor.next()), None);
+
+            // Seek to missing keys.
+            assert_eq!(entry!(cursor.seek_exact("d")), None);
+            assert_eq!(
+                entry!(cursor.seek_range("d")),
+                Some((&b"e"[..], Value::U64(2)))
+            );
+            assert_eq!(entry!(cursor.seek_range("h")), None);
+
+            // Step past either end.
+            assert_eq!(entry!(cursor.first()), Some((&b"a"[..], Value::U64(0))));
+            assert_eq!(entry!(cursor.prev()), None);
+            assert_eq!(entry!(cursor.last()), Some((&b"g"[..], Value::U64(3))));
+            assert_eq!(entry!(cursor.next()), None);
+
+            // An unpositioned cursor steps from either end.
+            let mut cursor = sk.cursor(&reader).expect("cursor");
+            assert_eq!(entry!(cursor.prev()), Some((&b"g"[..], Value::U64(3))));
+            let mut cursor = sk.cursor(&reader).expect("cursor");
+            assert_eq!(entry!(cursor.next()), Some((&b"a"[..], Value::U64(0))));
+        }
+
+        #[test]
+        fn test_single_store_get_many() {
+            let root = Builder::new()
+                .prefix("test_single_store_get_many")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1234))
+                .expect("wrote");
+            sk.put(&mut writer, "bar", &Value::Bool(true))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                sk.get_many(&reader, vec!["bar", "missing", "foo", "bar"])
+                    .expect("read"),
+                vec![
+                    Some(Value::Bool(true)),
+                    None,
+                    Some(Value::I64(1234)),
+                    Some(Value::Bool(true)),
+                ]
+            );
+            assert!(sk
+                .get_many(&reader, Vec::<&str>::new())
+                .expect("read")
+                .is_empty());
+        }
+
+        #[test]
+        fn test_single_store_delete_range() {
+            let root = Builder::new()
+                .prefix("test_single_store_delete_range")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            for key in &["a", "b", "c", "d", "e", "f", "g"] {
+                sk.put(&mut writer, key, &Value::Str(key)).expect("wrote");
+            }
+
+            // Half-open ranges exclude their end.
+            assert_eq!(sk.delete_range(&mut writer, "b".."d").expect("deleted"), 2);
+            // Inclusive ranges don't, and keys don't need to be present to bound a range.
+            assert_eq!(
+                sk.delete_range(&mut writer, "cc"..="e").expect("deleted"),
+                2
+            );
+            // Empty ranges delete nothing.
+            assert_eq!(sk.delete_range(&mut writer, "f".."f").expect("deleted"), 0);
+            assert_eq!(sk.delete_range(&mut writer, "g".."f").expect("deleted"), 0);
+            assert_eq!(sk.delete_range(&mut writer, "x"..).expect("deleted"), 0);
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            let keys: Vec<_> = sk
+                .iter_start(&reader)
+                .expect("iter")
+                .map(|result| result.expect("entry").0)
+                .collect();
+            assert_eq!(keys, vec![b"a", b"f", b"g"]);
+            drop(reader);
+
+            // Unbounded ranges reach either end of the store.
+            let mut writer = k.write().expect("writer");
+            assert_eq!(sk.delete_range(&mut writer, .."b").expect("deleted"), 1);
+            assert_eq!(
+                sk.delete_range(
+                    &mut writer,
+                    (Bound::Excluded("f".to_string()), Bound::Unbounded)
+                )
+                .expect("deleted"),
+                1
+            );
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(sk.get(&reader, "a").expect("read"), None);
+            assert_eq!(sk.get(&reader, "f").expect("read"), Some(Value::Str("f")));
+            assert_eq!(sk.get(&reader, "g").expect("read"), None);
+        }
+
+        #[test]
+        fn test_single_store_delete_if_exists() {
+            let root = Builder::new()
+                .prefix("test_single_store_delete_if_exists")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1234))
+                .expect("wrote");
+            assert!(sk.delete_if_exists(&mut writer, "foo").expect("deleted"));
+            assert!(!sk.delete_if_exists(&mut writer, "foo").expect("deleted"));
+            assert!(!sk
+                .delete_if_exists(&mut writer, "missing")
+                .expect("deleted"));
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(sk.get(&reader, "foo").expect("read"), None);
+        }
+
+        #[test]
+        fn test_single_store_put_with_flags() {
+            let root = Builder::new()
+                .prefix("test_single_store_put_with_flags")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put_with_flags(
+                &mut writer,
+                "foo",
+                &Value::I64(1234),
+                WriteFlags::NO_OVERWRITE,
+            )
+            .expect("wrote");
+            assert!(matches!(
+                sk.put_with_flags(
+                    &mut writer,
+                    "foo",
+                    &Value::I64(5678),
+                    WriteFlags::NO_OVERWRITE
+                ),
+                Err(StoreError::KeyValuePairExists)
+            ));
+            assert_eq!(
+                sk.get(&writer, "foo").expect("read"),
+                Some(Value::I64(1234))
+            );
+
+            // Appending works for sorted keys.
+            for key in &["g", "h", "i"] {
+                sk.put_with_flags(&mut writer, key, &Value::Str(key), WriteFlags::APPEND)
+                    .expect("appended");
+            }
+            assert!(sk
+                .put_with_flags(&mut writer, "a", &Value::Str("a"), WriteFlags::APPEND)
+                .is_err());
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            let keys: Vec<_> = sk
+                .iter_start(&reader)
+                .expect("iter")
+                .map(|result| result.expect("entry").0)
+                .collect();
+            assert_eq!(keys, vec![&b"foo"[..], b"g", b"h", b"i"]);
+        }
+
+        #[test]
+        fn test_export_json() {
+            let root = Builder::new()
+                .prefix("test_export_json")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "a", &Value::Bool(true)).expect("wrote");
+            sk.put(&mut writer, "b", &Value::I64(-1234)).expect("wrote");
+            sk.put(&mut writer, "c", &Value::F64(f64::NAN.into()))
+                .expect("wrote");
+            sk.put(&mut writer, "d", &Value::Str("dee")).expect("wrote");
+            sk.put(&mut writer, [0xfb, 0xff], &Value::Blob(b"blob"))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            let mut dump = vec![];
+            k.export_json(&mut dump).expect("exported");
+            let dump = String::from_utf8(dump).expect("utf-8");
+            assert_eq!(
+                dump.lines().collect::<Vec<_>>(),
+                vec![
+                    r#"{"key_b64":"YQ==","store":"sk","value":{"data":true,"type":"Bool"}}"#,
+                    r#"{"key_b64":"Yg==","store":"sk","value":{"data":-1234,"type":"I64"}}"#,
+                    r#"{"key_b64":"Yw==","store":"sk","value":{"data":"NaN","type":"F64"}}"#,
+                    r#"{"key_b64":"ZA==","store":"sk","value":{"data":"dee","type":"Str"}}"#,
+                    r#"{"key_b64":"+/8=","store":"sk","value":{"data":"YmxvYg==","type":"Blob"}}"#,
+                ]
+            );
+        }
+
+        #[test]
+        #[cfg(all(feature = "db-dup-sort", feature = "db-int-key"))]
+        fn test_export_json_flags() {
+            let root = Builder::new()
+                .prefix("test_export_json_flags")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let int = k
+                .open_integer::<_, u32>("int", StoreOptions::create())
+                .expect("opened");
+            let mut writer = k.write().expect("writer");
+            int.put(&mut writer, 1, &Value::I64(1)).expect("wrote");
+            writer.commit().expect("committed");
+
+            // Integer stores list their flag.
+            let mut dump = vec![];
+            k.export_json(&mut dump).expect("exported");
+            let dump = String::from_utf8(dump).expect("utf-8");
+            assert_eq!(
+                dump.lines().collect::<Vec<_>>(),
+                vec![concat!(
+                    r#"{"flags":["INTEGER_KEY"],"key_b64":"AQAAAA==","store":"int","#,
+                    r#""value":{"data":1,"type":"I64"}}"#
+                )]
+            );
+
+            // Stores with several values per key fail rather than being left out.
+            let multi = k
+                .open_multi("multi", StoreOptions::create())
+                .expect("opened");
+            let mut writer = k.write().expect("writer");
+            multi.put(&mut writer, "a", &Value::I64(1)).expect("wrote");
+            multi.put(&mut writer, "a", &Value::I64(2)).expect("wrote");
+            writer.commit().expect("committed");
+            match k.export_json(&mut vec![]) {
+                Err(StoreError::FlagsNotExportable(Some(name))) => assert_eq!(name, "multi"),
+                result => panic!("expected FlagsNotExportable, got {:?}", result),
+            }
+        }
+
+        #[test]
+        fn test_expiring_store() {
+            let root = Builder::new()
+                .prefix("test_expiring_store")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let s = k
+                .open_expiring("s", StoreOptions::create())
+                .expect("opened");
+
+            let now = SystemTime::now();
+            let hour = Duration::from_secs(3600);
+            let mut writer = k.write().expect("writer");
+            s.put(&mut writer, "fresh", &Value::I64(1), now + hour)
+                .expect("wrote");
+            s.put(&mut writer, "stale", &Value::I64(2), now - hour)
+                .expect("wrote");
+            s.put(&mut writer, "older", &Value::Str("3"), now - hour * 2)
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            // Expired entries are hidden, but not deleted by reading.
+            {
+                let reader = k.read().expect("reader");
+                assert_eq!(s.get(&reader, "fresh").expect("read"), Some(Value::I64(1)));
+                assert_eq!(s.get(&reader, "stale").expect("read"), None);
+                assert_eq!(s.get(&reader, "older").expect("read"), None);
+                assert_eq!(s.get(&reader, "missing").expect("read"), None);
+                assert_eq!(
+                    s.get_at(&reader, "stale", now - hour * 3).expect("read"),
+                    Some(Value::I64(2))
+                );
+                assert_eq!(
+                    s.get_at(&reader, "older", now - hour * 3).expect("read"),
+                    Some(Value::Str("3"))
+                );
+            }
+
+            // Purging only deletes the entries that expired as of the given time.
+            let mut writer = k.write().expect("writer");
+            assert_eq!(
+                s.purge_expired(&mut writer, now - hour - hour / 2)
+                    .expect("purged"),
+                1
+            );
+            assert_eq!(s.purge_expired(&mut writer, now).expect("purged"), 1);
+            assert_eq!(s.purge_expired(&mut writer, now).expect("purged"), 0);
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                s.get_at(&reader, "stale", now - hour * 3).expect("read"),
+                None
+            );
+            assert_eq!(s.get(&reader, "fresh").expect("read"), Some(Value::I64(1)));
+        }
+
+        #[test]
+        fn test_flush() {
+            let root = Builder::new()
+                .prefix("test_flush")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            {
+                let mut builder = Rkv::environment_builder::<BackendImpl>();
+                builder.set_max_dbs(1);
+                builder.set_durability(Durability::NoSync);
+                let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+                let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+
+                k.flush().expect("flushed");
+                // Flushing again without changes is fine.
+                k.flush().expect("flushed");
+            }
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k
+                .open_single("sk", StoreOptions::default())
+                .expect("opened");
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                sk.get(&reader, "foo").expect("read"),
+                Some(Value::I64(1234))
+            );
+        }
+
+        #[test]
+        #[cfg(feature = "db-dup-sort")]
+        fn test_open_incompatible_flags() {
+            let root = Builder::new()
+                .prefix("test_open_incompatible_flags")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            // Check the stores both right after creating them, and after loading them from disk.
+            for _ in 0..2 {
+                let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+                let single = k
+                    .open_single("single", StoreOptions::create())
+                    .expect("opened");
+                let multi = k
+                    .open_multi("multi", StoreOptions::create())
+                    .expect("opened");
+
+                for opts in [StoreOptions::create(), StoreOptions::default()] {
+                    match k.open_single("multi", opts) {
+                        Err(StoreError::IncompatibleFlags) => (),
+                        result => panic!("expected IncompatibleFlags, got {:?}", result),
+                    }
+                    match k.open_multi("single", opts) {
+                        Err(StoreError::IncompatibleFlags) => (),
+                        result => panic!("expected IncompatibleFlags, got {:?}", result),
+                    }
+                }
+                k.open_single("single", StoreOptions::default())
+                    .expect("opened");
+                k.open_multi("multi", StoreOptions::default())
+                    .expect("opened");
+
+                let mut writer = k.write().expect("writer");
+                single
+                    .put(&mut writer, "foo", &Value::I64(1))
+                    .expect("wrote");
+                multi
+                    .put(&mut writer, "foo", &Value::I64(2))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+            }
+        }
+
+        #[test]
+        fn test_iter_after() {
+            let root = Builder::new()
+                .prefix("test_iter_after")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1234))
+                .expect("wrote");
+            sk.put(&mut writer, "noo", &Value::F64(1234.0.into()))
+                .expect("wrote");
+            sk.put(&mut writer, "bar", &Value::Bool(true))
+                .expect("wrote");
+            sk.put(&mut writer, "baz", &Value::Str("héllo, yöu"))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            let reader = k.read().unwrap();
+            macro_rules! keys {
+                ($iter:expr) => {
+                    $iter
+                        .map(|pair| str::from_utf8(pair.expect("pair").0).unwrap())
+                        .collect::<Vec<_>>()
+                };
+            }
+
+            // Resuming after an existing key skips it.
+            let iter = sk.iter_after(&reader, "baz").unwrap();
+            assert_eq!(keys!(iter), vec!["foo", "noo"]);
+
+            // Resuming after a missing key is the same as iterating from it.
+            let iter = sk.iter_after(&reader, "bat").unwrap();
+            assert_eq!(keys!(iter), vec!["baz", "foo", "noo"]);
+            let iter = sk.iter_from(&reader, "bat").unwrap();
+            assert_eq!(keys!(iter), vec!["baz", "foo", "noo"]);
+
+            let iter = sk.iter_after(&reader, "a").unwrap();
+            assert_eq!(keys!(iter), vec!["bar", "baz", "foo", "noo"]);
+            assert!(sk.iter_after(&reader, "noo").unwrap().next().is_none());
+            assert!(sk.iter_after(&reader, "nuu").unwrap().next().is_none());
+        }
+
+        #[test]
+        fn test_ordered_integer_iter_order() {
+            let root = Builder::new()
+                .prefix("test_ordered_integer_iter_order")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let s = k
+                .open_ordered_integer::<_, u32>("s", StoreOptions::create())
+                .expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            for key in [65536, 256, 2, 1].iter() {
+                s.put(&mut writer, *key, &Value::U64(u64::from(*key)))
+                    .expect("wrote");
+            }
+            writer.commit().expect("committed");
+
+            // Little-endian keys would sort as 65536, 256, 1, 2.
+            let reader = k.read().expect("reader");
+            let keys = s
+                .iter_start(&reader)
+                .expect("iter")
+                .map(|pair| pair.expect("pair").0)
+                .collect::<Vec<_>>();
+            assert_eq!(keys, vec![1, 2, 256, 65536]);
+        }
+
+        #[test]
+        fn test_iter_values() {
+            let root = Builder::new()
+                .prefix("test_iter_values")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1234))
+                .expect("wrote");
+            sk.put(&mut writer, "bar", &Value::I64(-34)).expect("wrote");
+            sk.put(&mut writer, "baz", &Value::I64(100)).expect("wrote");
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            let sum = sk
+                .iter_values(&reader)
+                .expect("iter")
+                .map(|value| match value.expect("value") {
+                    Value::I64(n) => n,
+                    value => panic!("unexpected value {:?}", value),
+                })
+                .sum::<i64>();
+            assert_eq!(sum, 1300);
+        }
+
+        #[test]
+        fn test_raw_values() {
+            let root = Builder::new()
+                .prefix("test_raw_values")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            // 0xff isn't a type tag, so these bytes can't be decoded as a value.
+            let raw = [0xff, 0x00, 0x42];
+            let mut writer = k.write().expect("writer");
+            sk.put_raw(&mut writer, "raw", &raw).expect("wrote");
+            sk.put(&mut writer, "foo", &Value::I64(1234))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(sk.get_raw(&reader, "raw").expect("read"), Some(&raw[..]));
+            assert!(matches!(
+                sk.get(&reader, "raw"),
+                Err(StoreError::DataError(DataError::UnknownType(0xff)))
+            ));
+            assert_eq!(sk.get_raw(&reader, "missing").expect("read"), None);
+
+            // Values written by `put` are returned with their type tag.
+            let tagged = Value::I64(1234).to_bytes().expect("bytes");
+            assert_eq!(sk.get_raw(&reader, "foo").expect("read"), Some(&tagged[..]));
+        }
+
+        #[test]
+        fn test_nested_reads() {
+            let root = Builder::new()
+                .prefix("test_nested_reads")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let mut builder = Rkv::environment_builder::<BackendImpl>();
+            builder.set_flags(EnvironmentFlags::NO_TLS);
+            let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+            let sk = k.open_single(None, StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+            writer.commit().expect("committed");
+
+            let outer = k.read().expect("reader");
+            assert_eq!(sk.get(&outer, "foo").expect("read"), Some(Value::I64(1)));
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(2)).expect("wrote");
+            writer.commit().expect("committed");
+
+            // A nested reader in the same thread sees the latest commit, while the outer one
+            // keeps its snapshot.
+            {
+                let inner = k.read().expect("nested reader");
+                assert_eq!(sk.get(&inner, "foo").expect("read"), Some(Value::I64(2)));
+            }
+            assert_eq!(sk.get(&outer, "foo").expect("read"), Some(Value::I64(1)));
+        }
+
+        #[test]
+        fn test_reader_renew() {
+            let root = Builder::new()
+                .prefix("test_reader_renew")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+            let other = k.open_single("other", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+            writer.commit().expect("committed");
+
+            // Helpers read from the handler's reader rather than opening their own.
+            let read_foo =
+                |reader: &_| sk.get(reader, "foo").expect("read").map(|v| OwnedValue::from(&v));
+            let mut reader = k.read().expect("reader");
+            assert_eq!(read_foo(&reader), Some(OwnedValue::I64(1)));
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(2)).expect("wrote");
+            writer.commit().expect("committed");
+            let mut writer = k.write().expect("writer");
+            other.put(&mut writer, "bar", &Value::I64(3)).expect("wrote");
+            writer.commit().expect("committed");
+
+            // The reader keeps its snapshot until it's renewed, then sees the latest commit.
+            assert_eq!(read_foo(&reader), Some(OwnedValue::I64(1)));
+            assert_eq!(other.get(&reader, "bar").expect("read"), None);
+            reader.renew().expect("renewed");
+            assert_eq!(read_foo(&reader), Some(OwnedValue::I64(2)));
+            assert_eq!(other.get(&reader, "bar").expect("read"), Some(Value::I64(3)));
+            reader.renew().expect("renewed");
+            assert_eq!(read_foo(&reader), Some(OwnedValue::I64(2)));
+        }
+
+        #[test]
+        fn test_rename_store() {
+            let root = Builder::new()
+                .prefix("test_rename_store")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            {
+                let k = Rkv::with_capacity::<BackendImpl>(root.path(), 3).expect("rkv");
+                let sk = k
+                    .open_single("cache_v1", StoreOptions::create())
+                    .expect("opened");
+                k.open_single("other", StoreOptions::create())
+                    .expect("opened");
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote");
+                sk.put(&mut writer, "bar", &Value::Str("baz"))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+
+                k.rename_store("cache_v1", "cache").expect("renamed");
+                match k.rename_store("cache", "other") {
+                    Err(StoreError::KeyValuePairExists) => {}
+                    result => panic!("expected KeyValuePairExists, got {:?}", result),
+                }
+            }
+
+            let k = Rkv::with_capacity::<BackendImpl>(root.path(), 3).expect("rkv");
+            let mut dbs = k.get_dbs().expect("dbs");
+            dbs.sort();
+            assert_eq!(
+                dbs,
+                vec![Some("cache".to_owned()), Some("other".to_owned())]
+            );
+            assert!(k.open_single("cache_v1", StoreOptions::default()).is_err());
+
+            let sk = k
+                .open_single("cache", StoreOptions::default())
+                .expect("opened");
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                sk.get(&reader, "foo").expect("read"),
+                Some(Value::I64(1234))
+            );
+            assert_eq!(
+                sk.get(&reader, "bar").expect("read"),
+                Some(Value::Str("baz"))
+            );
+        }
+
+        #[test]
+        fn test_rename_store_changelog() {
+            let root = Builder::new()
+                .prefix("test_rename_store_changelog")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let mut opts = StoreOptions::create();
+            opts.changelog = true;
+            let sk = k.open_single("sk_v1", opts).expect("opened");
+            {
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+                sk.put(&mut writer, "bar", &Value::I64(2)).expect("wrote");
+                writer.commit().expect("committed");
+            }
+            let generation = sk
+                .generation(&k.read().expect("reader"))
+                .expect("generation");
+            assert_eq!(generation, 2);
+
+            k.rename_store("sk_v1", "sk").expect("renamed");
+            let mut dbs = k.get_dbs().expect("dbs");
+            dbs.sort();
+            assert_eq!(
+                dbs,
+                vec![Some("sk".to_owned()), Some("sk.changelog".to_owned())]
+            );
+
+            // The changelog carries on under the new name.
+            opts.create = false;
+            let sk = k.open_single("sk", opts).expect("opened");
+            {
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "baz", &Value::I64(3)).expect("wrote");
+                writer.commit().expect("committed");
+            }
+            let reader = k.read().expect("reader");
+            assert_eq!(sk.generation(&reader).expect("generation"), 3);
+            assert_eq!(
+                sk.changes_since(&reader, generation).expect("changes"),
+                vec![Change {
+                    generation: 2,
+                    op: ChangeOp::Put,
+                    key: b"baz".to_vec(),
+                }]
+            );
+        }
+
+        #[test]
+        fn test_drop_store_changelog() {
+            let root = Builder::new()
+                .prefix("test_drop_store_changelog")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("new succeeded");
+            let mut opts = StoreOptions::create();
+            opts.changelog = true;
+            let sk = k.open_single("sk", opts).expect("opened");
+            {
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+                writer.commit().expect("committed");
+            }
+
+            k.drop_store("sk").expect("dropped");
+            assert_eq!(k.get_dbs().expect("dbs"), vec![]);
+
+            // A store created again under the same name starts a new changelog.
+            let sk = k.open_single("sk", opts).expect("opened");
+            {
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "bar", &Value::I64(2)).expect("wrote");
+                writer.commit().expect("committed");
+            }
+            let reader = k.read().expect("reader");
+            assert_eq!(sk.generation(&reader).expect("generation"), 1);
+            assert_eq!(
+                sk.changes_since(&reader, 0).expect("changes"),
+                vec![Change {
+                    generation: 0,
+                    op: ChangeOp::Put,
+                    key: b"bar".to_vec(),
+                }]
+            );
+        }
+
+        #[test]
+        fn test_drop_store() {
+            let root = Builder::new()
+                .prefix("test_drop_store")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            {
+                let k = Rkv::with_capacity::<BackendImpl>(root.path(), 2).expect("rkv");
+                let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+                k.open_single("other", StoreOptions::create())
+                    .expect("opened");
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1234))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+
+                k.drop_store("sk").expect("dropped");
+                assert_eq!(k.get_dbs().expect("dbs"), vec![Some("other".to_owned())]);
+                assert!(k.drop_store("sk").is_err());
+
+                // The dropped store's slot can be reused.
+                k.open_single("another", StoreOptions::create())
+                    .expect("opened");
+                k.drop_store("another").expect("dropped");
+            }
+
+            let k = Rkv::with_capacity::<BackendImpl>(root.path(), 2).expect("rkv");
+            assert_eq!(k.get_dbs().expect("dbs"), vec![Some("other".to_owned())]);
+            assert!(k.open_single("sk", StoreOptions::default()).is_err());
+
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+            let reader = k.read().expect("reader");
+            assert_eq!(sk.get(&reader, "foo").expect("read"), None);
+        }
+
+        #[test]
+        fn test_try_write() {
+            let root = Builder::new()
+                .prefix("test_try_write")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1234))
+                .expect("wrote");
+            assert!(k.try_write().expect("tried").is_none());
+            writer.commit().expect("committed");
+
+            let mut writer = k.try_write().expect("tried").expect("writer");
+            assert!(k.try_write().expect("tried").is_none());
+            sk.put(&mut writer, "bar", &Value::I64(5678))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                sk.get(&reader, "foo").expect("read"),
+                Some(Value::I64(1234))
+            );
+            assert_eq!(
+                sk.get(&reader, "bar").expect("read"),
+                Some(Value::I64(5678))
+            );
+        }
+
+        #[test]
+        fn test_write_timeout() {
+            let root = Builder::new()
+                .prefix("test_write_timeout")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Arc::new(Rkv::new::<BackendImpl>(root.path()).expect("rkv"));
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let writer = k.write().expect("writer");
+            match k.write_timeout(Duration::from_millis(10)) {
+                Err(StoreError::WriteTimeout) => {}
+                result => panic!("expected WriteTimeout, got {:?}", result.map(|_| ())),
+            }
+
+            // A writer that's released in time is waited for.
+            let waiter = {
+                let k = k.clone();
+                thread::spawn(move || {
+                    let mut writer = k.write_timeout(Duration::from_secs(60)).expect("writer");
+                    sk.put(&mut writer, "foo", &Value::I64(1234))
+                        .expect("wrote");
+                    writer.commit().expect("committed");
+                })
+            };
+            thread::sleep(Duration::from_millis(10));
+            writer.abort();
+            waiter.join().expect("joined");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                sk.get(&reader, "foo").expect("read"),
+                Some(Value::I64(1234))
+            );
+        }
+
+        #[test]
+        fn test_open_all_single() {
+            let root = Builder::new()
+                .prefix("test_open_all_single")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::with_capacity::<BackendImpl>(root.path(), 3).expect("rkv");
+            for name in ["s1", "s2", "s3"] {
+                let store = k.open_single(name, StoreOptions::create()).expect("opened");
+                let mut writer = k.write().expect("writer");
+                store
+                    .put(&mut writer, "name", &Value::Str(name))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+            }
+
+            let mut stores = k.open_all_single().expect("opened");
+            stores.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let reader = k.read().expect("reader");
+            let names: Vec<_> = stores
+                .iter()
+                .map(|(name, store)| {
+                    let value = store.get(&reader, "name").expect("read");
+                    (name.as_deref(), value)
+                })
+                .collect();
+            assert_eq!(
+                names,
+                vec![
+                    (Some("s1"), Some(Value::Str("s1"))),
+                    (Some("s2"), Some(Value::Str("s2"))),
+                    (Some("s3"), Some(Value::Str("s3"))),
+                ]
+            );
+        }
+
+        #[test]
+        #[cfg(feature = "db-dup-sort")]
+        fn test_open_all_single_dup_sort() {
+            let root = Builder::new()
+                .prefix("test_open_all_single_dup_sort")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::with_capacity::<BackendImpl>(root.path(), 2).expect("rkv");
+            let single = k.open_single("single", StoreOptions::create()).expect("opened");
+            let multi = k.open_multi("multi", StoreOptions::create()).expect("opened");
+            let mut writer = k.write().expect("writer");
+            single.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+            multi.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+            multi.put(&mut writer, "foo", &Value::I64(2)).expect("wrote");
+            writer.commit().expect("committed");
+
+            // Databases with several values per key aren't opened as single stores...
+            let stores = k.open_all_single().expect("opened");
+            let names: Vec<_> = stores.iter().map(|(name, _)| name.as_deref()).collect();
+            assert_eq!(names, [Some("single")]);
+
+            // ...but they're still cleared along with the others.
+            k.clear_all().expect("cleared");
+            let reader = k.read().expect("reader");
+            assert_eq!(single.get(&reader, "foo").expect("read"), None);
+            assert_eq!(multi.iter_start(&reader).expect("iter").count(), 0);
+        }
+
+        #[test]
+        fn test_page() {
+            let root = Builder::new()
+                .prefix("test_page")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            for i in 0..10u64 {
+                sk.put(&mut writer, i.to_be_bytes(), &Value::U64(i))
+                    .expect("wrote");
+            }
+            writer.commit().expect("committed");
+
+            let mut pairs = vec![];
+            let mut pages = 0;
+            let mut after = None;
+            loop {
+                let reader = k.read().expect("reader");
+                let (page, next) = sk.page(&reader, after.as_deref(), 3).expect("paged");
+                assert!(page.len() <= 3);
+                pairs.extend(page);
+                pages += 1;
+                match next {
+                    Some(next) => after = Some(next),
+                    None => break,
+                }
+            }
+            assert_eq!(pages, 4);
+            let expected: Vec<_> = (0..10u64)
+                .map(|i| (i.to_be_bytes().to_vec(), OwnedValue::U64(i)))
+                .collect();
+            assert_eq!(pairs, expected);
+
+            // A page that ends with the last pair doesn't return a next key.
+            let reader = k.read().expect("reader");
+            let (pairs, next) = sk
+                .page(&reader, Some(&6u64.to_be_bytes()), 3)
+                .expect("paged");
+            assert_eq!(pairs.len(), 3);
+            assert_eq!(next, None);
+            let (pairs, next) = sk.page(&reader, None, 0).expect("paged");
+            assert!(pairs.is_empty());
+            assert_eq!(next, None);
+        }
+
+        #[test]
+        fn test_iter_prefix_composite() {
+            let root = Builder::new()
+                .prefix("test_iter_prefix_composite")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let key = |user: &str, session: &str| CompositeKey::new().push(user).push(session);
+            let mut writer = k.write().expect("writer");
+            // With "user:a" as a prefix, "user:ab:1" would match too, but these don't share
+            // the prefix of the user "a".
+            for (user, session) in [("a", "1"), ("a", "2"), ("ab", "1"), ("b", "1"), ("", "a")] {
+                let value = format!("{}:{}", user, session);
+                sk.put(&mut writer, key(user, session), &Value::Str(&value))
+                    .expect("wrote");
+            }
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            let prefix = CompositeKey::new().push("a");
+            let pairs: Vec<_> = sk
+                .iter_prefix(&reader, &prefix)
+                .expect("iter")
+                .map(|pair| {
+                    let (key, value) = pair.expect("pair");
+                    (CompositeKey::split(key).expect("split"), value)
+                })
+                .collect();
+            assert_eq!(
+                pairs,
+                vec![
+                    (vec![&b"a"[..], b"1"], Value::Str("a:1")),
+                    (vec![&b"a"[..], b"2"], Value::Str("a:2")),
+                ]
+            );
+
+            let mut iter = sk.iter_prefix(&reader, key("ab", "1")).expect("iter");
+            assert_eq!(
+                iter.next().expect("pair").expect("pair").1,
+                Value::Str("ab:1")
+            );
+            assert!(iter.next().is_none());
+            assert!(sk
+                .iter_prefix(&reader, CompositeKey::new().push("c"))
+                .expect("iter")
+                .next()
+                .is_none());
+        }
+
+        #[test]
+        fn test_iter_prefix() {
+            let root = Builder::new()
+                .prefix("test_iter_prefix")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let keys: [&[u8]; 7] = [
+                b"a",
+                b"ab",
+                b"b",
+                &[0xfe, 0xff],
+                &[0xff],
+                &[0xff, 0xff],
+                &[0xff, 0xff, 0x01],
+            ];
+            let mut writer = k.write().expect("writer");
+            for (i, key) in keys.iter().enumerate() {
+                sk.put(&mut writer, key, &Value::U64(i as u64))
+                    .expect("wrote");
+            }
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            let scan = |prefix: &[u8]| -> Vec<Vec<u8>> {
+                sk.iter_prefix(&reader, prefix)
+                    .expect("iter")
+                    .map(|pair| pair.expect("pair").0.to_vec())
+                    .collect()
+            };
+            assert_eq!(scan(b"a"), vec![b"a".to_vec(), b"ab".to_vec()]);
+            assert_eq!(scan(b"ab"), vec![b"ab".to_vec()]);
+            assert_eq!(scan(b"abc"), Vec::<Vec<u8>>::new());
+            assert_eq!(scan(&[0xfe]), vec![vec![0xfe, 0xff]]);
+            // There's no key after all the keys with a prefix of `0xff` bytes to stop at.
+            assert_eq!(
+                scan(&[0xff]),
+                vec![vec![0xff], vec![0xff, 0xff], vec![0xff, 0xff, 0x01]]
+            );
+            assert_eq!(
+                scan(&[0xff, 0xff]),
+                vec![vec![0xff, 0xff], vec![0xff, 0xff, 0x01]]
+            );
+            assert_eq!(scan(&[0xff, 0xff, 0xff]), Vec::<Vec<u8>>::new());
+            assert_eq!(
+                scan(b""),
+                keys.iter().map(|key| key.to_vec()).collect::<Vec<_>>()
+            );
+        }
+
+        #[test]
+        #[cfg(feature = "db-dup-sort")]
+        fn test_store_flags() {
+            use rkv::{backend::BackendDatabaseFlags, DatabaseFlags};
+
+            let root = Builder::new()
+                .prefix("test_store_flags")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+
+            let mut opts = StoreOptions::<DatabaseFlagsImpl>::create();
+            BackendDatabaseFlags::set(&mut opts.flags, DatabaseFlags::DUP_SORT, true);
+            let dup_sort = k.open_single("dup_sort", opts).expect("opened");
+            let plain = k
+                .open_single("plain", StoreOptions::create())
+                .expect("opened");
+
+            let reader = k.read().expect("reader");
+            let flags = dup_sort.flags(&reader).expect("flags");
+            assert!(flags.get(DatabaseFlags::DUP_SORT));
+            assert!(!plain
+                .flags(&reader)
+                .expect("flags")
+                .get(DatabaseFlags::DUP_SORT));
+        }
+
+        #[test]
+        fn test_open_default() {
+            let root = Builder::new()
+                .prefix("test_open_default")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+
+            let created = k.create_default().expect("created");
+            let mut writer = k.write().expect("writer");
+            created
+                .put(&mut writer, "foo", &Value::I64(1234))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            let opened = k.open_default(StoreOptions::default()).expect("opened");
+            let single = k
+                .open_single(None, StoreOptions::default())
+                .expect("opened");
+            assert_eq!(opened, single);
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                opened.get(&reader, "foo").expect("read"),
+                Some(Value::I64(1234))
+            );
+            assert_eq!(
+                single.get(&reader, "foo").expect("read"),
+                Some(Value::I64(1234))
+            );
+        }
+
+        #[test]
+        fn test_retain() {
+            let root = Builder::new()
+                .prefix("test_retain")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            for i in 0..10u64 {
+                sk.put(&mut writer, i.to_be_bytes(), &Value::U64(i))
+                    .expect("wrote");
+            }
+            let deleted = sk
+                .retain(&mut writer, |_, v| matches!(v, Value::U64(i) if i % 2 == 0))
+                .expect("retained");
+            assert_eq!(deleted, 5);
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            let values: Vec<_> = sk
+                .iter_start(&reader)
+                .expect("iter")
+                .map(|pair| pair.expect("pair").1)
+                .collect();
+            assert_eq!(
+                values,
+                [0u64, 2, 4, 6, 8]
+                    .iter()
+                    .map(|i| Value::U64(*i))
+                    .collect::<Vec<_>>()
+            );
+            reader.abort();
+
+            let mut writer = k.write().expect("writer");
+            assert_eq!(sk.retain(&mut writer, |_, _| true).expect("retained"), 0);
+            assert_eq!(sk.retain(&mut writer, |_, _| false).expect("retained"), 5);
+            assert_eq!(sk.iter_start(&writer).expect("iter").count(), 0);
+
+            // Nothing is deleted if a value can't be decoded, even one after pairs that
+            // would have been.
+            sk.put(&mut writer, "a", &Value::U64(1)).expect("wrote");
+            sk.put_raw(&mut writer, "b", &[0xff]).expect("wrote");
+            assert!(matches!(
+                sk.retain(&mut writer, |_, _| false),
+                Err(StoreError::DataError(_))
+            ));
+            assert_eq!(sk.get(&writer, "a").expect("read"), Some(Value::U64(1)));
+            assert_eq!(sk.get_raw(&writer, "b").expect("read"), Some(&[0xff][..]));
+        }
+
+        #[test]
+        fn test_disk_size() {
+            let root = Builder::new()
+                .prefix("test_disk_size")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+            let before = k.disk_size().expect("disk size");
+
+            let blob = vec![0xab; 256 * 1024];
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "blob", &Value::Blob(&blob))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            let after = k.disk_size().expect("disk size");
+            assert!(after >= before + blob.len() as u64);
+        }
+
+        #[test]
+        fn test_nested_write() {
+            let root = Builder::new()
+                .prefix("test_nested_write")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+
+            // Aborting a nested writer only discards its own changes.
+            let mut nested = writer.begin_nested().expect("nested");
+            assert_eq!(sk.get(&nested, "foo").expect("read"), Some(Value::I64(1)));
+            sk.put(&mut nested, "foo", &Value::I64(2)).expect("wrote");
+            sk.put(&mut nested, "bar", &Value::I64(2)).expect("wrote");
+            nested.abort();
+            assert_eq!(sk.get(&writer, "foo").expect("read"), Some(Value::I64(1)));
+            assert_eq!(sk.get(&writer, "bar").expect("read"), None);
+
+            // Committing one folds its changes into the parent, including those of its own
+            // nested writers.
+            let mut nested = writer.begin_nested().expect("nested");
+            sk.put(&mut nested, "baz", &Value::I64(3)).expect("wrote");
+            let mut inner = nested.begin_nested().expect("nested");
+            sk.delete(&mut inner, "foo").expect("deleted");
+            inner.commit().expect("committed");
+            let mut inner = nested.begin_nested().expect("nested");
+            sk.put(&mut inner, "qux", &Value::I64(4)).expect("wrote");
+            drop(inner);
+            nested.commit().expect("committed");
+            assert_eq!(sk.get(&writer, "foo").expect("read"), None);
+            assert_eq!(sk.get(&writer, "baz").expect("read"), Some(Value::I64(3)));
+            assert_eq!(sk.get(&writer, "qux").expect("read"), None);
+
+            // Nothing is written until the outermost writer commits.
+            let mut nested = writer.begin_nested().expect("nested");
+            sk.put(&mut nested, "bar", &Value::I64(5)).expect("wrote");
+            nested.commit().expect("committed");
+            writer.abort();
+            let reader = k.read().expect("reader");
+            assert_eq!(sk.iter_start(&reader).expect("iter").count(), 0);
+            reader.abort();
+
+            let mut writer = k.write().expect("writer");
+            let mut nested = writer.begin_nested().expect("nested");
+            sk.put(&mut nested, "bar", &Value::I64(6)).expect("wrote");
+            nested.commit().expect("committed");
+            writer.commit().expect("committed");
+            let reader = k.read().expect("reader");
+            assert_eq!(sk.get(&reader, "bar").expect("read"), Some(Value::I64(6)));
+        }
+
+        #[test]
+        fn test_iter_start_owned() {
+            let root = Builder::new()
+                .prefix("test_iter_start_owned")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+            sk.put(&mut writer, "bar", &Value::Str("baz"))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            let pairs = sk
+                .iter_start_owned(&reader)
+                .expect("iter")
+                .collect::<Result<Vec<_>, _>>()
+                .expect("pairs");
+            reader.abort();
+
+            // The reader is gone, and the store can even change, but the pairs are still there.
+            let mut writer = k.write().expect("writer");
+            sk.clear(&mut writer).expect("cleared");
+            writer.commit().expect("committed");
+            assert_eq!(
+                pairs,
+                vec![
+                    (b"bar".to_vec(), OwnedValue::Str("baz".to_string())),
+                    (b"foo".to_vec(), OwnedValue::I64(1)),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_put_owned() {
+            let root = Builder::new()
+                .prefix("test_put_owned")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::Str("bar"))
+                .expect("wrote");
+
+            // Converting the value ends its borrow of the writer, so it can be written back.
+            let mut value = sk
+                .get(&writer, "foo")
+                .expect("read")
+                .map(|v| OwnedValue::from(&v))
+                .expect("value");
+            if let OwnedValue::Str(s) = &mut value {
+                s.push('…');
+            }
+            sk.put_owned(&mut writer, "foo", &value).expect("wrote");
+            sk.put_owned(&mut writer, "copy", &value).expect("wrote");
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                sk.get(&reader, "foo").expect("read"),
+                Some(Value::Str("bar…"))
+            );
+            assert_eq!(
+                sk.get(&reader, "copy").expect("read"),
+                Some(Value::Str("bar…"))
+            );
+        }
+
+        #[test]
+        fn test_writer_extend() {
+            let root = Builder::new()
+                .prefix("test_writer_extend")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "stale", &Value::I64(0)).expect("wrote");
+            writer
+                .extend(
+                    &sk,
+                    vec![
+                        Op::Clear,
+                        Op::Put("foo", Value::I64(1)),
+                        Op::Put("bar", Value::Str("baz")),
+                        Op::Put("foo", Value::I64(2)),
+                        Op::Delete("bar"),
+                        Op::Put("qux", Value::Bool(true)),
+                    ],
+                )
+                .expect("extended");
+
+            // The batch is visible to the writer, in order, before it's committed.
+            assert_eq!(sk.get(&writer, "stale").expect("read"), None);
+            assert_eq!(sk.get(&writer, "foo").expect("read"), Some(Value::I64(2)));
+            assert_eq!(sk.get(&writer, "bar").expect("read"), None);
+            {
+                let reader = k.read().expect("reader");
+                assert_eq!(sk.get(&reader, "foo").expect("read"), None);
+            }
+
+            // A failing operation stops the batch, leaving the earlier ones applied.
+            let result = writer.extend(
+                &sk,
+                vec![
+                    Op::Put("bar", Value::I64(3)),
+                    Op::Delete("missing"),
+                    Op::Put("baz", Value::I64(4)),
+                ],
+            );
+            assert!(matches!(result, Err(StoreError::KeyValuePairNotFound)));
+            assert_eq!(sk.get(&writer, "bar").expect("read"), Some(Value::I64(3)));
+            assert_eq!(sk.get(&writer, "baz").expect("read"), None);
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            let pairs: Vec<_> = sk
+                .iter_start(&reader)
+                .expect("iter")
+                .map(|pair| pair.expect("pair"))
+                .collect();
+            assert_eq!(
+                pairs,
+                vec![
+                    (&b"bar"[..], Value::I64(3)),
+                    (&b"foo"[..], Value::I64(2)),
+                    (&b"qux"[..], Value::Bool(true)),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_max_value_size() {
+            let root = Builder::new()
+                .prefix("test_max_value_size")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+
+            // Values aren't limited by default.
+            {
+                let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+                let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "large", &Value::Blob(&[0; 100]))
+                    .expect("wrote");
+                sk.put(&mut writer, "small", &Value::Str("foo"))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+            }
+
+            let mut builder = Rkv::environment_builder::<BackendImpl>();
+            builder.set_max_dbs(2);
+            builder.set_max_value_size(64);
+            let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+            let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+            // A tagged blob of 100 bytes takes 109: the tag, its length, then the bytes.
+            let mut writer = k.write().expect("writer");
+            assert!(matches!(
+                sk.put(&mut writer, "other", &Value::Blob(&[0; 100])),
+                Err(StoreError::DataError(DataError::ValueTooLarge {
+                    size: 109,
+                    max: 64
+                }))
+            ));
+            sk.put(&mut writer, "other", &Value::Blob(&[0; 50]))
+                .expect("wrote");
+            writer.commit().expect("committed");
+
+            // Values already stored are checked before they're decoded.
+            let reader = k.read().expect("reader");
+            assert!(matches!(
+                sk.get(&reader, "large"),
+                Err(StoreError::DataError(DataError::ValueTooLarge {
+                    size: 109,
+                    max: 64
+                }))
+            ));
+            assert_eq!(
+                sk.get(&reader, "small").expect("read"),
+                Some(Value::Str("foo"))
+            );
+            let mut iter = sk.iter_start(&reader).expect("iter");
+            assert!(matches!(
+                iter.next(),
+                Some(Err(StoreError::DataError(DataError::ValueTooLarge { .. })))
+            ));
+            assert_eq!(
+                iter.next().map(|pair| pair.expect("pair").0),
+                Some(&b"other"[..])
+            );
+            assert_eq!(
+                iter.next().map(|pair| pair.expect("pair")),
+                Some((&b"small"[..], Value::Str("foo")))
+            );
+            assert!(iter.next().is_none());
+        }
+
+        #[test]
+        fn test_open_single_created() {
+            let root = Builder::new()
+                .prefix("test_open_single_created")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+
+            let (sk, created) = k
+                .open_single_created("sk", StoreOptions::create())
+                .expect("opened");
+            assert!(created);
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+            writer.commit().expect("committed");
+
+            let (sk, created) = k
+                .open_single_created("sk", StoreOptions::create())
+                .expect("opened");
+            assert!(!created);
+            let (_, created) = k
+                .open_single_created("sk", StoreOptions::default())
+                .expect("opened");
+            assert!(!created);
+            let reader = k.read().expect("reader");
+            assert_eq!(sk.get(&reader, "foo").expect("read"), Some(Value::I64(1)));
+        }
+
+        #[test]
+        fn test_iter_start_lossy() {
+            let root = Builder::new()
+                .prefix("test_iter_start_lossy")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            {
+                let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+                let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "a", &Value::I64(1)).expect("wrote");
+                sk.put(&mut writer, "c", &Value::Str("foo")).expect("wrote");
+                writer.commit().expect("committed");
+            }
+
+            // Store a value that can't be decoded, bypassing the typed API.
+            {
+                let env = BackendImpl::new().set_max_dbs(1).open(root.path()).expect("env");
+                let db = env.open_db(Some("sk")).expect("opened");
+                let mut txn = env.begin_rw_txn().expect("txn");
+                txn.put(&db, b"b", &[0xff], WriteFlagsImpl::empty())
+                    .expect("wrote");
+                txn.commit().expect("committed");
+            }
+
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let sk = k
+                .open_single("sk", StoreOptions::default())
+                .expect("opened");
+            let reader = k.read().expect("reader");
+
+            // The typed iterator yields an error for the corrupt value.
+            let results: Vec<_> = sk.iter_start(&reader).expect("iter").collect();
+            assert!(matches!(
+                results[1],
+                Err(StoreError::DataError(DataError::UnknownType(0xff)))
+            ));
+
+            let mut good = vec![];
+            let mut bad = vec![];
+            for entry in sk.iter_start_lossy(&reader).expect("iter") {
+                match entry.expect("entry") {
+                    (key, Ok(value)) => good.push((key, value)),
+                    (key, Err(err)) => bad.push((key, err)),
+                }
+            }
+            assert_eq!(
+                good,
+                vec![(&b"a"[..], Value::I64(1)), (&b"c"[..], Value::Str("foo"))]
+            );
+            assert!(matches!(bad[..], [(b"b", DataError::UnknownType(0xff))]));
+        }
+
+        #[test]
+        fn test_writemap() {
+            let root = Builder::new()
+                .prefix("test_writemap")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let open = || {
+                let mut builder = Rkv::environment_builder::<BackendImpl>();
+                builder
+                    .set_max_dbs(2)
+                    .set_writemap(true)
+                    .set_map_async(true);
+                Rkv::from_builder(root.path(), builder).expect("rkv")
+            };
+
+            {
+                let k = open();
+                let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+                let mut writer = k.write().expect("writer");
+                sk.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+                sk.put(&mut writer, "bar", &Value::Blob(&[7; 4096]))
+                    .expect("wrote");
+                writer.commit().expect("committed");
+                k.sync(true).expect("synced");
+
+                let reader = k.read().expect("reader");
+                assert_eq!(sk.get(&reader, "foo").expect("read"), Some(Value::I64(1)));
+            }
+
+            let k = open();
+            let sk = k
+                .open_single("sk", StoreOptions::default())
+                .expect("opened");
+            let reader = k.read().expect("reader");
+            assert_eq!(sk.get(&reader, "foo").expect("read"), Some(Value::I64(1)));
+            assert_eq!(
+                sk.get(&reader, "bar").expect("read"),
+                Some(Value::Blob(&[7; 4096]))
+            );
+        }
+
+        #[test]
+        fn test_default_write_flags() {
+            let root = Builder::new()
+                .prefix("test_default_write_flags")
+                .tempdir()
+                .expect("tempdir");
+            fs::create_dir_all(root.path()).expect("dir created");
+            let k = Rkv::new::<BackendImpl>(root.path()).expect("rkv");
+            let opts = StoreOptions {
+                default_write_flags: Some(WriteFlags::NO_OVERWRITE),
+                ..StoreOptions::create()
+            };
+            let sk = k.open_single("sk", opts).expect("opened");
+
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1)).expect("wrote");
+            assert!(matches!(
+                sk.put(&mut writer, "foo", &Value::I64(2)),
+                Err(StoreError::KeyValuePairExists)
+            ));
+            assert!(matches!(
+                writer.extend(&sk, vec![Op::Put("foo", Value::I64(3))]),
+                Err(StoreError::KeyValuePairExists)
+            ));
+            // Every other method that writes values applies them too.
+            assert!(matches!(
+                sk.put_many(&mut writer, vec![("foo", Value::I64(3))]),
+                Err(StoreError::KeyValuePairExists)
+            ));
+            assert!(matches!(
+                sk.put_raw(&mut writer, "foo", &[3]),
+                Err(StoreError::KeyValuePairExists)
+            ));
+            assert_eq!(sk.get(&writer, "foo").expect("read"), Some(Value::I64(1)));
+            sk.put_many(&mut writer, vec![("bar", Value::I64(1))])
+                .expect("wrote");
+
+            // Methods that replace values by design don't.
+            assert_eq!(
+                sk.put_get_old(&mut writer, "bar", &Value::I64(2))
+                    .expect("wrote"),
+                Some(OwnedValue::I64(1))
+            );
+            assert_eq!(sk.increment(&mut writer, "bar", 1).expect("incremented"), 3);
+            assert_eq!(sk.increment(&mut writer, "baz", 1).expect("incremented"), 1);
+            assert!(sk
+                .compare_and_swap(&mut writer, "baz", Some(&Value::I64(1)), &Value::I64(5))
+                .expect("swapped"));
+            assert!(!sk
+                .compare_and_swap(&mut writer, "baz", Some(&Value::I64(1)), &Value::I64(6))
+                .expect("compared"));
+            assert_eq!(sk.get(&writer, "baz").expect("read"), Some(Value::I64(5)));
+
+            // Explicit flags override the defaults.
+            sk.put_with_flags(&mut writer, "foo", &Value::I64(4), WriteFlagsImpl::empty())
+                .expect("wrote");
+            assert_eq!(sk.get(&writer, "foo").expect("read"), Some(Value::I64(4)));
+            writer.commit().expect("committed");
+
+            // Other handles on the same database don't share the defaults.
+            let plain = k.open_single("sk", StoreOptions::create()).expect("opened");
+            let mut writer = k.write().expect("writer");
+            plain
+                .put(&mut writer, "foo", &Value::I64(5))
+                .expect("wrote");
+            writer.commit().expect("committed");
+        }
+    };
+}
+
+mod safe {
+    use super::*;
+    use rkv::backend::{SafeModeDatabaseFlags, SafeModeWriteFlags};
+
+    backend_tests!(SafeMode, SafeModeWriteFlags, SafeModeDatabaseFlags);
+}
+
+#[cfg(feature = "lmdb")]
+mod lmdb {
+    use super::*;
+    use rkv::backend::{BackendFlags, LmdbDatabaseFlags, LmdbWriteFlags};
+
+    backend_tests!(Lmdb, LmdbWriteFlags, LmdbDatabaseFlags);
+}
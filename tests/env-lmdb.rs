@@ -16,7 +16,7 @@ use std::{
     fs,
     path::Path,
     str,
-    sync::{Arc, RwLock},
+    sync::{mpsc, Arc, RwLock},
     thread,
 };
 
@@ -25,8 +25,8 @@ use tempfile::Builder;
 
 use rkv::{
     backend::{
-        BackendEnvironmentBuilder, BackendInfo, BackendStat, Lmdb, LmdbDatabase, LmdbEnvironment,
-        LmdbRwTransaction,
+        BackendEnvironmentBuilder, BackendInfo, BackendStat, Durability, Lmdb, LmdbDatabase,
+        LmdbEnvironment, LmdbRwTransaction,
     },
     EnvironmentFlags, Rkv, SingleStore, StoreError, StoreOptions, Value, Writer,
 };
@@ -361,7 +361,7 @@ fn test_exceed_map_size() {
 }
 
 #[test]
-#[should_panic(expected = "wrote: KeyValuePairBadSize")]
+#[should_panic(expected = "wrote: KeyTooLong { len: 512, max: 511 }")]
 fn test_exceed_key_size_limit() {
     let root = Builder::new()
         .prefix("test_exceed_key_size_limit")
@@ -1132,6 +1132,7 @@ fn test_load_ratio() {
     writer.commit().expect("commited");
     let after_clear_ratio = k.load_ratio().expect("ratio").unwrap();
     assert!(after_clear_ratio < new_ratio);
+    assert!(k.freelist().expect("freelist") > 0);
 }
 
 #[test]
@@ -1529,7 +1530,7 @@ fn test_store_multiple_thread() {
         .into_iter()
         .map(|handle| handle.join().expect("value"))
         .sum();
-    assert_eq!(thread_sum, (0..num_threads).sum());
+    assert_eq!(thread_sum, (0..num_threads).sum::<u64>());
 }
 
 #[test]
@@ -1619,7 +1620,7 @@ fn test_use_value_as_key() {
     // And you need to copy the values to an owned type so the Writer isn't
     // still being borrowed by a retrieved value when you try to borrow
     // the Writer again to modify another value.
-    let fields = vec![
+    let fields = [
         (
             rkv.open_single("name1", StoreOptions::create())
                 .expect("opened"),
@@ -1645,3 +1646,637 @@ fn test_use_value_as_key() {
         writer.commit().expect("committed");
     }
 }
+
+#[test]
+fn test_single_store_stat() {
+    let root = Builder::new()
+        .prefix("test_single_store_stat")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::new::<Lmdb>(root.path()).expect("new succeeded");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    let other = k
+        .open_single("other", StoreOptions::create())
+        .expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    for i in 0..10u64 {
+        sk.put(&mut writer, i.to_be_bytes(), &Value::U64(i))
+            .expect("wrote");
+    }
+    other
+        .put(&mut writer, "foo", &Value::Bool(true))
+        .expect("wrote");
+    // Uncommitted writes are visible to the writer.
+    assert_eq!(sk.stat(&writer).expect("stat").entries, 10);
+    writer.commit().expect("committed");
+
+    let reader = k.read().expect("reader");
+    let stat = sk.stat(&reader).expect("stat");
+    assert_eq!(stat.entries, 10);
+    assert!(stat.size > 0);
+    assert_eq!(stat.depth, Some(1));
+    assert_eq!(other.stat(&reader).expect("stat").entries, 1);
+}
+
+#[test]
+fn test_durability() {
+    let root = Builder::new()
+        .prefix("test_durability")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    {
+        let mut builder = Rkv::environment_builder::<Lmdb>();
+        builder.set_max_dbs(1);
+        builder.set_durability(Durability::NoSync);
+        let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+        let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+        // Bulk-load with a commit per batch, and only sync at the end.
+        for batch in 0..10u64 {
+            let mut writer = k.write().expect("writer");
+            for i in 0..100 {
+                let n = batch * 100 + i;
+                sk.put(&mut writer, n.to_be_bytes(), &Value::U64(n))
+                    .expect("wrote");
+            }
+            writer.commit().expect("committed");
+        }
+        k.sync(true).expect("synced");
+    }
+
+    let k = Rkv::new::<Lmdb>(root.path()).expect("new succeeded");
+    let sk = k
+        .open_single("sk", StoreOptions::default())
+        .expect("opened");
+    let reader = k.read().expect("reader");
+    assert_eq!(sk.stat(&reader).expect("stat").entries, 1000);
+    assert_eq!(
+        sk.get(&reader, 999u64.to_be_bytes()).expect("read"),
+        Some(Value::U64(999))
+    );
+}
+
+#[test]
+fn test_auto_resize() {
+    let root = Builder::new()
+        .prefix("test_auto_resize")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let value = "x".repeat(1024);
+    let fill = |k: &Rkv<LmdbEnvironment>, sk: &SingleStore<LmdbDatabase>, from: u32| {
+        let mut writer = k.write().expect("writer");
+        for i in from..from + 1000 {
+            sk.put(&mut writer, i.to_be_bytes(), &Value::Str(&value))?;
+        }
+        writer.commit()
+    };
+
+    // Without auto-resizing, the caller has to deal with a full map.
+    {
+        let mut builder = Rkv::environment_builder::<Lmdb>();
+        builder.set_max_dbs(1).set_map_size(64 * 1024);
+        let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+        let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+        assert!(matches!(fill(&k, &sk, 0), Err(StoreError::MapFull)));
+    }
+
+    let mut builder = Rkv::environment_builder::<Lmdb>();
+    builder
+        .set_max_dbs(1)
+        .set_map_size(64 * 1024)
+        .set_auto_resize(true);
+    let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+    // A single transaction that outgrows the map several times over.
+    fill(&k, &sk, 0).expect("filled");
+    // And another one on top of it, with a delete to replay too.
+    let mut writer = k.write().expect("writer");
+    sk.delete(&mut writer, 0u32.to_be_bytes()).expect("deleted");
+    for i in 1000..3000u32 {
+        sk.put(&mut writer, i.to_be_bytes(), &Value::Str(&value))
+            .expect("wrote");
+    }
+    writer.commit().expect("committed");
+    assert!(k.info().expect("info").map_size() > 2 * 1024 * 1024);
+
+    let reader = k.read().expect("reader");
+    assert_eq!(sk.stat(&reader).expect("stat").entries, 2999);
+    assert_eq!(sk.get(&reader, 0u32.to_be_bytes()).expect("read"), None);
+    assert_eq!(
+        sk.get(&reader, 2999u32.to_be_bytes()).expect("read"),
+        Some(Value::Str(&value))
+    );
+}
+
+#[test]
+fn test_auto_resize_with_reader() {
+    let root = Builder::new()
+        .prefix("test_auto_resize_with_reader")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let mut builder = Rkv::environment_builder::<Lmdb>();
+    builder
+        .set_max_dbs(1)
+        .set_map_size(64 * 1024)
+        .set_auto_resize(true);
+    let k = Arc::new(Rkv::from_builder(root.path(), builder).expect("rkv"));
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+    let value = "x".repeat(1024);
+    let fill = || {
+        let mut writer = k.write().expect("writer");
+        for i in 0..1000u32 {
+            sk.put(&mut writer, i.to_be_bytes(), &Value::Str(&value))?;
+        }
+        writer.commit()
+    };
+
+    // The map can't grow while another thread reads.
+    let (read_tx, read_rx) = mpsc::channel();
+    let (done_tx, done_rx) = mpsc::channel::<()>();
+    let reader = {
+        let k = k.clone();
+        thread::spawn(move || {
+            let _reader = k.read().expect("reader");
+            read_tx.send(()).expect("sent");
+            done_rx.recv().expect("received");
+        })
+    };
+    read_rx.recv().expect("received");
+    assert!(matches!(fill(), Err(StoreError::MapFull)));
+    done_tx.send(()).expect("sent");
+    reader.join().expect("joined");
+
+    // Once it's done, it can.
+    fill().expect("filled");
+    let reader = k.read().expect("reader");
+    assert_eq!(sk.stat(&reader).expect("stat").entries, 1000);
+}
+
+#[test]
+fn test_env_info() {
+    let root = Builder::new()
+        .prefix("test_env_info")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let mut builder = Rkv::environment_builder::<Lmdb>();
+    builder.set_max_dbs(2);
+    builder.set_map_size(2 * 1024 * 1024);
+    let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    k.open_single("sk2", StoreOptions::create())
+        .expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, "foo", &Value::Str("bar"))
+        .expect("wrote");
+    writer.commit().expect("committed");
+
+    let info = k.env_info().expect("info");
+    assert_eq!(info.map_size, Some(2 * 1024 * 1024));
+    assert!(info.last_pgno.expect("last_pgno") > 0);
+    assert!(info.size > 0);
+    assert_eq!(info.max_readers, Some(126));
+    assert_eq!(info.num_dbs, 2);
+}
+
+#[test]
+fn test_environment_exists() {
+    let root = Builder::new()
+        .prefix("test_environment_exists")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    assert!(!Rkv::environment_exists::<Lmdb>(root.path()));
+
+    let k = Rkv::new::<Lmdb>(root.path()).expect("rkv");
+    // LMDB creates its data file when opening the environment.
+    assert!(Rkv::environment_exists::<Lmdb>(root.path()));
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, "foo", &Value::I64(1234))
+        .expect("wrote");
+    writer.commit().expect("committed");
+    assert!(Rkv::environment_exists::<Lmdb>(root.path()));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+fn test_store_foreign() {
+    let root_a = Builder::new()
+        .prefix("test_store_foreign_a")
+        .tempdir()
+        .expect("tempdir");
+    let root_b = Builder::new()
+        .prefix("test_store_foreign_b")
+        .tempdir()
+        .expect("tempdir");
+    let a = Rkv::new::<Lmdb>(root_a.path()).expect("rkv");
+    let b = Rkv::new::<Lmdb>(root_b.path()).expect("rkv");
+    // Both stores get the same LMDB handle, so LMDB itself couldn't tell them apart.
+    let sa = a.open_single("s", StoreOptions::create()).expect("opened");
+    let sb = b.open_single("s", StoreOptions::create()).expect("opened");
+
+    let mut writer = b.write().expect("writer");
+    assert!(matches!(
+        sa.put(&mut writer, "foo", &Value::I64(1)),
+        Err(StoreError::StoreForeign)
+    ));
+    sb.put(&mut writer, "foo", &Value::I64(2)).expect("wrote");
+    writer.commit().expect("committed");
+
+    let reader = b.read().expect("reader");
+    assert!(matches!(
+        sa.get(&reader, "foo"),
+        Err(StoreError::StoreForeign)
+    ));
+    assert_eq!(sb.get(&reader, "foo").expect("read"), Some(Value::I64(2)));
+    reader.abort();
+
+    let reader = a.read().expect("reader");
+    assert_eq!(sa.get(&reader, "foo").expect("read"), None);
+}
+
+#[test]
+fn test_key_too_long() {
+    let root = Builder::new()
+        .prefix("test_key_too_long")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let k = Rkv::new::<Lmdb>(root.path()).expect("rkv");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, [0; 511], &Value::I64(1))
+        .expect("wrote");
+    assert!(matches!(
+        sk.put(&mut writer, [0; 512], &Value::I64(2)),
+        Err(StoreError::KeyTooLong { len: 512, max: 511 })
+    ));
+    writer.commit().expect("committed");
+
+    let reader = k.read().expect("reader");
+    assert_eq!(
+        sk.get(&reader, [0; 511]).expect("read"),
+        Some(Value::I64(1))
+    );
+}
+
+#[test]
+fn test_nested_write_auto_resize() {
+    let root = Builder::new()
+        .prefix("test_nested_write_auto_resize")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let mut builder = Rkv::environment_builder::<Lmdb>();
+    builder
+        .set_max_dbs(1)
+        .set_map_size(64 * 1024)
+        .set_auto_resize(true);
+    let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    let value = "x".repeat(1024);
+
+    let mut writer = k.write().expect("writer");
+    let mut nested = writer.begin_nested().expect("nested");
+    sk.put(&mut nested, "committed", &Value::I64(1))
+        .expect("wrote");
+    nested.commit().expect("committed");
+    let mut nested = writer.begin_nested().expect("nested");
+    sk.put(&mut nested, "aborted", &Value::I64(2))
+        .expect("wrote");
+    nested.abort();
+    // Growing the map replays the changes of the committed nested writer, but not
+    // those of the aborted one.
+    for i in 0..1000u32 {
+        sk.put(&mut writer, i.to_be_bytes(), &Value::Str(&value))
+            .expect("wrote");
+    }
+    writer.commit().expect("committed");
+    assert!(k.info().expect("info").map_size() > 64 * 1024);
+
+    let reader = k.read().expect("reader");
+    assert_eq!(
+        sk.get(&reader, "committed").expect("read"),
+        Some(Value::I64(1))
+    );
+    assert_eq!(sk.get(&reader, "aborted").expect("read"), None);
+    assert_eq!(sk.stat(&reader).expect("stat").entries, 1001);
+}
+
+#[test]
+fn test_reader_cache() {
+    let root = Builder::new()
+        .prefix("test_reader_cache")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let k = Arc::new(Rkv::with_reader_cache(root.path(), 2).expect("rkv"));
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+    // Each read renews the transaction cached by the previous one, which must not
+    // hold on to its snapshot.
+    for i in 0..5 {
+        let mut writer = k.write().expect("writer");
+        sk.put(&mut writer, "foo", &Value::I64(i)).expect("wrote");
+        writer.commit().expect("committed");
+        let reader = k.read().expect("reader");
+        assert_eq!(sk.get(&reader, "foo").expect("read"), Some(Value::I64(i)));
+        reader.abort();
+        let reader = k.read().expect("reader");
+        assert_eq!(sk.get(&reader, "foo").expect("read"), Some(Value::I64(i)));
+    }
+
+    // Other threads get their own transactions.
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let k = k.clone();
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    let reader = k.read().expect("reader");
+                    assert_eq!(sk.get(&reader, "foo").expect("read"), Some(Value::I64(4)));
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().expect("joined");
+    }
+}
+
+#[test]
+fn test_reader_info() {
+    let root = Builder::new()
+        .prefix("test_reader_info")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let k = Rkv::new::<Lmdb>(root.path()).expect("rkv");
+    let _ = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+    assert_eq!(k.reader_info().expect("info").in_use, 0);
+    let reader = k.read().expect("reader");
+    assert_eq!(k.reader_info().expect("info").in_use, 1);
+    reader.abort();
+    assert_eq!(k.reader_info().expect("info").in_use, 0);
+    assert_eq!(k.check_stale_readers().expect("checked"), 0);
+    assert_eq!(k.reader_info().expect("info").max, Some(126));
+}
+
+#[test]
+fn test_get_dbs_dbs_full() {
+    let root = Builder::new()
+        .prefix("test_get_dbs_dbs_full")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    {
+        let k = Rkv::with_capacity::<Lmdb>(root.path(), 2).expect("rkv");
+        k.open_single("a", StoreOptions::create()).expect("opened");
+        k.open_single("b", StoreOptions::create()).expect("opened");
+        let default = k.create_default().expect("opened");
+        let mut writer = k.write().expect("writer");
+        default
+            .put(&mut writer, "plain", &Value::I64(1))
+            .expect("wrote");
+        writer.commit().expect("committed");
+
+        // Listing the databases again doesn't take more slots.
+        for _ in 0..3 {
+            assert_eq!(
+                k.get_dbs().expect("dbs"),
+                [Some("a".to_string()), Some("b".to_string())]
+            );
+        }
+    }
+
+    // Pairs whose values have the size of a database's record are opened to tell them
+    // apart from databases.
+    {
+        let k = Rkv::with_capacity::<Lmdb>(root.path(), 3).expect("rkv");
+        let default = k.open_default(StoreOptions::default()).expect("opened");
+        // Less the value's tag and the blob's length.
+        let record = [1; 8 + 5 * std::mem::size_of::<usize>() - 9];
+        let mut writer = k.write().expect("writer");
+        default
+            .put(&mut writer, "record", &Value::Blob(&record))
+            .expect("wrote");
+        writer.commit().expect("committed");
+        assert_eq!(
+            k.get_dbs().expect("dbs"),
+            [Some("a".to_string()), Some("b".to_string())]
+        );
+    }
+
+    // Without a slot for every database, they can't be listed.
+    let k = Rkv::with_capacity::<Lmdb>(root.path(), 1).expect("rkv");
+    assert!(matches!(k.get_dbs(), Err(StoreError::DbsFull)));
+}
+
+#[test]
+fn test_clear_all() {
+    let root = Builder::new()
+        .prefix("test_clear_all")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let k = Rkv::new::<Lmdb>(root.path()).expect("rkv");
+    let stores = [
+        k.open_single("a", StoreOptions::create()).expect("opened"),
+        k.open_single("b", StoreOptions::create()).expect("opened"),
+    ];
+    let default = k.create_default().expect("opened");
+    let mut writer = k.write().expect("writer");
+    for store in &stores {
+        store
+            .put(&mut writer, "foo", &Value::I64(1))
+            .expect("wrote");
+        store
+            .put(&mut writer, "bar", &Value::I64(2))
+            .expect("wrote");
+    }
+    // The default database's own pairs aren't listed as databases.
+    default
+        .put(&mut writer, "foo", &Value::I64(3))
+        .expect("wrote");
+    writer.commit().expect("committed");
+    let names = k.get_dbs().expect("dbs");
+    assert_eq!(names, [Some("a".to_string()), Some("b".to_string())]);
+
+    // They're cleared along with the other databases, whose records are kept.
+    k.clear_all().expect("cleared");
+    {
+        let reader = k.read().expect("reader");
+        for store in &stores {
+            assert_eq!(store.stat(&reader).expect("stat").entries, 0);
+        }
+        assert_eq!(default.get(&reader, "foo").expect("read"), None);
+    }
+    assert_eq!(k.get_dbs().expect("dbs"), names);
+    k.open_single("a", StoreOptions::default()).expect("opened");
+
+    // Without named databases, the default one is cleared.
+    let root = Builder::new()
+        .prefix("test_clear_all")
+        .tempdir()
+        .expect("tempdir");
+    let k = Rkv::from_builder(root.path(), Rkv::environment_builder::<Lmdb>()).expect("rkv");
+    let store = k.create_default().expect("opened");
+    let mut writer = k.write().expect("writer");
+    store
+        .put(&mut writer, "foo", &Value::I64(1))
+        .expect("wrote");
+    writer.commit().expect("committed");
+
+    k.clear_all().expect("cleared");
+    let reader = k.read().expect("reader");
+    assert_eq!(store.stat(&reader).expect("stat").entries, 0);
+    assert_eq!(k.get_dbs().expect("dbs"), [None]);
+}
+
+#[test]
+fn test_rename_store_stale() {
+    let root = Builder::new()
+        .prefix("test_rename_store_stale")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let k = Rkv::with_capacity::<Lmdb>(root.path(), 3).expect("rkv");
+    let old = k
+        .open_single("old", StoreOptions::create())
+        .expect("opened");
+    k.rename_store("old", "new").expect("renamed");
+
+    // LMDB hands the handle of the old store out again, here to `other`, which the
+    // old store must not write to.
+    let other = k
+        .open_single("other", StoreOptions::create())
+        .expect("opened");
+    let new = k
+        .open_single("new", StoreOptions::default())
+        .expect("opened");
+    let mut writer = k.write().expect("writer");
+    match old.put(&mut writer, "foo", &Value::I64(1)) {
+        Err(StoreError::StoreDropped) => {}
+        result => panic!("expected StoreDropped, got {:?}", result),
+    }
+    other
+        .put(&mut writer, "foo", &Value::I64(2))
+        .expect("wrote");
+    writer.commit().expect("committed");
+
+    let reader = k.read().expect("reader");
+    match old.get(&reader, "foo") {
+        Err(StoreError::StoreDropped) => {}
+        result => panic!("expected StoreDropped, got {:?}", result),
+    }
+    assert_eq!(
+        other.get(&reader, "foo").expect("read"),
+        Some(Value::I64(2))
+    );
+    assert_eq!(new.get(&reader, "foo").expect("read"), None);
+}
+
+#[test]
+fn test_drop_store_stale() {
+    let root = Builder::new()
+        .prefix("test_drop_store_stale")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let k = Rkv::with_capacity::<Lmdb>(root.path(), 1).expect("rkv");
+    let dropped = k
+        .open_single("dropped", StoreOptions::create())
+        .expect("opened");
+    k.drop_store("dropped").expect("dropped");
+
+    // With a single slot, the handle of the dropped store is the only one left.
+    let other = k
+        .open_single("other", StoreOptions::create())
+        .expect("opened");
+    let mut writer = k.write().expect("writer");
+    match dropped.put(&mut writer, "foo", &Value::I64(1)) {
+        Err(StoreError::StoreDropped) => {}
+        result => panic!("expected StoreDropped, got {:?}", result),
+    }
+    match dropped.clear(&mut writer) {
+        Err(StoreError::StoreDropped) => {}
+        result => panic!("expected StoreDropped, got {:?}", result),
+    }
+    other
+        .put(&mut writer, "foo", &Value::I64(2))
+        .expect("wrote");
+    writer.commit().expect("committed");
+
+    let reader = k.read().expect("reader");
+    match dropped.get(&reader, "foo") {
+        Err(StoreError::StoreDropped) => {}
+        result => panic!("expected StoreDropped, got {:?}", result),
+    }
+    assert_eq!(
+        other.get(&reader, "foo").expect("read"),
+        Some(Value::I64(2))
+    );
+}
+
+#[test]
+#[cfg(feature = "db-dup-sort")]
+fn test_reserve_dup_sort() {
+    use rkv::{
+        backend::{
+            BackendDatabaseFlags, BackendEnvironment, BackendFlags, BackendRwTransaction,
+            LmdbDatabaseFlags, LmdbWriteFlags,
+        },
+        DatabaseFlags,
+    };
+
+    let root = Builder::new()
+        .prefix("test_reserve_dup_sort")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    {
+        let env = Lmdb::new().set_max_dbs(1).open(root.path()).expect("env");
+        let mut flags = LmdbDatabaseFlags::empty();
+        flags.set(DatabaseFlags::DUP_SORT, true);
+        let db = env.create_db(Some("dup"), flags).expect("created");
+
+        // LMDB can't reserve space for duplicate values, so they're copied instead.
+        let mut txn = env.begin_rw_txn().expect("txn");
+        for value in ["foo", "bar"] {
+            let bytes = Value::Str(value).to_bytes().expect("bytes");
+            txn.reserve(&db, b"k", bytes.len(), LmdbWriteFlags::empty(), |buf| {
+                buf.copy_from_slice(&bytes)
+            })
+            .expect("reserved");
+        }
+        txn.commit().expect("committed");
+    }
+
+    let k = Rkv::new::<Lmdb>(root.path()).expect("rkv");
+    let store = k
+        .open_multi("dup", StoreOptions::default())
+        .expect("opened");
+    let reader = k.read().expect("reader");
+    let values: Vec<_> = store
+        .get(&reader, "k")
+        .expect("read")
+        .map(|pair| pair.expect("pair").1)
+        .collect();
+    assert_eq!(values, [Value::Str("bar"), Value::Str("foo")]);
+}
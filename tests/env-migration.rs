@@ -8,13 +8,14 @@
 // CONDITIONS OF ANY KIND, either express or implied. See the License for the
 // specific language governing permissions and limitations under the License.
 
-use std::{fs, path::Path};
+use std::{fs, ops::ControlFlow, path::Path};
 
 use tempfile::Builder;
 
 use rkv::{
     backend::{Lmdb, LmdbEnvironment, SafeMode, SafeModeEnvironment},
-    Manager, Migrator, Rkv, StoreOptions, Value,
+    migrator::{CancelToken, MergeStrategy, CHECKPOINT_STORE},
+    Manager, MigrateError, Migrator, OwnedValue, Rkv, StoreOptions, Value,
 };
 
 macro_rules! populate_store {
@@ -605,3 +606,315 @@ fn test_easy_migrator_from_manager_failed_migration() {
     test_easy_migrator_from_manager_failed_migration_1();
     test_easy_migrator_from_manager_failed_migration_2();
 }
+
+#[test]
+fn test_resumable_migrator_lmdb_to_safe() {
+    let root = Builder::new()
+        .prefix("test_resumable_migrator_lmdb_to_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let src_env = Rkv::new::<Lmdb>(root.path()).expect("new succeeded");
+    populate_store!(&src_env);
+    let numbers = src_env
+        .open_single("numbers", StoreOptions::create())
+        .expect("opened");
+    {
+        let mut writer = src_env.write().expect("writer");
+        for i in 0..1500u64 {
+            numbers
+                .put(&mut writer, i.to_be_bytes(), &Value::U64(i))
+                .expect("wrote");
+        }
+        writer.commit().expect("committed");
+    }
+
+    // A cancelled migration still copies one batch before stopping.
+    let dst_env = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    match Migrator::resumable_migrate_lmdb_to_safe_mode(&src_env, &dst_env, &cancel) {
+        Err(MigrateError::Cancelled) => (),
+        result => panic!("unexpected result {:?}", result),
+    }
+    {
+        let dst_numbers = dst_env
+            .open_single("numbers", StoreOptions::default())
+            .expect("opened");
+        let reader = dst_env.read().expect("reader");
+        let copied = dst_numbers.iter_start(&reader).expect("iter").count();
+        assert!(copied > 0 && copied < 1500);
+    }
+
+    // The partial copy can't be mistaken for an empty destination...
+    match Migrator::migrate_lmdb_to_safe_mode(&src_env, &dst_env) {
+        Err(MigrateError::DestinationNotEmpty) => (),
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    // ...but the migration can be resumed.
+    Migrator::resumable_migrate_lmdb_to_safe_mode(&src_env, &dst_env, &CancelToken::new())
+        .expect("migrated");
+    {
+        let store = dst_env
+            .open_single("store", StoreOptions::default())
+            .expect("opened");
+        let dst_numbers = dst_env
+            .open_single("numbers", StoreOptions::default())
+            .expect("opened");
+        let reader = dst_env.read().expect("reader");
+        assert_eq!(
+            store.get(&reader, "foo").expect("read"),
+            Some(Value::I64(1234))
+        );
+        let values: Vec<_> = dst_numbers
+            .iter_start(&reader)
+            .expect("iter")
+            .map(|entry| entry.expect("read").1)
+            .collect();
+        assert_eq!(values, (0..1500u64).map(Value::U64).collect::<Vec<_>>());
+    }
+
+    // The checkpoint doesn't outlive the migration.
+    assert!(!dst_env
+        .get_dbs()
+        .expect("dbs")
+        .contains(&Some(CHECKPOINT_STORE.to_string())));
+
+    // A completed migration can't be resumed again.
+    match Migrator::resumable_migrate_lmdb_to_safe_mode(&src_env, &dst_env, &CancelToken::new()) {
+        Err(MigrateError::DestinationNotEmpty) => (),
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+macro_rules! merge_into_populated {
+    ($prefix:expr, $strategy:expr) => {{
+        let root = Builder::new().prefix($prefix).tempdir().expect("tempdir");
+        fs::create_dir_all(root.path()).expect("dir created");
+
+        let src_env = Rkv::new::<Lmdb>(root.path()).expect("new succeeded");
+        populate_store!(&src_env);
+
+        let dst_env = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+        let store = dst_env
+            .open_single("store", StoreOptions::create())
+            .expect("opened");
+        {
+            let mut writer = dst_env.write().expect("writer");
+            store
+                .put(&mut writer, "foo", &Value::I64(1))
+                .expect("wrote");
+            store
+                .put(&mut writer, "qux", &Value::Bool(false))
+                .expect("wrote");
+            writer.commit().expect("committed");
+        }
+
+        let result = Migrator::migrate_lmdb_to_safe_mode_with($strategy, &src_env, &dst_env);
+        let reader = dst_env.read().expect("reader");
+        let entries: Vec<(String, OwnedValue)> = store
+            .iter_start(&reader)
+            .expect("iter")
+            .map(|entry| entry.expect("read"))
+            .map(|(key, value)| {
+                (
+                    String::from_utf8(key.to_vec()).unwrap(),
+                    OwnedValue::from(&value),
+                )
+            })
+            .collect();
+        (result, entries)
+    }};
+}
+
+#[test]
+fn test_migrator_merge_strategies() {
+    // The source value wins.
+    let (result, entries) =
+        merge_into_populated!("test_migrator_merge_overwrite", MergeStrategy::Overwrite);
+    result.expect("migrated");
+    assert_eq!(
+        entries,
+        vec![
+            ("bar".to_string(), OwnedValue::Bool(true)),
+            ("baz".to_string(), OwnedValue::Str("héllo, yöu".to_string())),
+            ("foo".to_string(), OwnedValue::I64(1234)),
+            ("qux".to_string(), OwnedValue::Bool(false)),
+        ]
+    );
+
+    // The destination value wins.
+    let (result, entries) = merge_into_populated!("test_migrator_merge_skip", MergeStrategy::Skip);
+    result.expect("migrated");
+    assert_eq!(
+        entries,
+        vec![
+            ("bar".to_string(), OwnedValue::Bool(true)),
+            ("baz".to_string(), OwnedValue::Str("héllo, yöu".to_string())),
+            ("foo".to_string(), OwnedValue::I64(1)),
+            ("qux".to_string(), OwnedValue::Bool(false)),
+        ]
+    );
+
+    // Nothing is written.
+    let (result, entries) = merge_into_populated!("test_migrator_merge_fail", MergeStrategy::Fail);
+    match result {
+        Err(MigrateError::KeyConflict) => (),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_eq!(
+        entries,
+        vec![
+            ("foo".to_string(), OwnedValue::I64(1)),
+            ("qux".to_string(), OwnedValue::Bool(false)),
+        ]
+    );
+}
+
+#[cfg(all(feature = "db-dup-sort", feature = "db-int-key"))]
+macro_rules! check_multi_and_integer_stores {
+    ($env:expr) => {{
+        let multi = $env
+            .open_multi("multi", StoreOptions::default())
+            .expect("opened");
+        let int = $env
+            .open_integer::<_, u32>("int", StoreOptions::default())
+            .expect("opened");
+        let reader = $env.read().expect("reader");
+        let values: Vec<Value> = multi
+            .get(&reader, "a")
+            .expect("read")
+            .map(|entry| entry.expect("read").1)
+            .collect();
+        assert_eq!(values, [Value::I64(1), Value::I64(2), Value::I64(3)]);
+        let values: Vec<Value> = multi
+            .get(&reader, "b")
+            .expect("read")
+            .map(|entry| entry.expect("read").1)
+            .collect();
+        assert_eq!(values, [Value::I64(4)]);
+        for key in [1, 256, 65536].iter() {
+            assert_eq!(
+                int.get(&reader, *key).expect("read"),
+                Some(Value::U64(u64::from(*key)))
+            );
+        }
+    }};
+}
+
+#[test]
+#[cfg(all(feature = "db-dup-sort", feature = "db-int-key"))]
+fn test_migrator_multi_and_integer_stores() {
+    let root = Builder::new()
+        .prefix("test_migrator_multi_and_integer_stores")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let root_back = Builder::new()
+        .prefix("test_migrator_multi_and_integer_stores_back")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root_back.path()).expect("dir created");
+
+    let src_env = Rkv::new::<Lmdb>(root.path()).expect("new succeeded");
+    {
+        let multi = src_env
+            .open_multi("multi", StoreOptions::create())
+            .expect("opened");
+        let int = src_env
+            .open_integer::<_, u32>("int", StoreOptions::create())
+            .expect("opened");
+        let mut writer = src_env.write().expect("writer");
+        for (key, value) in [("a", 1), ("a", 2), ("a", 3), ("b", 4)].iter() {
+            multi
+                .put(&mut writer, key, &Value::I64(*value))
+                .expect("wrote");
+        }
+        for key in [1, 256, 65536].iter() {
+            int.put(&mut writer, *key, &Value::U64(u64::from(*key)))
+                .expect("wrote");
+        }
+        writer.commit().expect("committed");
+    }
+
+    // Migrate to SafeMode, and back to LMDB, which doesn't iterate integer keys in the
+    // same order.
+    let dst_env = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    Migrator::migrate_lmdb_to_safe_mode(&src_env, &dst_env).expect("migrated");
+    check_multi_and_integer_stores!(dst_env);
+
+    let back_env = Rkv::new::<Lmdb>(root_back.path()).expect("new succeeded");
+    Migrator::migrate_safe_mode_to_lmdb(&dst_env, &back_env).expect("migrated");
+    check_multi_and_integer_stores!(back_env);
+
+    // Merging doesn't support duplicate values.
+    let merge_env = Rkv::new::<SafeMode>(root_back.path()).expect("new succeeded");
+    match Migrator::migrate_lmdb_to_safe_mode_with(MergeStrategy::Overwrite, &src_env, &merge_env) {
+        Err(MigrateError::DupSortUnsupported) => (),
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn test_migrator_progress() {
+    let root = Builder::new()
+        .prefix("test_migrator_progress")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let src_env = Rkv::new::<Lmdb>(root.path()).expect("new succeeded");
+    populate_store!(&src_env);
+
+    // The callback is invoked once per key.
+    {
+        let dst_env = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+        let mut reports = vec![];
+        Migrator::migrate_lmdb_to_safe_mode_with_progress(&src_env, &dst_env, |progress| {
+            reports.push((
+                progress.store.map(String::from),
+                progress.keys_migrated,
+                progress.total_keys,
+            ));
+            ControlFlow::Continue(())
+        })
+        .expect("migrated");
+        let store = Some("store".to_string());
+        assert_eq!(
+            reports,
+            [
+                (store.clone(), 1, Some(3)),
+                (store.clone(), 2, Some(3)),
+                (store, 3, Some(3)),
+            ]
+        );
+    }
+
+    // Breaking stops the migration, without committing the store being copied.
+    let dst_root = Builder::new()
+        .prefix("test_migrator_progress_break")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(dst_root.path()).expect("dir created");
+    let dst_env = Rkv::new::<SafeMode>(dst_root.path()).expect("new succeeded");
+    let mut calls = 0;
+    match Migrator::migrate_lmdb_to_safe_mode_with_progress(&src_env, &dst_env, |_| {
+        calls += 1;
+        if calls == 2 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }) {
+        Err(MigrateError::Cancelled) => (),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert_eq!(calls, 2);
+    let store = dst_env
+        .open_single("store", StoreOptions::default())
+        .expect("opened");
+    let reader = dst_env.read().expect("reader");
+    assert_eq!(store.iter_start(&reader).expect("iter").count(), 0);
+}
@@ -25,10 +25,11 @@ use tempfile::Builder;
 
 use rkv::{
     backend::{
-        BackendEnvironmentBuilder, SafeMode, SafeModeDatabase, SafeModeEnvironment,
+        BackendEnvironmentBuilder, Durability, SafeMode, SafeModeDatabase, SafeModeEnvironment,
         SafeModeRwTransaction,
     },
-    Rkv, SingleStore, StoreError, StoreOptions, Value, Writer,
+    CompositeKey, DataError, OwnedReader, Rkv, SingleStore, StoreError, StoreOptions, Value,
+    Writer,
 };
 
 fn check_rkv(k: &Rkv<SafeModeEnvironment>) {
@@ -1252,7 +1253,7 @@ fn test_store_multiple_thread_safe() {
         .into_iter()
         .map(|handle| handle.join().expect("value"))
         .sum();
-    assert_eq!(thread_sum, (0..num_threads).sum());
+    assert_eq!(thread_sum, (0..num_threads).sum::<u64>());
 }
 
 #[test]
@@ -1342,7 +1343,7 @@ fn test_use_value_as_key_safe() {
     // And you need to copy the values to an owned type so the Writer isn't
     // still being borrowed by a retrieved value when you try to borrow
     // the Writer again to modify another value.
-    let fields = vec![
+    let fields = [
         (
             rkv.open_single("name1", StoreOptions::create())
                 .expect("opened"),
@@ -1368,3 +1369,500 @@ fn test_use_value_as_key_safe() {
         writer.commit().expect("committed");
     }
 }
+
+#[test]
+fn test_single_store_stat_safe() {
+    let root = Builder::new()
+        .prefix("test_single_store_stat_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    let other = k
+        .open_single("other", StoreOptions::create())
+        .expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    for i in 0..10u64 {
+        sk.put(&mut writer, i.to_be_bytes(), &Value::U64(i))
+            .expect("wrote");
+    }
+    other
+        .put(&mut writer, "foo", &Value::Bool(true))
+        .expect("wrote");
+    // Uncommitted writes are visible to the writer.
+    assert_eq!(sk.stat(&writer).expect("stat").entries, 10);
+    writer.commit().expect("committed");
+
+    let reader = k.read().expect("reader");
+    let stat = sk.stat(&reader).expect("stat");
+    assert_eq!(stat.entries, 10);
+    assert!(stat.size > 0);
+    assert_eq!(stat.depth, None);
+    assert_eq!(other.stat(&reader).expect("stat").entries, 1);
+}
+
+#[test]
+fn test_load_ratio_safe() {
+    let root = Builder::new()
+        .prefix("test_load_ratio_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    // Without a map size there's no ceiling to measure against.
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    assert_eq!(k.load_ratio().expect("ratio"), None);
+    drop(k);
+
+    let mut builder = Rkv::environment_builder::<SafeMode>();
+    builder.set_map_size(1024 * 1024);
+    let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    let empty_ratio = k.load_ratio().expect("ratio").unwrap();
+    assert!(empty_ratio < 0.01);
+
+    // Fill the environment to roughly half of its ceiling.
+    let mut writer = k.write().expect("writer");
+    for i in 0..512u32 {
+        sk.put(&mut writer, i.to_be_bytes(), &Value::Blob(&[0; 1024]))
+            .expect("wrote");
+    }
+    writer.commit().expect("committed");
+    let ratio = k.load_ratio().expect("ratio").unwrap();
+    assert!((ratio - 0.5).abs() < 0.05, "ratio = {}", ratio);
+
+    // Raising the ceiling lowers the ratio; a size of 0 removes the ceiling.
+    k.set_map_size(2 * 1024 * 1024).expect("resized");
+    let new_ratio = k.load_ratio().expect("ratio").unwrap();
+    assert!((new_ratio - 0.25).abs() < 0.05, "ratio = {}", new_ratio);
+    k.set_map_size(0).expect("resized");
+    assert_eq!(k.load_ratio().expect("ratio"), None);
+}
+
+#[test]
+fn test_read_snapshot_safe() {
+    let root = Builder::new()
+        .prefix("test_read_snapshot_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, "foo", &Value::I64(1234))
+        .expect("wrote");
+    writer.commit().expect("committed");
+
+    let snapshot = k.read_snapshot().expect("snapshot");
+
+    // Commits after the snapshot was taken aren't visible through it.
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, "foo", &Value::I64(5678))
+        .expect("wrote");
+    sk.put(&mut writer, "bar", &Value::Bool(true))
+        .expect("wrote");
+    writer.commit().expect("committed");
+
+    // Snapshots don't borrow the environment, and don't block opening stores.
+    k.open_single("other", StoreOptions::create())
+        .expect("opened");
+    let handle = thread::spawn(move || {
+        assert_eq!(
+            sk.get(&snapshot, "foo").expect("read"),
+            Some(Value::I64(1234))
+        );
+        assert_eq!(sk.get(&snapshot, "bar").expect("read"), None);
+        let keys: Vec<_> = sk
+            .iter_start(&snapshot)
+            .expect("iter")
+            .map(|result| result.expect("entry").0.to_vec())
+            .collect();
+        assert_eq!(keys, vec![b"foo".to_vec()]);
+    });
+    handle.join().expect("joined");
+
+    let reader = k.read().expect("reader");
+    assert_eq!(
+        sk.get(&reader, "foo").expect("read"),
+        Some(Value::I64(5678))
+    );
+}
+
+#[test]
+fn test_owned_reader_shared_safe() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<OwnedReader>();
+
+    let root = Builder::new()
+        .prefix("test_owned_reader_shared_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    for i in 0..100u64 {
+        sk.put(&mut writer, i.to_be_bytes(), &Value::U64(i))
+            .expect("wrote");
+    }
+    writer.commit().expect("committed");
+
+    let reader = Arc::new(k.read_snapshot().expect("snapshot"));
+
+    // Later commits don't affect the threads' view.
+    let mut writer = k.write().expect("writer");
+    sk.clear(&mut writer).expect("cleared");
+    writer.commit().expect("committed");
+
+    let handles: Vec<_> = (0..4u64)
+        .map(|t| {
+            let reader = reader.clone();
+            thread::spawn(move || {
+                for i in (t..100).step_by(4) {
+                    assert_eq!(
+                        sk.get(&*reader, i.to_be_bytes()).expect("read"),
+                        Some(Value::U64(i))
+                    );
+                }
+                sk.iter_start(&*reader).expect("iter").count()
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().expect("joined"), 100);
+    }
+}
+
+#[test]
+fn test_durability_safe() {
+    let root = Builder::new()
+        .prefix("test_durability_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    {
+        let mut builder = Rkv::environment_builder::<SafeMode>();
+        builder.set_max_dbs(1);
+        builder.set_durability(Durability::NoSync);
+        let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+        let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+        // Bulk-load with a commit per batch, and only sync at the end.
+        for batch in 0..10u64 {
+            let mut writer = k.write().expect("writer");
+            for i in 0..100 {
+                let n = batch * 100 + i;
+                sk.put(&mut writer, n.to_be_bytes(), &Value::U64(n))
+                    .expect("wrote");
+            }
+            writer.commit().expect("committed");
+        }
+
+        // Nothing reaches the disk until then.
+        let unsynced = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+        assert!(unsynced.open_single("sk", StoreOptions::default()).is_err());
+
+        k.sync(true).expect("synced");
+    }
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    let sk = k
+        .open_single("sk", StoreOptions::default())
+        .expect("opened");
+    let reader = k.read().expect("reader");
+    assert_eq!(sk.stat(&reader).expect("stat").entries, 1000);
+    assert_eq!(
+        sk.get(&reader, 999u64.to_be_bytes()).expect("read"),
+        Some(Value::U64(999))
+    );
+}
+
+#[test]
+fn test_import_json_errors_safe() {
+    let root = Builder::new()
+        .prefix("test_import_json_errors_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+
+    let dump = concat!(
+        r#"{"store":"sk","key_b64":"YQ==","value":{"type":"I64","data":1}}"#,
+        "\n\n",
+        r#"{"store":"sk","key_b64":"Y!==","value":{"type":"I64","data":2}}"#,
+        "\n",
+    );
+    match k.import_json(dump.as_bytes()) {
+        Err(StoreError::DataError(DataError::InvalidRecord { line, reason })) => {
+            assert_eq!(line, 3);
+            assert!(reason.starts_with("key_b64"), "{}", reason);
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    let dump = r#"{"store":null,"key_b64":"YQ==","value":{"type":"Float","data":1.5}}"#;
+    assert!(matches!(
+        k.import_json(dump.as_bytes()),
+        Err(StoreError::DataError(DataError::UnknownTypeName(name))) if name == "Float"
+    ));
+
+    let dump = r#"{"store":null,"key_b64":"YQ==","value":{"type":"U64","data":-1}}"#;
+    assert!(matches!(
+        k.import_json(dump.as_bytes()),
+        Err(StoreError::DataError(DataError::InvalidRecord {
+            line: 1,
+            ..
+        }))
+    ));
+
+    // Nothing was imported from the malformed dumps.
+    assert!(k.get_dbs().expect("dbs").is_empty());
+
+    let dump = r#"{"store":null,"key_b64":"YQ==","value":{"type":"Str","data":"a"}}"#;
+    assert_eq!(k.import_json(dump.as_bytes()).expect("imported"), 1);
+    let store = k
+        .open_single(None, StoreOptions::default())
+        .expect("opened");
+    let reader = k.read().expect("reader");
+    assert_eq!(
+        store.get(&reader, "a").expect("read"),
+        Some(Value::Str("a"))
+    );
+}
+
+#[test]
+fn test_env_info_safe() {
+    let root = Builder::new()
+        .prefix("test_env_info_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let mut builder = Rkv::environment_builder::<SafeMode>();
+    builder.set_map_size(2 * 1024 * 1024);
+    let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    k.open_single("sk2", StoreOptions::create())
+        .expect("opened");
+
+    let empty = k.env_info().expect("info");
+    assert_eq!(empty.map_size, Some(2 * 1024 * 1024));
+    assert_eq!(empty.last_pgno, None);
+    assert_eq!(empty.max_readers, None);
+    assert_eq!(empty.num_readers, 0);
+    assert_eq!(empty.num_dbs, 2);
+
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, "foo", &Value::Blob(&[0; 1024]))
+        .expect("wrote");
+    writer.commit().expect("committed");
+
+    let _reader = k.read().expect("reader");
+    let info = k.env_info().expect("info");
+    assert!(info.size >= empty.size + 1024);
+    assert_eq!(info.num_readers, 1);
+}
+
+#[test]
+fn test_environment_exists_safe() {
+    let root = Builder::new()
+        .prefix("test_environment_exists_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    assert!(!Rkv::environment_exists::<SafeMode>(root.path()));
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("rkv");
+    // Nothing is written until the first commit.
+    assert!(!Rkv::environment_exists::<SafeMode>(root.path()));
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, "foo", &Value::I64(1234))
+        .expect("wrote");
+    writer.commit().expect("committed");
+    assert!(Rkv::environment_exists::<SafeMode>(root.path()));
+}
+
+#[test]
+fn test_freelist_safe() {
+    let root = Builder::new()
+        .prefix("test_freelist_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let k = Rkv::new::<SafeMode>(root.path()).expect("rkv");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    assert_eq!(k.freelist().expect("freelist"), 0);
+
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, "foo", &Value::I64(1234))
+        .expect("wrote");
+    writer.commit().expect("committed");
+    let mut writer = k.write().expect("writer");
+    sk.delete(&mut writer, "foo").expect("deleted");
+    writer.commit().expect("committed");
+    assert_eq!(k.freelist().expect("freelist"), 0);
+}
+
+#[test]
+fn test_compact_safe() {
+    let root = Builder::new()
+        .prefix("test_compact_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let path = root.path().join("data.safe.bin");
+    let k = Rkv::with_capacity::<SafeMode>(root.path(), 2).expect("rkv");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    let dropped = k
+        .open_single("dropped", StoreOptions::create())
+        .expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    for i in 0..100u64 {
+        let key = i.to_be_bytes();
+        sk.put(&mut writer, key, &Value::Blob(&[0; 128]))
+            .expect("wrote");
+        dropped
+            .put(&mut writer, key, &Value::U64(i))
+            .expect("wrote");
+    }
+    writer.commit().expect("committed");
+    let size = fs::metadata(&path).expect("metadata").len();
+
+    let mut writer = k.write().expect("writer");
+    for i in (0..100u64).step_by(2) {
+        sk.delete(&mut writer, i.to_be_bytes()).expect("deleted");
+    }
+    writer.commit().expect("committed");
+    k.drop_store("dropped").expect("dropped");
+
+    {
+        let _reader = k.read().expect("reader");
+        match k.compact() {
+            Err(StoreError::SafeModeError(_)) => {}
+            result => panic!("expected SafeModeError, got {:?}", result),
+        }
+    }
+    k.compact().expect("compacted");
+    assert!(fs::metadata(&path).expect("metadata").len() < size);
+
+    // Stores opened before compacting have to be opened again.
+    {
+        let reader = k.read().expect("reader");
+        assert!(sk.get(&reader, 1u64.to_be_bytes()).is_err());
+    }
+
+    let sk = k
+        .open_single("sk", StoreOptions::default())
+        .expect("opened");
+    let reader = k.read().expect("reader");
+    for i in 0..100u64 {
+        let expected = if i % 2 == 0 {
+            None
+        } else {
+            Some(Value::Blob(&[0; 128]))
+        };
+        assert_eq!(sk.get(&reader, i.to_be_bytes()).expect("read"), expected);
+    }
+    assert_eq!(k.get_dbs().expect("dbs"), vec![Some("sk".to_owned())]);
+}
+
+#[test]
+fn test_key_too_long_safe() {
+    let root = Builder::new()
+        .prefix("test_key_too_long_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let mut builder = Rkv::environment_builder::<SafeMode>();
+    builder.set_max_key_size(8);
+    let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, [0; 8], &Value::I64(1)).expect("wrote");
+    let key = CompositeKey::new().push("user").push("session");
+    assert!(matches!(
+        sk.put(&mut writer, &key, &Value::I64(2)),
+        Err(StoreError::KeyTooLong { len: 19, max: 8 })
+    ));
+    writer.commit().expect("committed");
+
+    // Keys aren't limited by default.
+    let root = Builder::new()
+        .prefix("test_key_too_long_safe")
+        .tempdir()
+        .expect("tempdir");
+    let k = Rkv::new::<SafeMode>(root.path()).expect("rkv");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, [0; 4096], &Value::I64(1))
+        .expect("wrote");
+}
+
+#[test]
+fn test_reader_info_safe() {
+    let root = Builder::new()
+        .prefix("test_reader_info_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let k = Rkv::new::<SafeMode>(root.path()).expect("rkv");
+    let _ = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+    assert_eq!(k.reader_info().expect("info").in_use, 0);
+    let reader = k.read().expect("reader");
+    assert_eq!(k.reader_info().expect("info").in_use, 1);
+    reader.abort();
+    assert_eq!(k.reader_info().expect("info").in_use, 0);
+    assert_eq!(k.check_stale_readers().expect("checked"), 0);
+    assert_eq!(k.reader_info().expect("info").max, None);
+}
+
+#[test]
+fn test_clear_all_safe() {
+    let root = Builder::new()
+        .prefix("test_clear_all_safe")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let k = Rkv::new::<SafeMode>(root.path()).expect("rkv");
+    let stores = [
+        k.create_default().expect("opened"),
+        k.open_single("a", StoreOptions::create()).expect("opened"),
+        k.open_single("b", StoreOptions::create()).expect("opened"),
+    ];
+    let mut writer = k.write().expect("writer");
+    for store in &stores {
+        store
+            .put(&mut writer, "foo", &Value::I64(1))
+            .expect("wrote");
+        store
+            .put(&mut writer, "bar", &Value::I64(2))
+            .expect("wrote");
+    }
+    writer.commit().expect("committed");
+    let mut names = k.get_dbs().expect("dbs");
+    names.sort();
+    assert_eq!(names, [None, Some("a".to_string()), Some("b".to_string())]);
+
+    k.clear_all().expect("cleared");
+    let reader = k.read().expect("reader");
+    for store in &stores {
+        assert_eq!(store.stat(&reader).expect("stat").entries, 0);
+    }
+    reader.abort();
+    let mut cleared = k.get_dbs().expect("dbs");
+    cleared.sort();
+    assert_eq!(cleared, names);
+}
@@ -0,0 +1,128 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+// The warnings are only logged by debug builds.
+#![cfg(debug_assertions)]
+
+use std::{cell::RefCell, fs, sync::Once};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use tempfile::Builder;
+
+#[cfg(feature = "lmdb")]
+use rkv::backend::Lmdb;
+use rkv::{
+    backend::{BackendEnvironmentBuilder, SafeMode},
+    Rkv, StoreOptions, Value,
+};
+
+thread_local! {
+    static WARNINGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Collects the warnings of each thread, so that tests running in parallel only see
+/// their own.
+struct CapturingLogger;
+
+impl Log for CapturingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            WARNINGS.with(|w| w.borrow_mut().push(record.args().to_string()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CapturingLogger = CapturingLogger;
+static INIT: Once = Once::new();
+
+fn take_warnings() -> Vec<String> {
+    INIT.call_once(|| {
+        log::set_logger(&LOGGER).expect("logger");
+        log::set_max_level(LevelFilter::Warn);
+    });
+    WARNINGS.with(|w| w.borrow_mut().drain(..).collect())
+}
+
+macro_rules! test_implicit_abort {
+    ($backend:ty, $prefix:expr) => {{
+        let root = Builder::new().prefix($prefix).tempdir().expect("tempdir");
+        fs::create_dir_all(root.path()).expect("dir created");
+
+        let mut builder = Rkv::environment_builder::<$backend>();
+        builder.set_warn_on_implicit_abort(true);
+        let k = Rkv::from_builder(root.path(), builder).expect("rkv");
+        let sk = k.open_single(None, StoreOptions::create()).expect("opened");
+        take_warnings();
+
+        // Dropping a writer with changes warns.
+        {
+            let mut writer = k.write().expect("writer");
+            sk.put(&mut writer, "foo", &Value::I64(1234))
+                .expect("wrote");
+        }
+        let warnings = take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("uncommitted changes"));
+
+        // Nothing was written.
+        let reader = k.read().expect("reader");
+        assert_eq!(sk.get(&reader, "foo").expect("read"), None);
+        reader.abort();
+
+        // Committing, aborting, or dropping a writer without changes doesn't warn.
+        let mut writer = k.write().expect("writer");
+        sk.put(&mut writer, "foo", &Value::I64(1234))
+            .expect("wrote");
+        writer.commit().expect("committed");
+        let mut writer = k.write().expect("writer");
+        sk.delete(&mut writer, "foo").expect("deleted");
+        writer.abort();
+        let writer = k.write().expect("writer");
+        sk.get(&writer, "foo").expect("read");
+        drop(writer);
+        assert!(take_warnings().is_empty());
+    }};
+}
+
+#[test]
+fn test_warn_on_implicit_abort_safe() {
+    test_implicit_abort!(SafeMode, "test_warn_on_implicit_abort_safe");
+}
+
+#[test]
+#[cfg(feature = "lmdb")]
+fn test_warn_on_implicit_abort() {
+    test_implicit_abort!(Lmdb, "test_warn_on_implicit_abort");
+}
+
+#[test]
+fn test_no_warning_by_default() {
+    let root = Builder::new()
+        .prefix("test_no_warning_by_default")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("rkv");
+    let sk = k.open_single(None, StoreOptions::create()).expect("opened");
+    take_warnings();
+
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, "foo", &Value::I64(1234))
+        .expect("wrote");
+    drop(writer);
+    assert!(take_warnings().is_empty());
+}
@@ -51,8 +51,8 @@ fn test_integer_keys() {
 
     // The integer module provides only the u32 integer key variant
     // of IntegerStore, so we can use it without further ado.
-    test_integer_keys!(s, std::u32::MIN);
-    test_integer_keys!(s, std::u32::MAX);
+    test_integer_keys!(s, u32::MIN);
+    test_integer_keys!(s, u32::MAX);
 
     // If you want to use another integer key variant, you need to implement
     // a newtype, implement PrimitiveInt, and implement or derive Serialize
@@ -67,22 +67,22 @@ fn test_integer_keys() {
     #[derive(Serialize)]
     struct I32(i32);
     impl PrimitiveInt for I32 {}
-    test_integer_keys!(t, I32(std::i32::MIN));
-    test_integer_keys!(t, I32(std::i32::MAX));
+    test_integer_keys!(t, I32(i32::MIN));
+    test_integer_keys!(t, I32(i32::MAX));
 
     let u = k.open_integer("s", StoreOptions::create()).expect("open");
 
     #[derive(Serialize)]
     struct U16(u16);
     impl PrimitiveInt for U16 {}
-    test_integer_keys!(u, U16(std::u16::MIN));
-    test_integer_keys!(u, U16(std::u16::MAX));
+    test_integer_keys!(u, U16(u16::MIN));
+    test_integer_keys!(u, U16(u16::MAX));
 
     let v = k.open_integer("s", StoreOptions::create()).expect("open");
 
     #[derive(Serialize)]
     struct U64(u64);
     impl PrimitiveInt for U64 {}
-    test_integer_keys!(v, U64(std::u64::MIN));
-    test_integer_keys!(v, U64(std::u64::MAX));
+    test_integer_keys!(v, U64(u64::MIN));
+    test_integer_keys!(v, U64(u64::MAX));
 }
@@ -0,0 +1,60 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+#![cfg(feature = "json-validation")]
+
+use std::fs;
+
+use serde::Deserialize;
+use tempfile::Builder;
+
+use rkv::{backend::SafeMode, DataError, Rkv, StoreError, StoreOptions, Value};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Config {
+    name: String,
+    retries: u32,
+}
+
+#[test]
+fn test_json_validation() {
+    let root = Builder::new()
+        .prefix("test_json_validation")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    sk.put(
+        &mut writer,
+        "config",
+        &Value::Json(r#"{"name": "sync", "retries": 3}"#),
+    )
+    .expect("wrote");
+    for malformed in ["", "{", r#"{"name": }"#, "[1, 2] 3"] {
+        match sk.put(&mut writer, "bad", &Value::Json(malformed)) {
+            Err(StoreError::DataError(DataError::InvalidJson(_))) => (),
+            result => panic!("expected InvalidJson for {:?}, got {:?}", malformed, result),
+        }
+    }
+    writer.commit().expect("committed");
+
+    let reader = k.read().expect("reader");
+    assert_eq!(sk.get(&reader, "bad").expect("read"), None);
+    let config = sk.get(&reader, "config").expect("read").expect("config");
+    assert_eq!(
+        config.as_json::<Config>().expect("parsed"),
+        Config {
+            name: "sync".to_string(),
+            retries: 3,
+        }
+    );
+}
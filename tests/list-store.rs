@@ -0,0 +1,129 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use std::fs;
+
+use tempfile::Builder;
+
+use rkv::{
+    backend::SafeMode, value::Type, DataError, OwnedValue, Rkv, StoreError, StoreOptions, Value,
+};
+
+#[test]
+fn test_list_store_push_pop() {
+    let root = Builder::new()
+        .prefix("test_list_store_push_pop")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    let s = k.open_list("s", StoreOptions::create()).expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    // A missing key is an empty list.
+    assert_eq!(s.get_list(&writer, "l").expect("read"), vec![]);
+    assert_eq!(s.pop(&mut writer, "l").expect("popped"), None);
+
+    s.push(&mut writer, "l", &Value::I64(1)).expect("pushed");
+    s.push(&mut writer, "l", &Value::Str("two"))
+        .expect("pushed");
+    s.push(&mut writer, "l", &Value::Bool(true))
+        .expect("pushed");
+    assert_eq!(
+        s.pop(&mut writer, "l").expect("popped"),
+        Some(OwnedValue::Bool(true))
+    );
+    writer.commit().expect("committed");
+
+    let reader = k.read().expect("reader");
+    assert_eq!(
+        s.get_list(&reader, "l").expect("read"),
+        vec![OwnedValue::I64(1), OwnedValue::Str("two".to_string())]
+    );
+    reader.abort();
+
+    // Popping the last value removes the key.
+    let mut writer = k.write().expect("writer");
+    assert_eq!(
+        s.pop(&mut writer, "l").expect("popped"),
+        Some(OwnedValue::Str("two".to_string()))
+    );
+    assert_eq!(
+        s.pop(&mut writer, "l").expect("popped"),
+        Some(OwnedValue::I64(1))
+    );
+    assert_eq!(s.pop(&mut writer, "l").expect("popped"), None);
+    writer.commit().expect("committed");
+
+    let plain = k.open_single("s", StoreOptions::default()).expect("opened");
+    let reader = k.read().expect("reader");
+    assert!(!plain.exists(&reader, "l").expect("read"));
+}
+
+#[test]
+fn test_list_store_persist() {
+    let root = Builder::new()
+        .prefix("test_list_store_persist")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    {
+        let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+        let s = k.open_list("s", StoreOptions::create()).expect("opened");
+        let mut writer = k.write().expect("writer");
+        s.put_list(&mut writer, "l", &[Value::U64(1), Value::Blob(b"two")])
+            .expect("wrote");
+        s.push(&mut writer, "l", &Value::F64(3.0.into()))
+            .expect("pushed");
+        writer.commit().expect("committed");
+    }
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    let s = k.open_list("s", StoreOptions::default()).expect("opened");
+    let reader = k.read().expect("reader");
+    assert_eq!(
+        s.get_list(&reader, "l").expect("read"),
+        vec![
+            OwnedValue::U64(1),
+            OwnedValue::Blob(b"two".to_vec()),
+            OwnedValue::F64(3.0)
+        ]
+    );
+}
+
+#[test]
+fn test_list_store_not_a_list() {
+    let root = Builder::new()
+        .prefix("test_list_store_not_a_list")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::new::<SafeMode>(root.path()).expect("new succeeded");
+    let s = k.open_list("s", StoreOptions::create()).expect("opened");
+    let plain = k.open_single("s", StoreOptions::default()).expect("opened");
+
+    let mut writer = k.write().expect("writer");
+    plain
+        .put(&mut writer, "v", &Value::I64(1234))
+        .expect("wrote");
+    s.push(&mut writer, "l", &Value::I64(1234)).expect("pushed");
+    assert!(matches!(
+        s.push(&mut writer, "v", &Value::I64(1)),
+        Err(StoreError::DataError(DataError::NotAList(Type::I64)))
+    ));
+    // Lists can't be misread as values.
+    assert!(matches!(
+        plain.get(&writer, "l"),
+        Err(StoreError::DataError(DataError::UnknownType(_)))
+    ));
+}
@@ -140,6 +140,153 @@ fn test_same_safe() {
     assert!(Arc::ptr_eq(&created_arc, &fetched_arc));
 }
 
+/// Test that the manager can be queried about paths that don't exist yet.
+#[test]
+fn test_get_missing_path_safe() {
+    type Manager = rkv::Manager<SafeModeEnvironment>;
+
+    let root = Builder::new()
+        .prefix("test_get_missing_path_safe")
+        .tempdir()
+        .expect("tempdir");
+    let p = root.path().join("missing").join("env");
+    assert!(Manager::singleton()
+        .read()
+        .unwrap()
+        .get(p.as_path())
+        .expect("success")
+        .is_none());
+
+    fs::create_dir_all(&p).expect("dir created");
+    let created_arc = Manager::singleton()
+        .write()
+        .unwrap()
+        .get_or_create(p.as_path(), Rkv::new::<SafeMode>)
+        .expect("created");
+    let fetched_arc = Manager::singleton()
+        .read()
+        .unwrap()
+        .get(p.as_path())
+        .expect("success")
+        .expect("existed");
+    assert!(Arc::ptr_eq(&created_arc, &fetched_arc));
+}
+
+/// Test that each backend has its own singleton, which only knows about the environments
+/// of that backend.
+#[cfg(feature = "lmdb")]
+#[test]
+fn test_singleton_per_backend() {
+    let root = Builder::new()
+        .prefix("test_singleton_per_backend")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let p = root.path();
+    let lmdb_arc = rkv::Manager::<LmdbEnvironment>::singleton()
+        .write()
+        .unwrap()
+        .get_or_create(p, Rkv::new::<Lmdb>)
+        .expect("created");
+    assert!(rkv::Manager::<SafeModeEnvironment>::singleton()
+        .read()
+        .unwrap()
+        .get(p)
+        .expect("success")
+        .is_none());
+
+    // The same path can be managed by both backends, e.g. to migrate it in place.
+    let safe_arc = rkv::Manager::<SafeModeEnvironment>::singleton()
+        .write()
+        .unwrap()
+        .get_or_create(p, Rkv::new::<SafeMode>)
+        .expect("created");
+    let fetched_lmdb_arc = rkv::Manager::<LmdbEnvironment>::singleton()
+        .read()
+        .unwrap()
+        .get(p)
+        .expect("success")
+        .expect("existed");
+    assert!(Arc::ptr_eq(&lmdb_arc, &fetched_lmdb_arc));
+    let fetched_safe_arc = rkv::Manager::<SafeModeEnvironment>::singleton()
+        .read()
+        .unwrap()
+        .get(p)
+        .expect("success")
+        .expect("existed");
+    assert!(Arc::ptr_eq(&safe_arc, &fetched_safe_arc));
+}
+
+/// Test that the environments each backend's manager hands out for the same path are
+/// distinct, and keep their data in their own files.
+#[cfg(feature = "lmdb")]
+#[test]
+fn test_same_path_per_backend() {
+    let root = Builder::new()
+        .prefix("test_same_path_per_backend")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let p = root.path();
+    let lmdb_arc = rkv::Manager::<LmdbEnvironment>::singleton()
+        .write()
+        .unwrap()
+        .get_or_create(p, Rkv::new::<Lmdb>)
+        .expect("created");
+    let safe_arc = rkv::Manager::<SafeModeEnvironment>::singleton()
+        .write()
+        .unwrap()
+        .get_or_create(p, Rkv::new::<SafeMode>)
+        .expect("created");
+
+    {
+        let lmdb = lmdb_arc.read().unwrap();
+        let store = lmdb
+            .open_single("s", StoreOptions::create())
+            .expect("opened");
+        let mut writer = lmdb.write().expect("writer");
+        store
+            .put(&mut writer, "foo", &Value::Str("lmdb"))
+            .expect("wrote");
+        writer.commit().expect("committed");
+    }
+    {
+        let safe = safe_arc.read().unwrap();
+        let store = safe
+            .open_single("s", StoreOptions::create())
+            .expect("opened");
+        let mut writer = safe.write().expect("writer");
+        store
+            .put(&mut writer, "foo", &Value::Str("safe"))
+            .expect("wrote");
+        writer.commit().expect("committed");
+    }
+
+    assert!(p.join("data.mdb").exists());
+    assert!(p.join("data.safe.bin").exists());
+
+    let lmdb = lmdb_arc.read().unwrap();
+    let store = lmdb
+        .open_single("s", StoreOptions::default())
+        .expect("opened");
+    let reader = lmdb.read().expect("reader");
+    assert_eq!(
+        store.get(&reader, "foo").expect("read"),
+        Some(Value::Str("lmdb"))
+    );
+    let safe = safe_arc.read().unwrap();
+    let store = safe
+        .open_single("s", StoreOptions::default())
+        .expect("opened");
+    let reader = safe.read().expect("reader");
+    assert_eq!(
+        store.get(&reader, "foo").expect("read"),
+        Some(Value::Str("safe"))
+    );
+}
+
 /// Test that the manager will return the same Rkv instance each time for each path.
 #[cfg(feature = "lmdb")]
 #[test]
@@ -432,7 +579,11 @@ fn test_safe_mode_corrupt_while_open_3() {
         .expect("opened");
 
     let reader = env.read().expect("reader");
-    assert_eq!(store.get(&reader, "foo").expect("read"), None, "Nothing to be read");
+    assert_eq!(
+        store.get(&reader, "foo").expect("read"),
+        None,
+        "Nothing to be read"
+    );
 
     // We can write.
     let mut writer = env.write().expect("writer");
@@ -0,0 +1,242 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+#[cfg(feature = "db-int-key")]
+use std::convert::TryFrom;
+use std::fs;
+
+use tempfile::Builder;
+
+#[cfg(feature = "db-int-key")]
+use rkv::{backend::BackendDatabaseFlags, DatabaseFlags, OwnedValue, StoreError};
+use rkv::{backend::SafeMode, Rkv, StoreOptions, Value};
+
+#[test]
+fn test_ordered_integer_keys() {
+    let root = Builder::new()
+        .prefix("test_ordered_integer_keys")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::with_capacity::<SafeMode>(root.path(), 8).expect("new succeeded");
+
+    macro_rules! test_integer_keys {
+        ($store:expr, $key:expr) => {{
+            let mut writer = k.write().expect("writer");
+
+            $store
+                .put(&mut writer, $key, &Value::Str("hello!"))
+                .expect("write");
+            assert_eq!(
+                $store.get(&writer, $key).expect("read"),
+                Some(Value::Str("hello!"))
+            );
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            assert_eq!(
+                $store.get(&reader, $key).expect("read"),
+                Some(Value::Str("hello!"))
+            );
+        }};
+    }
+
+    // Every primitive integer type can be used as a key. Each type needs its own
+    // store, since keys of different sizes don't compare meaningfully.
+
+    let s_u8 = k
+        .open_ordered_integer("u8", StoreOptions::create())
+        .expect("open");
+    test_integer_keys!(s_u8, u8::MIN);
+    test_integer_keys!(s_u8, u8::MAX);
+
+    let s_u16 = k
+        .open_ordered_integer("u16", StoreOptions::create())
+        .expect("open");
+    test_integer_keys!(s_u16, u16::MIN);
+    test_integer_keys!(s_u16, u16::MAX);
+
+    let s_u32 = k
+        .open_ordered_integer("u32", StoreOptions::create())
+        .expect("open");
+    test_integer_keys!(s_u32, u32::MIN);
+    test_integer_keys!(s_u32, u32::MAX);
+
+    let s_u64 = k
+        .open_ordered_integer("u64", StoreOptions::create())
+        .expect("open");
+    test_integer_keys!(s_u64, u64::MIN);
+    test_integer_keys!(s_u64, u64::MAX);
+
+    let s_i8 = k
+        .open_ordered_integer("i8", StoreOptions::create())
+        .expect("open");
+    test_integer_keys!(s_i8, i8::MIN);
+    test_integer_keys!(s_i8, i8::MAX);
+
+    let s_i16 = k
+        .open_ordered_integer("i16", StoreOptions::create())
+        .expect("open");
+    test_integer_keys!(s_i16, i16::MIN);
+    test_integer_keys!(s_i16, i16::MAX);
+
+    let s_i32 = k
+        .open_ordered_integer("i32", StoreOptions::create())
+        .expect("open");
+    test_integer_keys!(s_i32, i32::MIN);
+    test_integer_keys!(s_i32, i32::MAX);
+
+    let s_i64 = k
+        .open_ordered_integer("i64", StoreOptions::create())
+        .expect("open");
+    test_integer_keys!(s_i64, i64::MIN);
+    test_integer_keys!(s_i64, i64::MAX);
+}
+
+#[test]
+fn test_ordered_integer_keys_order() {
+    let root = Builder::new()
+        .prefix("test_ordered_integer_keys_order")
+        .tempdir()
+        .expect("tempdir");
+    fs::create_dir_all(root.path()).expect("dir created");
+
+    let k = Rkv::with_capacity::<SafeMode>(root.path(), 8).expect("new succeeded");
+
+    macro_rules! test_order {
+        ($name:expr, $t:ty, [$($key:expr),*]) => {{
+            let s = k
+                .open_ordered_integer::<_, $t>($name, StoreOptions::create())
+                .expect("open");
+            let sorted: [$t; 5] = [$($key),*];
+
+            let mut writer = k.write().expect("writer");
+            for key in sorted.iter().rev() {
+                s.put(&mut writer, *key, &Value::I64(*key as i64)).expect("write");
+            }
+            writer.commit().expect("committed");
+
+            let reader = k.read().expect("reader");
+            let keys = s
+                .iter_start(&reader)
+                .expect("iter")
+                .map(|result| result.expect("ok").0)
+                .collect::<Vec<$t>>();
+            assert_eq!(keys, sorted);
+
+            let keys = s
+                .iter_from(&reader, sorted[2])
+                .expect("iter")
+                .map(|result| result.expect("ok").0)
+                .collect::<Vec<$t>>();
+            assert_eq!(keys, &sorted[2..]);
+        }};
+    }
+
+    test_order!("u8", u8, [0, 1, 2, 128, u8::MAX]);
+    test_order!("u16", u16, [0, 1, 255, 256, u16::MAX]);
+    test_order!("u32", u32, [0, 1, 256, 65536, u32::MAX]);
+    test_order!("u64", u64, [0, 1, 256, 1 << 32, u64::MAX]);
+    test_order!("i8", i8, [i8::MIN, -1, 0, 1, i8::MAX]);
+    test_order!("i16", i16, [i16::MIN, -256, 0, 256, i16::MAX]);
+    test_order!("i32", i32, [i32::MIN, -65536, -1, 65536, i32::MAX]);
+    test_order!("i64", i64, [i64::MIN, -(1 << 32), 0, 1 << 32, i64::MAX]);
+}
+
+#[cfg(feature = "db-int-key")]
+macro_rules! test_migrate_integer_store {
+    ($backend:ty, $flags:ty, $prefix:expr) => {{
+        let root = Builder::new().prefix($prefix).tempdir().expect("tempdir");
+        fs::create_dir_all(root.path()).expect("dir created");
+
+        {
+            let k = Rkv::new::<$backend>(root.path()).expect("new succeeded");
+            let s = k
+                .open_integer::<_, u32>("s", StoreOptions::create())
+                .expect("open");
+            let mut writer = k.write().expect("writer");
+            for key in [65536, 256, 2, 1].iter() {
+                s.put(&mut writer, *key, &Value::U64(u64::from(*key)))
+                    .expect("write");
+            }
+            writer.commit().expect("committed");
+        }
+
+        let k = Rkv::new::<$backend>(root.path()).expect("new succeeded");
+
+        // The store keeps its format, and can't be opened as an ordered store.
+        let s = k
+            .open_integer::<_, u32>("s", StoreOptions::default())
+            .expect("open");
+        {
+            let reader = k.read().expect("reader");
+            assert_eq!(s.get(&reader, 256).expect("read"), Some(Value::U64(256)));
+        }
+        assert!(matches!(
+            k.open_ordered_integer::<_, u32>("s", StoreOptions::default()),
+            Err(StoreError::IncompatibleFlags)
+        ));
+
+        // Migrate it by copying its pairs into an ordered store.
+        let mut opts = StoreOptions::<$flags>::default();
+        BackendDatabaseFlags::set(&mut opts.flags, DatabaseFlags::INTEGER_KEY, true);
+        let legacy = k.open_single("s", opts).expect("open");
+        let ordered = k
+            .open_ordered_integer::<_, u32>("ordered", StoreOptions::create())
+            .expect("open");
+        {
+            let mut writer = k.write().expect("writer");
+            let pairs = legacy
+                .iter_start(&writer)
+                .expect("iter")
+                .map(|pair| {
+                    let (key, value) = pair.expect("pair");
+                    let key = u32::from_le_bytes(<[u8; 4]>::try_from(key).expect("u32"));
+                    (key, OwnedValue::from(&value))
+                })
+                .collect::<Vec<_>>();
+            for (key, value) in pairs.iter() {
+                ordered
+                    .put(&mut writer, *key, &Value::from(value))
+                    .expect("write");
+            }
+            writer.commit().expect("committed");
+        }
+
+        let reader = k.read().expect("reader");
+        let keys = ordered
+            .iter_start(&reader)
+            .expect("iter")
+            .map(|pair| pair.expect("pair").0)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec![1, 2, 256, 65536]);
+    }};
+}
+
+#[test]
+#[cfg(feature = "db-int-key")]
+fn test_migrate_integer_store_safe() {
+    use rkv::backend::SafeModeDatabaseFlags;
+
+    test_migrate_integer_store!(
+        SafeMode,
+        SafeModeDatabaseFlags,
+        "test_migrate_integer_store_safe"
+    );
+}
+
+#[test]
+#[cfg(all(feature = "db-int-key", feature = "lmdb"))]
+fn test_migrate_integer_store_lmdb() {
+    use rkv::backend::{Lmdb, LmdbDatabaseFlags};
+
+    test_migrate_integer_store!(Lmdb, LmdbDatabaseFlags, "test_migrate_integer_store_lmdb");
+}
@@ -0,0 +1,104 @@
+// Copyright 2018-2019 Mozilla
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+#![cfg(feature = "encryption")]
+
+use std::{fs, path::Path};
+
+use tempfile::Builder;
+
+use rkv::{
+    backend::{BackendEnvironmentBuilder, RecoveryStrategy, SafeMode, SafeModeEnvironment},
+    Rkv, StoreError, StoreOptions, Value,
+};
+
+const KEY: [u8; 32] = [7; 32];
+
+fn open(path: &Path, key: &[u8; 32]) -> Result<Rkv<SafeModeEnvironment>, StoreError> {
+    let mut builder = Rkv::environment_builder::<SafeMode>();
+    builder.set_encryption_key(key);
+    Rkv::from_builder(path, builder)
+}
+
+fn write_secret(path: &Path) {
+    let k = open(path, &KEY).expect("opened");
+    let sk = k.open_single("sk", StoreOptions::create()).expect("opened");
+    let mut writer = k.write().expect("writer");
+    sk.put(&mut writer, "secret", &Value::Str("hunter2"))
+        .expect("wrote");
+    writer.commit().expect("committed");
+}
+
+#[test]
+fn test_encryption_roundtrip() {
+    let root = Builder::new()
+        .prefix("test_encryption_roundtrip")
+        .tempdir()
+        .expect("tempdir");
+    write_secret(root.path());
+
+    let bytes = fs::read(root.path().join("data.safe.bin")).expect("read");
+    assert!(!bytes.windows(7).any(|w| w == b"hunter2"));
+
+    let k = open(root.path(), &KEY).expect("opened");
+    let sk = k
+        .open_single("sk", StoreOptions::default())
+        .expect("opened");
+    let reader = k.read().expect("reader");
+    assert_eq!(
+        sk.get(&reader, "secret").expect("read"),
+        Some(Value::Str("hunter2"))
+    );
+}
+
+#[test]
+fn test_encryption_wrong_key() {
+    let root = Builder::new()
+        .prefix("test_encryption_wrong_key")
+        .tempdir()
+        .expect("tempdir");
+    write_secret(root.path());
+
+    assert!(matches!(
+        open(root.path(), &[8; 32]),
+        Err(StoreError::DecryptionFailed)
+    ));
+    // Without a key, the encrypted file isn't a valid database either.
+    assert!(matches!(
+        Rkv::new::<SafeMode>(root.path()),
+        Err(StoreError::FileInvalid)
+    ));
+}
+
+#[test]
+fn test_encryption_tampered() {
+    let root = Builder::new()
+        .prefix("test_encryption_tampered")
+        .tempdir()
+        .expect("tempdir");
+    write_secret(root.path());
+
+    let path = root.path().join("data.safe.bin");
+    let mut bytes = fs::read(&path).expect("read");
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0x01;
+    fs::write(&path, &bytes).expect("written");
+    assert!(matches!(
+        open(root.path(), &KEY),
+        Err(StoreError::DecryptionFailed)
+    ));
+
+    // The corruption recovery strategy applies to tampered files too.
+    let mut builder = Rkv::environment_builder::<SafeMode>();
+    builder.set_encryption_key(&KEY);
+    builder.set_corruption_recovery_strategy(RecoveryStrategy::Rename);
+    let k = Rkv::from_builder(root.path(), builder).expect("opened");
+    assert!(k.get_dbs().expect("dbs").is_empty());
+    assert!(root.path().join("data.safe.bin.corrupt").exists());
+}
@@ -18,19 +18,19 @@ use rkv::{
     Readable, Rkv, StoreOptions, Value, Writer,
 };
 
-/// Consider a struct like this:
-/// struct Sample {
-///     id: u64,
-///     value: String,
-///     date: String,
-/// }
-/// We would like to index all of the fields so that we can search for the struct not only
-/// by ID but also by value and date.  When we index the fields individually in their own
-/// tables, it is important that we run all operations within a single transaction to
-/// ensure coherence of the indices.
-/// This test features helper functions for reading and writing the parts of the struct.
-/// Note that the reader functions take `Readable` because they might run within a Read
-/// Transaction or a Write Transaction.  The test demonstrates fetching values via both.
+// Consider a struct like this:
+// struct Sample {
+//     id: u64,
+//     value: String,
+//     date: String,
+// }
+// We would like to index all of the fields so that we can search for the struct not only
+// by ID but also by value and date.  When we index the fields individually in their own
+// tables, it is important that we run all operations within a single transaction to
+// ensure coherence of the indices.
+// This test features helper functions for reading and writing the parts of the struct.
+// Note that the reader functions take `Readable` because they might run within a Read
+// Transaction or a Write Transaction.  The test demonstrates fetching values via both.
 
 type SingleStore = rkv::SingleStore<SafeModeDatabase>;
 type MultiStore = rkv::MultiStore<SafeModeDatabase>;